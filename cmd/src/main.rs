@@ -0,0 +1,1011 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::process;
+
+use chrono::prelude::*;
+use chrono::Duration;
+
+use rtimelog::activity::{
+    check_duration_hints, format_hm, get_blocks, get_blocks_with_leaving, Activities, RoundingMode,
+    TimeWindow,
+};
+use rtimelog::clipboard;
+use rtimelog::clock;
+use rtimelog::config::{parse_category_map, Config};
+use rtimelog::editor::run_editor;
+use rtimelog::export::{
+    write_activities_json, write_csv, write_gtimelog_week, write_json, write_tsv,
+};
+use rtimelog::hook::run_post_add_hook;
+use rtimelog::pattern::Pattern;
+use rtimelog::report::{
+    parse_amount, parse_category_rates, render_svg, time_between, ContextSwitches, Invoice,
+    MeetingLoad, MonthCalendar, Presence, ReportPeriod, Rhythm, Singleton, Stats, TaskTotals,
+    WeekGrid,
+};
+use rtimelog::store::{diff_lines, Entry, StoreError, Timelog};
+
+const USAGE: &str = "Usage: rtimelog-cmd [--file PATH|--profile NAME] COMMAND [OPTIONS]
+
+Commands:
+  add [--ago MINUTES] TASK
+        add an entry for TASK which was just finished, or MINUTES ago
+  between FROM_TASK TO_TASK [--date DATE]
+        time from the first entry with FROM_TASK to the next one with TO_TASK,
+        like from arrived to the first code (default: today)
+  calendar [--month YYYY-MM]
+        work hours per day in a calendar of the month (default: this month)
+  check [--fix] [--fix-separators] [--hints [--hint-tolerance MINUTES]]
+        report all problems in the timelog, and fail if there are any;
+        with --fix, first collapse repeated spaces in tasks; with
+        --fix-separators, first put exactly one empty line between days; with
+        --hints, also report blocks whose duration differs from their [~45m]
+        duration hint by more than the tolerance (default: 10 minutes)
+  config
+        show the effective settings and where they come from
+  continue [--task-of DATE]
+        add an entry with the task of the most recent entry, or of the last
+        entry on DATE
+  edit
+        open the timelog in $VISUAL or $EDITOR
+  export [--from DATE] [--to DATE] [--date DATE]
+         [--format tsv|csv|json [--json-pretty]|svg]
+  export --format gtimelog-week [--date DATE] [--name NAME] [--email EMAIL]
+        one line per activity block with date, start, stop, duration in
+        minutes, and task (default: today); with --format csv, one line per
+        entry, with empty start and duration for the first entry of a day;
+        with --format json, an array of objects with these keys, indented
+        with --json-pretty; with --format svg, a timeline image of a single
+        day; with --format gtimelog-week, gtimelog's weekly report of the week
+        containing DATE
+  invoice [--from DATE] [--to DATE] [--rate AMOUNT]
+          [--category-rates CATEGORY=AMOUNT,...] [--currency CURRENCY]
+          [--task-regex PATTERN] [--round MINUTES [--round-mode nearest|up|down]]
+        work per category multiplied by its hourly rate from --category-rates,
+        or by --rate, and the total amount (default: this month, in EUR); with
+        --task-regex, only bill tasks which match PATTERN; with --round, round
+        each activity to a multiple of MINUTES first (default mode: up)
+  merge OTHER_FILE
+        add the entries of OTHER_FILE, e.g. from a different machine; exact
+        duplicates are skipped
+  rename [--substring] FROM TO
+        change the task of all entries which are exactly FROM to TO, e.g. to
+        fix typos; with --substring, replace FROM anywhere in the tasks
+  repair
+        remove a truncated last line, like after a crash, if it is the only
+        line which can't be parsed; the previous file is kept as .bak
+  report [--from DATE] [--to DATE] [--ago DAYS]
+         [--sprint-start DATE [--sprint-days DAYS]] [[--year YEAR] --week N]
+         [--dates DATE,DATE,...]
+         [--task-regex PATTERN]
+         [--after HH:MM] [--before HH:MM] [--prorate] [--clipboard]
+         [--round MINUTES [--round-mode nearest|up|down]]
+         [--by-category [--merge-categories FROM=TO,...]]
+         [--by-tag [--split-tags]] [--tag-summary] [--by-regex-group PATTERN]
+         [--no-merge]
+         [--work-minutes|--slack-minutes|--hours-only|--meetings]
+         [--format text|json [--json-pretty]]
+        activities and total work (default: today, or the
+        default_report_period setting, or DAYS days ago, like --ago 1 for
+        yesterday, or the DAYS (default: 14) days from --sprint-start, or ISO
+        week N of YEAR (default: this year), or only the given --dates; with
+        --task-regex, only tasks which match PATTERN (. \\d * + ^ $, or any
+        regex with the regex feature) are counted; with --after/--before, only
+        blocks which end in that time of day range, or with --prorate only
+        their part inside the range; with --clipboard, copy the report instead
+        of printing it; with --round, round each activity to a multiple of
+        MINUTES (default mode: up), for billing; with --by-category, show the
+        work per category, counting the FROM categories as TO; with --by-tag,
+        show the work per '-- tag1 tag2' tag, with --split-tags sharing it
+        equally between the tags of an activity; with --tag-summary, the work
+        per tag with the activities under it (default: this week); with
+        --by-regex-group, show the work per text of the (group) in PATTERN,
+        like '#(\\d+)' for tickets; with --no-merge, list every block
+        separately instead of adding up repeated tasks; with --work-minutes or
+        --slack-minutes, only print that total as a number, for scripts; with
+        --hours-only, a grid of work hours per category and weekday (default:
+        this week); with --meetings, meeting and focused work time per day;
+        with --format json, the activities and totals in seconds as JSON, for
+        scripts
+  rhythm [--from DATE] [--to DATE] [--min-day-work MINUTES]
+        average and median start and end of the work days
+        (default: last 4 weeks); with --min-day-work, ignore days with less
+        work
+  stats [--from DATE] [--to DATE] [--exclude-today] [--min-day-work MINUTES]
+        work done per day, and average (default: last 4 weeks); with
+        --min-day-work, days with less work are not counted in the average
+  switches [--from DATE] [--to DATE]
+        how often the category of consecutive work blocks changed, and the
+        categories which were switched into most often (default: today)
+  tasks [--from DATE] [--to DATE] [--category]
+        every distinct task with its total time, biggest first (default: whole
+        timelog); with --category, the work per category instead
+  typos [--from DATE] [--to DATE]
+        tasks which occur only once, which are often typos, with the most
+        similar frequent task (default: last 4 weeks)
+  undo [--count N]
+        remove the most recent entry, or the last N entries
+  verify-roundtrip
+        check that saving the timelog would not change the file, and show the
+        difference if it would";
+
+// remove "--name value" from args and return the value
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, Box<dyn Error>> {
+    match args.iter().position(|a| a == name) {
+        Some(i) => {
+            if i + 1 >= args.len() {
+                return Err(format!("{name} needs an argument").into());
+            }
+            let value = args.remove(i + 1);
+            args.remove(i);
+            Ok(Some(value))
+        }
+        None => Ok(None),
+    }
+}
+
+fn take_date(args: &mut Vec<String>, name: &str) -> Result<Option<NaiveDate>, Box<dyn Error>> {
+    match take_option(args, name)? {
+        Some(s) => match NaiveDate::parse_from_str(&s, "%Y-%m-%d") {
+            Ok(d) => Ok(Some(d)),
+            Err(_) => Err(format!("Invalid date for {name}: {s}").into()),
+        },
+        None => Ok(None),
+    }
+}
+
+fn take_time(args: &mut Vec<String>, name: &str) -> Result<Option<NaiveTime>, Box<dyn Error>> {
+    match take_option(args, name)? {
+        Some(s) => match NaiveTime::parse_from_str(&s, "%H:%M") {
+            Ok(t) => Ok(Some(t)),
+            Err(_) => Err(format!("Invalid time for {name}: {s}").into()),
+        },
+        None => Ok(None),
+    }
+}
+
+// --from and --to dates; default to the given period until today
+fn take_range(
+    args: &mut Vec<String>,
+    default: Duration,
+) -> Result<(NaiveDate, NaiveDate), Box<dyn Error>> {
+    let to = take_date(args, "--to")?.unwrap_or(clock::today());
+    let from = take_date(args, "--from")?.unwrap_or(to - default);
+    Ok((from, to))
+}
+
+// remove "--name" from args and return whether it was present
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    match args.iter().position(|a| a == name) {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    }
+}
+
+// all options must have been consumed
+fn check_no_args(args: &[String]) -> Result<(), Box<dyn Error>> {
+    match args.first() {
+        Some(a) => Err(format!("Unexpected argument: {a}").into()),
+        None => Ok(()),
+    }
+}
+
+fn load(file: Option<PathBuf>) -> Result<Timelog, StoreError> {
+    let timelog = match file {
+        Some(path) => Timelog::new_from_file(&path)?,
+        None => Timelog::new_from_default_file()?,
+    };
+    Ok(timelog.with_base_file(Config::new_from_default_file().base_file.as_ref()))
+}
+
+fn check(file: Option<PathBuf>, mut args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let fix = take_flag(&mut args, "--fix");
+    let fix_separators = take_flag(&mut args, "--fix-separators");
+    let hints = take_flag(&mut args, "--hints");
+    let tolerance = match take_option(&mut args, "--hint-tolerance")? {
+        Some(m) => Duration::minutes(
+            m.parse()
+                .map_err(|_| format!("Invalid --hint-tolerance {m}, expected minutes"))?,
+        ),
+        None => Duration::minutes(10),
+    };
+    check_no_args(&args)?;
+    let path = file.map_or_else(Timelog::get_default_file, Ok)?;
+
+    if fix {
+        let mut timelog = Timelog::new_from_file(&path)?;
+        let changed = timelog.normalize_tasks();
+        if changed > 0 {
+            timelog.save()?;
+            println!("Fixed spacing in {changed} entries");
+        }
+    }
+
+    if fix_separators {
+        let raw = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        match Timelog::reformat(&raw) {
+            Ok(Some(formatted)) => {
+                fs::write(&path, formatted)
+                    .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+                println!("Fixed day separators");
+            }
+            Ok(None) => (),
+            // these get reported below
+            Err(_) => println!("Not fixing day separators, as some lines can't be parsed"),
+        }
+    }
+
+    let raw = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    let problems = Timelog::lint(&raw);
+    for p in &problems {
+        println!("{}: {}", path.display(), p);
+    }
+    let mut count = problems.len();
+
+    if hints {
+        // a file which goes back in time can't be read, but lint reported that already
+        let timelog = Timelog::new_from_file(&path).unwrap_or_default();
+        for b in check_duration_hints(
+            timelog.get_time_range(NaiveDateTime::MIN, NaiveDateTime::MAX),
+            tolerance,
+        ) {
+            println!(
+                "{}: {}: {} took {}, but hint is {}",
+                path.display(),
+                b.stop.format("%F %H:%M"),
+                b.task,
+                format_hm(&b.duration()),
+                format_hm(&b.hint.unwrap())
+            );
+            count += 1;
+        }
+    }
+
+    match count {
+        0 => Ok(()),
+        n => Err(format!("{n} problem(s) found").into()),
+    }
+}
+
+fn config(args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    check_no_args(&args)?;
+    match Config::default_file() {
+        Some(path) => println!("# config file: {}", path.display()),
+        None => println!("# no config file, home directory is unknown"),
+    }
+    print!("{}", Config::new_from_default_file().describe());
+    Ok(())
+}
+
+fn add(mut timelog: Timelog, mut args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let ago = match take_option(&mut args, "--ago")? {
+        Some(n) => Some(
+            n.parse::<u32>()
+                .map_err(|_| format!("Invalid --ago {n}, expected number of minutes"))?,
+        ),
+        None => None,
+    };
+    if args.is_empty() {
+        return Err("Missing task".into());
+    }
+    let task = args.join(" ");
+
+    let entry = match ago {
+        Some(minutes) => timelog.add_minutes_ago(task, minutes)?.clone(),
+        None => timelog.add(task).clone(),
+    };
+    timelog.save()?;
+    if let Some(hook) = Config::new_from_default_file().post_add_hook {
+        run_post_add_hook(&hook, &entry);
+    }
+    Ok(())
+}
+
+fn calendar(timelog: &Timelog, mut args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let first = match take_option(&mut args, "--month")? {
+        Some(m) => NaiveDate::parse_from_str(&format!("{m}-01"), "%Y-%m-%d")
+            .map_err(|_| format!("Invalid --month {m}, expected YYYY-MM"))?,
+        None => clock::today().with_day(1).unwrap(),
+    };
+    check_no_args(&args)?;
+
+    // entries after the month are ignored
+    print!(
+        "{}",
+        MonthCalendar::new_from_entries(
+            timelog.get_period(&first, 31),
+            first,
+            &Config::new_from_default_file()
+        )
+    );
+    Ok(())
+}
+
+fn continue_task(mut timelog: Timelog, mut args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let day = take_date(&mut args, "--task-of")?;
+    check_no_args(&args)?;
+
+    let task = match timelog.get_last_task(day.as_ref()) {
+        Some(t) => t.to_string(),
+        None => match day {
+            Some(d) => return Err(format!("No entries on {d}").into()),
+            None => return Err("No entries yet".into()),
+        },
+    };
+    println!("Continuing: {task}");
+    let entry = timelog.add(task).clone();
+    timelog.save()?;
+    if let Some(hook) = Config::new_from_default_file().post_add_hook {
+        run_post_add_hook(&hook, &entry);
+    }
+    Ok(())
+}
+
+fn export(timelog: &Timelog, mut args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let (mut from, mut to) = take_range(&mut args, Duration::days(0))?;
+    if let Some(date) = take_date(&mut args, "--date")? {
+        from = date;
+        to = date;
+    }
+    let format = take_option(&mut args, "--format")?.unwrap_or_else(|| "tsv".to_string());
+    let name = take_option(&mut args, "--name")?.unwrap_or_else(|| "Anonymous".to_string());
+    let email =
+        take_option(&mut args, "--email")?.unwrap_or_else(|| "activity@example.com".to_string());
+    let json_pretty = take_flag(&mut args, "--json-pretty");
+    check_no_args(&args)?;
+
+    if format == "gtimelog-week" {
+        let monday = to - Duration::days(to.weekday().num_days_from_monday().into());
+        let entries = timelog.get_period(&monday, 7);
+        write_gtimelog_week(
+            entries,
+            monday.iso_week().week(),
+            &name,
+            &email,
+            &Config::new_from_default_file(),
+            &mut io::stdout(),
+        )?;
+        return Ok(());
+    }
+
+    let entries = timelog.get_date_range(&from, &to);
+    match format.as_str() {
+        "tsv" => write_tsv(entries, &mut io::stdout())?,
+        "json" => write_json(entries, json_pretty, &mut io::stdout())?,
+        "csv" => write_csv(entries, &mut io::stdout())?,
+        "svg" if from != to => return Err("SVG export needs a single day, use --date".into()),
+        "svg" => print!(
+            "{}",
+            render_svg(&get_blocks(entries), &Config::new_from_default_file())
+        ),
+        f => {
+            return Err(format!(
+                "Unknown export format {f}, supported: tsv, csv, json, svg, gtimelog-week"
+            )
+            .into())
+        }
+    }
+    Ok(())
+}
+
+fn invoice(timelog: &Timelog, mut args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let today = clock::today();
+    let (from, to) = take_range(&mut args, today - today.with_day(1).unwrap())?;
+    let rate = match take_option(&mut args, "--rate")? {
+        Some(r) => Some(parse_amount(&r).ok_or(format!(
+            "Invalid --rate {r}, expected an amount like 80 or 80.50"
+        ))?),
+        None => None,
+    };
+    let category_rates = match take_option(&mut args, "--category-rates")? {
+        Some(spec) => parse_category_rates(&spec)?,
+        None => HashMap::new(),
+    };
+    if rate.is_none() && category_rates.is_empty() {
+        return Err("invoice needs --rate or --category-rates".into());
+    }
+    let currency = take_option(&mut args, "--currency")?.unwrap_or("EUR".to_string());
+    let pattern = match take_option(&mut args, "--task-regex")? {
+        Some(p) => Some(Pattern::new(&p)?),
+        None => None,
+    };
+    let round = match take_option(&mut args, "--round")? {
+        Some(m) => Some(Duration::minutes(
+            m.parse::<i64>()
+                .ok()
+                .filter(|m| *m > 0)
+                .ok_or(format!("Invalid --round {m}, expected minutes"))?,
+        )),
+        None => None,
+    };
+    let round_mode: RoundingMode = match take_option(&mut args, "--round-mode")? {
+        Some(mode) if round.is_some() => mode
+            .parse()
+            .map_err(|e| format!("Invalid --round-mode: {e}"))?,
+        Some(_) => return Err("--round-mode needs --round".into()),
+        None => RoundingMode::default(),
+    };
+    check_no_args(&args)?;
+
+    let config = Config::new_from_default_file();
+    let blocks = get_blocks_with_leaving(
+        timelog.get_date_range(&from, &to),
+        config.leaving_marker.as_deref(),
+    )
+    .into_iter()
+    .filter(|b| pattern.as_ref().is_none_or(|p| p.is_match(&b.task)));
+    let mut activities = Activities::new_from_blocks(blocks).with_config(&config);
+    if let Some(increment) = round {
+        activities.round(increment, round_mode);
+    }
+    let invoice = Invoice::new(&activities, rate, &category_rates, &currency)?;
+    println!(
+        "Work from {} to {}:",
+        config.format_date(&from),
+        config.format_date(&to)
+    );
+    print!("{invoice}");
+    Ok(())
+}
+
+fn merge(mut timelog: Timelog, mut args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    if args.len() != 1 {
+        return Err("merge needs exactly one file".into());
+    }
+    let other = PathBuf::from(args.remove(0));
+    if !other.exists() {
+        return Err(format!("{} does not exist", other.display()).into());
+    }
+
+    let added = timelog.merge(Timelog::new_from_file(&other)?);
+    if added > 0 {
+        timelog.save()?;
+    }
+    println!("Added {added} entries from {}", other.display());
+    Ok(())
+}
+
+fn rename(mut timelog: Timelog, mut args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let substring = take_flag(&mut args, "--substring");
+    if args.len() != 2 {
+        return Err("rename needs exactly one FROM and one TO task".into());
+    }
+    let to = args.remove(1);
+    let from = args.remove(0);
+
+    let changed = if substring {
+        timelog.rename_task_substring(&from, &to)
+    } else {
+        timelog.rename_task(&from, &to)
+    };
+    if changed > 0 {
+        timelog.save()?;
+    }
+    println!("Renamed {changed} entries");
+    Ok(())
+}
+
+fn repair(file: Option<PathBuf>, args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    check_no_args(&args)?;
+    let path = file.map_or_else(Timelog::get_default_file, Ok)?;
+    let raw = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    match Timelog::repair_truncated(&raw) {
+        Ok(Some((repaired, problem))) => {
+            let mut backup = path.clone().into_os_string();
+            backup.push(".bak");
+            let backup = PathBuf::from(backup);
+            fs::copy(&path, &backup)
+                .map_err(|e| format!("Failed to back up to {}: {}", backup.display(), e))?;
+            fs::write(&path, repaired)
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            println!(
+                "Removed truncated {problem}\nThe previous file is in {}",
+                backup.display()
+            );
+            Ok(())
+        }
+        Ok(None) => {
+            println!("Nothing to repair");
+            Ok(())
+        }
+        Err(problems) => {
+            for p in &problems {
+                println!("{}: {}", path.display(), p);
+            }
+            Err(
+                "Not repairing, as these are not just a truncated last line; \
+                 fix them with rtimelog-cmd edit"
+                    .into(),
+            )
+        }
+    }
+}
+
+fn edit(file: Option<PathBuf>, args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    check_no_args(&args)?;
+    let path = file.map_or_else(Timelog::get_default_file, Ok)?;
+    let status = run_editor(&path)
+        .map_err(|e| format!("Failed to run editor on {}: {}", path.display(), e))?;
+    if !status.success() {
+        return Err(format!("Editor failed: {status}").into());
+    }
+    Ok(())
+}
+
+fn report(timelog: &Timelog, mut args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let mut config = Config::new_from_default_file();
+    let hours_only = take_flag(&mut args, "--hours-only");
+    let tag_summary = take_flag(&mut args, "--tag-summary");
+    let period = if hours_only || tag_summary {
+        ReportPeriod::Week
+    } else {
+        config.default_report_period
+    };
+    let today = clock::today();
+    let (mut from, mut to) = take_range(&mut args, today - period.start(today))?;
+    if let Some(ago) = take_option(&mut args, "--ago")? {
+        let ago: i64 = ago
+            .parse()
+            .map_err(|_| format!("Invalid --ago {ago}, expected number of days"))?;
+        from = clock::today() - Duration::days(ago);
+        to = from;
+    }
+    let sprint_start = take_date(&mut args, "--sprint-start")?;
+    let sprint_days = match take_option(&mut args, "--sprint-days")? {
+        Some(d) => Some(d.parse::<u32>().ok().filter(|d| *d > 0).ok_or(format!(
+            "Invalid --sprint-days {d}, expected number of days"
+        ))?),
+        None => None,
+    };
+    if let Some(start) = sprint_start {
+        from = start;
+        to = start + Duration::days(sprint_days.unwrap_or(14) as i64 - 1);
+    } else if sprint_days.is_some() {
+        return Err("--sprint-days needs --sprint-start".into());
+    }
+    let year = match take_option(&mut args, "--year")? {
+        Some(y) => Some(
+            y.parse::<i32>()
+                .map_err(|_| format!("Invalid --year {y}"))?,
+        ),
+        None => None,
+    };
+    let iso_week = match take_option(&mut args, "--week")? {
+        Some(w) => {
+            let year = year.unwrap_or(clock::today().iso_week().year());
+            let monday = w
+                .parse::<u32>()
+                .ok()
+                .and_then(|w| NaiveDate::from_isoywd_opt(year, w, Weekday::Mon))
+                .ok_or(format!("Invalid --week {w}, {year} has no such ISO week"))?;
+            from = monday;
+            to = monday + Duration::days(6);
+            Some((year, monday.iso_week().week()))
+        }
+        None if year.is_some() => return Err("--year needs --week".into()),
+        None => None,
+    };
+    let mut dates = match take_option(&mut args, "--dates")? {
+        Some(spec) => {
+            let mut dates = spec
+                .split(',')
+                .map(|d| {
+                    NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d")
+                        .map_err(|_| format!("Invalid date for --dates: {d}"))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            dates.sort();
+            dates.dedup();
+            // cannot fail, split() yields at least one item
+            from = dates[0];
+            to = *dates.last().unwrap();
+            Some(dates)
+        }
+        None => None,
+    };
+    let pattern = match take_option(&mut args, "--task-regex")? {
+        Some(p) => Some(Pattern::new(&p)?),
+        None => None,
+    };
+    let after = take_time(&mut args, "--after")?;
+    let before = take_time(&mut args, "--before")?;
+    if let (Some(a), Some(b)) = (after, before) {
+        // within the virtual day, so that 02:00 can be after 23:00
+        let day = clock::today();
+        if clock::on_day(&day, a) > clock::on_day(&day, b) {
+            return Err(format!(
+                "--after {} is later than --before {} on the same day; for a range past \
+                 midnight, set RTIMELOG_VIRTUAL_MIDNIGHT",
+                a.format("%H:%M"),
+                b.format("%H:%M")
+            )
+            .into());
+        }
+    }
+    // with filters, untracked time is intended
+    let filtered = pattern.is_some() || after.is_some() || before.is_some();
+    let window = TimeWindow {
+        after,
+        before,
+        prorate: take_flag(&mut args, "--prorate"),
+    };
+    let clipboard = take_flag(&mut args, "--clipboard");
+    let round = match take_option(&mut args, "--round")? {
+        Some(m) => Some(Duration::minutes(
+            m.parse::<i64>()
+                .ok()
+                .filter(|m| *m > 0)
+                .ok_or(format!("Invalid --round {m}, expected minutes"))?,
+        )),
+        None => None,
+    };
+    let round_mode: RoundingMode = match take_option(&mut args, "--round-mode")? {
+        Some(mode) if round.is_some() => mode
+            .parse()
+            .map_err(|e| format!("Invalid --round-mode: {e}"))?,
+        Some(_) => return Err("--round-mode needs --round".into()),
+        None => RoundingMode::default(),
+    };
+    let by_category = take_flag(&mut args, "--by-category");
+    let by_tag = take_flag(&mut args, "--by-tag");
+    let split_tags = take_flag(&mut args, "--split-tags");
+    let group_pattern = match take_option(&mut args, "--by-regex-group")? {
+        Some(p) => Some(Pattern::new(&p)?),
+        None => None,
+    };
+    let no_merge = take_flag(&mut args, "--no-merge");
+    let meetings = take_flag(&mut args, "--meetings");
+    let work_minutes = take_flag(&mut args, "--work-minutes");
+    let slack_minutes = take_flag(&mut args, "--slack-minutes");
+    if work_minutes && slack_minutes {
+        return Err("--work-minutes and --slack-minutes are mutually exclusive".into());
+    }
+    let json = match take_option(&mut args, "--format")?.as_deref() {
+        None | Some("text") => false,
+        Some("json") if by_category || by_tag || tag_summary || group_pattern.is_some() => {
+            return Err("--format json does not support --by-category, --by-tag, \
+                 --tag-summary, or --by-regex-group"
+                .into())
+        }
+        Some("json") => true,
+        Some(f) => return Err(format!("Unknown report format {f}, supported: text, json").into()),
+    };
+    let json_pretty = take_flag(&mut args, "--json-pretty");
+    config.adjust_to_output(
+        env::var_os("NO_COLOR").is_some(),
+        io::stdout().is_terminal() && !clipboard,
+    );
+    let mut merge = config.merge_categories.clone();
+    if let Some(spec) = take_option(&mut args, "--merge-categories")? {
+        merge.extend(parse_category_map(&spec)?);
+    }
+    check_no_args(&args)?;
+
+    // entries of the individual --dates, which are not a contiguous range
+    let mut selected = Vec::new();
+    if let Some(dates) = dates.as_mut() {
+        dates.retain(|date| {
+            let day = timelog.get_day(date);
+            if day.is_empty() {
+                eprintln!("WARNING: Skipping {date}, it has no entries");
+            }
+            selected.extend_from_slice(day);
+            !day.is_empty()
+        });
+        if dates.is_empty() {
+            return Err("None of the --dates has entries".into());
+        }
+    }
+
+    let entries = match (sprint_start, iso_week) {
+        _ if dates.is_some() => &selected,
+        (Some(start), _) => timelog.get_period(&start, sprint_days.unwrap_or(14)),
+        (None, Some((year, week))) => timelog.get_iso_week(year, week).unwrap(),
+        (None, None) => timelog.get_date_range(&from, &to),
+    };
+    if hours_only {
+        print!("{}", WeekGrid::new_from_entries(entries, &merge, &config));
+        return Ok(());
+    }
+    if meetings {
+        print!("{}", MeetingLoad::new_from_entries(entries, &config));
+        return Ok(());
+    }
+    let blocks = get_blocks_with_leaving(entries, config.leaving_marker.as_deref())
+        .into_iter()
+        .filter(|b| pattern.as_ref().is_none_or(|p| p.is_match(&b.task)))
+        .filter_map(|b| window.apply(b));
+    let mut activities = if no_merge {
+        Activities::new_from_blocks_unmerged(blocks)
+    } else {
+        Activities::new_from_blocks(blocks)
+    }
+    .with_config(&config);
+    if let Some(increment) = round {
+        activities.round(increment, round_mode);
+    }
+    if work_minutes {
+        println!("{}", activities.work_minutes());
+        return Ok(());
+    }
+    if slack_minutes {
+        println!("{}", activities.slack_minutes());
+        return Ok(());
+    }
+    if json {
+        write_activities_json(&activities, json_pretty, &mut io::stdout())?;
+        return Ok(());
+    }
+    let listing = if by_category {
+        activities.render_by_category(&merge)
+    } else if by_tag {
+        activities.render_by_tag(split_tags)
+    } else if tag_summary {
+        activities.render_tag_summary()
+    } else if let Some(pattern) = &group_pattern {
+        activities.render_by_regex_group(pattern)
+    } else {
+        activities.to_string()
+    };
+    let output = if from == to {
+        let mut output = format!("Work done on {}:\n{listing}", config.format_date(&from));
+        // rounded durations don't add up to the presence
+        if let Some(presence) = Presence::new(entries, &activities).filter(|_| round.is_none()) {
+            output.push_str(&format!("{presence}\n"));
+            if let Some(warning) = presence.warning(config.untracked_tolerance) {
+                if !filtered {
+                    output.push_str(&format!("{warning}\n"));
+                }
+            }
+        }
+        output
+    } else if let Some(dates) = dates {
+        let dates: Vec<String> = dates.iter().map(|d| config.format_date(d)).collect();
+        format!("Work done on {}:\n{listing}", dates.join("; "))
+    } else {
+        format!(
+            "Work done from {} to {}:\n{listing}",
+            config.format_date(&from),
+            config.format_date(&to)
+        )
+    };
+    if clipboard {
+        if clipboard::copy_or_print(&output) {
+            println!("Report copied to clipboard");
+        }
+    } else {
+        print!("{output}");
+    }
+    Ok(())
+}
+
+// --min-day-work MINUTES, by default count all days
+fn take_min_day_work(args: &mut Vec<String>) -> Result<Duration, Box<dyn Error>> {
+    match take_option(args, "--min-day-work")? {
+        Some(m) => Ok(Duration::minutes(m.parse().map_err(|_| {
+            format!("Invalid --min-day-work {m}, expected minutes")
+        })?)),
+        None => Ok(Duration::zero()),
+    }
+}
+
+fn rhythm(timelog: &Timelog, mut args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let (from, to) = take_range(&mut args, Duration::weeks(4))?;
+    let min_day_work = take_min_day_work(&mut args)?;
+    check_no_args(&args)?;
+
+    let config = Config::new_from_default_file();
+    match Rhythm::new_from_entries_with_min(
+        timelog.get_date_range(&from, &to),
+        min_day_work,
+        &config,
+    ) {
+        Some(r) => print!("{r}"),
+        None => println!("No entries between {from} and {to}"),
+    }
+    Ok(())
+}
+
+fn between(timelog: &Timelog, mut args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let date = take_date(&mut args, "--date")?.unwrap_or(clock::today());
+    let [from, to] = args.as_slice() else {
+        return Err("between needs exactly two tasks".into());
+    };
+
+    let d = time_between(timelog.get_day(&date), from, to).map_err(|e| format!("{e} on {date}"))?;
+    println!("{} from {from} to {to}", format_hm(&d));
+    Ok(())
+}
+
+fn switches(timelog: &Timelog, mut args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let (from, to) = take_range(&mut args, Duration::days(0))?;
+    check_no_args(&args)?;
+
+    let config = Config::new_from_default_file();
+    print!(
+        "{}",
+        ContextSwitches::new_from_entries(timelog.get_date_range(&from, &to), &config)
+    );
+    Ok(())
+}
+
+fn tasks(timelog: &Timelog, mut args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    // default to the whole file
+    let from = take_date(&mut args, "--from")?.unwrap_or(NaiveDate::MIN);
+    let to = take_date(&mut args, "--to")?.unwrap_or(NaiveDate::MAX);
+    let by_category = take_flag(&mut args, "--category");
+    check_no_args(&args)?;
+
+    print!(
+        "{}",
+        TaskTotals::new_from_entries(
+            timelog.get_date_range(&from, &to),
+            by_category,
+            &Config::new_from_default_file()
+        )
+    );
+    Ok(())
+}
+
+fn typos(timelog: &Timelog, mut args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let (from, to) = take_range(&mut args, Duration::weeks(4))?;
+    check_no_args(&args)?;
+
+    for s in Singleton::find(timelog.get_date_range(&from, &to)) {
+        println!("{s}");
+    }
+    Ok(())
+}
+
+fn stats(timelog: &Timelog, mut args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let (from, to) = take_range(&mut args, Duration::weeks(4))?;
+    let excluded = take_flag(&mut args, "--exclude-today").then(clock::today);
+    let min_day_work = take_min_day_work(&mut args)?;
+    check_no_args(&args)?;
+
+    print!(
+        "{}",
+        Stats::new_from_entries_with_min(
+            timelog.get_date_range(&from, &to),
+            excluded,
+            min_day_work,
+            &Config::new_from_default_file()
+        )
+    );
+    Ok(())
+}
+
+fn undo(mut timelog: Timelog, mut args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    let count = match take_option(&mut args, "--count")? {
+        Some(n) => n
+            .parse::<usize>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or(format!("Invalid --count {n}, expected number of entries"))?,
+        None => 1,
+    };
+    check_no_args(&args)?;
+
+    let removed: Vec<Entry> = (0..count).map_while(|_| timelog.remove_last()).collect();
+    // don't save anything if it's not possible to do all of it
+    match removed.len() {
+        0 => return Err("No entries to undo".into()),
+        n if n < count => {
+            return Err(format!("Cannot undo {count} entries, there are only {n}").into())
+        }
+        _ => (),
+    }
+    for entry in removed {
+        println!(
+            "Removed: {}: {}",
+            entry.stop.format("%Y-%m-%d %H:%M"),
+            entry.task
+        );
+    }
+    timelog.save()?;
+    Ok(())
+}
+
+fn verify_roundtrip(file: Option<PathBuf>, args: Vec<String>) -> Result<(), Box<dyn Error>> {
+    check_no_args(&args)?;
+    let path = file.map_or_else(Timelog::get_default_file, Ok)?;
+    let raw = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    match Timelog::roundtrip(&raw) {
+        Ok(saved) if saved == raw => {
+            println!("{} is unchanged by saving", path.display());
+            Ok(())
+        }
+        Ok(saved) => {
+            print!("{}", diff_lines(&raw, &saved));
+            Err(format!("Saving would change {}", path.display()).into())
+        }
+        Err(problems) => {
+            for p in &problems {
+                println!("{}: {}", path.display(), p);
+            }
+            Err(format!("{} can't be read completely", path.display()).into())
+        }
+    }
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let file = match (
+        take_option(&mut args, "--file")?,
+        take_option(&mut args, "--profile")?,
+    ) {
+        (Some(_), Some(_)) => return Err("--file and --profile are mutually exclusive".into()),
+        (Some(path), None) => Some(PathBuf::from(path)),
+        (None, Some(name)) => Some(Config::new_from_default_file().timelog_file(Some(&name))?),
+        (None, None) => None,
+    };
+
+    if args.is_empty() {
+        return Err(USAGE.into());
+    }
+    let command = args.remove(0);
+
+    match command.as_str() {
+        "add" => add(load(file)?, args),
+        "between" => between(&load(file)?, args),
+        "calendar" => calendar(&load(file)?, args),
+        "check" => check(file, args),
+        "config" => config(args),
+        "continue" => continue_task(load(file)?, args),
+        "edit" => edit(file, args),
+        "export" => export(&load(file)?, args),
+        "invoice" => invoice(&load(file)?, args),
+        "merge" => merge(load(file)?, args),
+        "rename" => rename(load(file)?, args),
+        "repair" => repair(file, args),
+        "report" => report(&load(file)?, args),
+        "rhythm" => rhythm(&load(file)?, args),
+        "stats" => stats(&load(file)?, args),
+        "switches" => switches(&load(file)?, args),
+        "tasks" => tasks(&load(file)?, args),
+        "typos" => typos(&load(file)?, args),
+        "undo" => undo(load(file)?, args),
+        "verify-roundtrip" => verify_roundtrip(file, args),
+        "-h" | "--help" | "help" => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        _ => Err(format!("Unknown command: {command}\n\n{USAGE}").into()),
+    }
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{e}");
+        process::exit(1);
+    }
+}
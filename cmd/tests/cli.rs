@@ -0,0 +1,329 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+use pretty_assertions::assert_eq;
+
+/**
+ * Temporary directory with a timelog and an empty config directory
+ *
+ * This keeps the user's own config and timelog out of the tests.
+ */
+struct TestDir {
+    path: PathBuf,
+}
+
+impl TestDir {
+    fn new(name: &str, timelog: &str) -> TestDir {
+        let path =
+            std::env::temp_dir().join(format!("rtimelog-cmd-test-{name}-{}", std::process::id()));
+        fs::create_dir_all(path.join("config")).unwrap();
+        fs::write(path.join("timelog.txt"), timelog).unwrap();
+        TestDir { path }
+    }
+
+    fn file(&self, name: &str) -> PathBuf {
+        self.path.join(name)
+    }
+
+    fn read(&self, name: &str) -> String {
+        fs::read_to_string(self.file(name)).unwrap()
+    }
+
+    fn run(&self, args: &[&str], env: &[(&str, &str)]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_rtimelog-cmd"))
+            .arg("--file")
+            .arg(self.file("timelog.txt"))
+            .args(args)
+            .env("XDG_CONFIG_HOME", self.file("config"))
+            .envs(env.iter().copied())
+            .output()
+            .unwrap()
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.path).unwrap();
+    }
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn test_undo_base_file() {
+    let dir = TestDir::new(
+        "undo-base",
+        "2022-06-10 07:00: arrived\n2022-06-10 09:00: code\n",
+    );
+    fs::write(
+        dir.file("base.txt"),
+        "2022-06-10 08:00: team meeting\n2022-06-10 10:00: holiday\n",
+    )
+    .unwrap();
+    let base = dir.file("base.txt");
+    let env = [("RTIMELOG_BASE_FILE", base.to_str().unwrap())];
+
+    // the later base entry is skipped, the own one gets removed
+    let output = dir.run(&["undo"], &env);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "Removed: 2022-06-10 09:00: code\n");
+    assert_eq!(dir.read("timelog.txt"), "2022-06-10 07:00: arrived\n");
+
+    // base entries don't count as entries to undo
+    let output = dir.run(&["undo", "--count", "2"], &env);
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim(),
+        "Cannot undo 2 entries, there are only 1"
+    );
+    assert_eq!(dir.read("timelog.txt"), "2022-06-10 07:00: arrived\n");
+    assert_eq!(
+        dir.read("base.txt"),
+        "2022-06-10 08:00: team meeting\n2022-06-10 10:00: holiday\n"
+    );
+}
+
+#[test]
+fn test_task_regex() {
+    let dir = TestDir::new(
+        "task-regex",
+        "2022-06-10 07:00: arrived\n2022-06-10 08:00: customer joe: call\n\
+         2022-06-10 09:00: code\n",
+    );
+    let output = dir.run(
+        &[
+            "report",
+            "--from",
+            "2022-06-10",
+            "--task-regex",
+            "^customer",
+        ],
+        &[],
+    );
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("customer joe: call"));
+    assert!(!stdout(&output).contains("code"));
+
+    // without the regex feature, unsupported escapes are an error instead of a literal letter
+    let output = dir.run(&["report", "--task-regex", "\\w+"], &[]);
+    if cfg!(feature = "regex") {
+        assert!(output.status.success());
+    } else {
+        assert!(!output.status.success());
+        assert_eq!(
+            String::from_utf8_lossy(&output.stderr).trim(),
+            "Invalid pattern \\w+: '\\w' is not supported, only . \\d * + ^ $ and one (group)"
+        );
+    }
+}
+
+#[test]
+fn test_by_regex_group() {
+    let dir = TestDir::new(
+        "by-regex-group",
+        "2022-06-10 07:00: arrived\n2022-06-10 08:00: fix #12: crash\n\
+         2022-06-10 08:30: review #3\n2022-06-10 09:00: fix #12: test\n",
+    );
+    let output = dir.run(
+        &[
+            "report",
+            "--from",
+            "2022-06-10",
+            "--to",
+            "2022-06-10",
+            "--by-regex-group",
+            "#(\\d+)",
+        ],
+        &[],
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "Work done on Friday, 2022-06-10:
+ 1 h 30 min: 12
+ 0 h 30 min: 3
+-------
+Total work done: 2 h 0 min
+Total slacking: 0 h 0 min
+Present 2 h 0 min, tracked 2 h 0 min, untracked 0 h 0 min
+"
+    );
+
+    // same validation as --task-regex
+    let output = dir.run(&["report", "--by-regex-group", "#(\\w+)"], &[]);
+    assert_eq!(output.status.success(), cfg!(feature = "regex"));
+}
+
+#[test]
+#[cfg(not(feature = "clipboard"))]
+fn test_clipboard_fallback() {
+    let dir = TestDir::new(
+        "clipboard",
+        "2022-06-10 07:00: arrived\n2022-06-10 08:00: code\n",
+    );
+    let output = dir.run(
+        &[
+            "report",
+            "--from",
+            "2022-06-10",
+            "--to",
+            "2022-06-10",
+            "--clipboard",
+        ],
+        &[],
+    );
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "WARNING: No clipboard available (built without the clipboard feature), printing instead\n"
+    );
+    assert!(stdout(&output).starts_with("Work done on Friday, 2022-06-10:\n 1 h  0 min: code\n"));
+}
+
+#[test]
+fn test_now_env() {
+    let dir = TestDir::new("now", "2022-06-10 07:00: arrived\n2022-06-10 08:00: code\n");
+    let output = dir.run(&["report"], &[("RTIMELOG_NOW", "2022-06-10 09:00")]);
+    assert!(output.status.success());
+    assert!(stdout(&output).starts_with("Work done on Friday, 2022-06-10:\n 1 h  0 min: code\n"));
+
+    // the report asks for the time several times, but only warns once
+    let output = dir.run(&["report"], &[("RTIMELOG_NOW", "bogus")]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "WARNING: ignoring invalid $RTIMELOG_NOW: bogus\n"
+    );
+}
+
+#[test]
+fn test_report_minutes() {
+    let dir = TestDir::new(
+        "minutes",
+        "2022-06-10 07:00: arrived\n2022-06-10 08:30: code\n2022-06-10 08:45: ** tea\n\
+         2022-06-10 09:15: email\n\n2022-06-13 07:00: arrived\n2022-06-13 07:30: code\n",
+    );
+    let range = ["--from", "2022-06-10", "--to", "2022-06-13"];
+    let output = dir.run(
+        &[&["report"][..], &range, &["--work-minutes"]].concat(),
+        &[],
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "150\n");
+
+    let output = dir.run(
+        &[&["report"][..], &range, &["--slack-minutes"]].concat(),
+        &[],
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "15\n");
+
+    // empty range
+    let output = dir.run(
+        &[
+            "report",
+            "--from",
+            "2022-06-11",
+            "--to",
+            "2022-06-12",
+            "--work-minutes",
+        ],
+        &[],
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "0\n");
+
+    let output = dir.run(
+        &[
+            &["report"][..],
+            &range,
+            &["--work-minutes", "--slack-minutes"],
+        ]
+        .concat(),
+        &[],
+    );
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim(),
+        "--work-minutes and --slack-minutes are mutually exclusive"
+    );
+}
+
+#[test]
+fn test_tasks_whole_file() {
+    let dir = TestDir::new(
+        "tasks",
+        "2022-06-09 07:00: arrived\n2022-06-09 08:00: code\n\n\
+         2022-06-10 07:00: arrived\n2022-06-10 07:30: code\n2022-06-10 08:00: email\n",
+    );
+    // the default range is open on both ends, also with a virtual midnight
+    for env in [&[][..], &[("RTIMELOG_VIRTUAL_MIDNIGHT", "04:00")]] {
+        let output = dir.run(&["tasks"], env);
+        assert!(output.status.success());
+        assert_eq!(stdout(&output), " 1 h 30 min: code\n 0 h 30 min: email\n");
+    }
+}
+
+#[test]
+fn test_continue_future_base() {
+    let dir = TestDir::new(
+        "continue-base",
+        "2022-06-10 07:00: arrived\n2022-06-10 09:00: code\n",
+    );
+    fs::write(dir.file("base.txt"), "2022-12-24 00:00: holiday\n").unwrap();
+    let base = dir.file("base.txt");
+    let env = [
+        ("RTIMELOG_BASE_FILE", base.to_str().unwrap()),
+        ("RTIMELOG_NOW", "2022-06-10 10:00"),
+    ];
+
+    let output = dir.run(&["continue"], &env);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "Continuing: code\n");
+    assert_eq!(
+        dir.read("timelog.txt"),
+        "2022-06-10 07:00: arrived\n2022-06-10 09:00: code\n2022-06-10 10:00: code\n"
+    );
+}
+
+#[test]
+fn test_report_time_window() {
+    let dir = TestDir::new(
+        "time-window",
+        "2022-06-10 20:00: arrived\n2022-06-10 23:00: evening\n2022-06-11 02:00: night\n",
+    );
+    let args = [
+        "report",
+        "--from",
+        "2022-06-10",
+        "--to",
+        "2022-06-10",
+        "--after",
+        "22:00",
+        "--before",
+        "02:00",
+        "--work-minutes",
+    ];
+
+    let output = dir.run(&args, &[]);
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim(),
+        "--after 22:00 is later than --before 02:00 on the same day; for a range past \
+         midnight, set RTIMELOG_VIRTUAL_MIDNIGHT"
+    );
+
+    // with a later virtual midnight, 02:00 is after 22:00 on the same day
+    let output = dir.run(&args, &[("RTIMELOG_VIRTUAL_MIDNIGHT", "04:00")]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "360\n");
+    let output = dir.run(
+        &[&args[..], &["--prorate"]].concat(),
+        &[("RTIMELOG_VIRTUAL_MIDNIGHT", "04:00")],
+    );
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "240\n");
+}
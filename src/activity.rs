@@ -15,11 +15,118 @@
 
 extern crate chrono;
 
+use std::collections::HashMap;
 use std::fmt;
+use std::fmt::Write as _; // import without risk of name clashing
+use std::io;
 
-use chrono::{Datelike, Duration, NaiveDateTime};
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
 
-use crate::store::Entry;
+use crate::clock;
+use crate::config::Config;
+use crate::pattern::Pattern;
+use crate::store::{normalize_task, Entry, Timelog};
+
+pub fn is_slack(task: &str) -> bool {
+    task.contains("**")
+}
+
+// like is_slack(), but also for tasks starting with the configured slack_prefix, like "*",
+// or in one of the slack_categories
+pub fn is_slack_with(task: &str, prefix: &str, categories: &[String]) -> bool {
+    is_slack(task)
+        || task.starts_with(prefix)
+        || category(task).is_some_and(|c| categories.iter().any(|s| s == c))
+}
+
+// category of a task, like "project" in "project: fix bug"
+pub fn category(task: &str) -> Option<&str> {
+    task.split_once(": ").map(|(c, _)| c.trim())
+}
+
+// gtimelog style tags, like "sysadmin" and "www" in "fix server -- sysadmin www"
+pub fn tags(task: &str) -> Vec<&str> {
+    match task.split_once(" -- ") {
+        Some((_, tags)) => tags.split_whitespace().collect(),
+        None => Vec::new(),
+    }
+}
+
+// whether task contains keyword, ignoring case, like "rtimelog" in "RTimelog: code"
+pub fn matches_keyword(task: &str, keyword: &str) -> bool {
+    task.to_lowercase().contains(&keyword.to_lowercase())
+}
+
+// meetings are tasks in the marker category, tagged with the marker, or just the marker
+pub fn is_meeting(task: &str, marker: &str) -> bool {
+    task == marker || category(task) == Some(marker) || tags(task).contains(&marker)
+}
+
+// task with the category separator ": " replaced by separator, for display
+pub fn display_task(task: &str, separator: &str) -> String {
+    match task.split_once(": ") {
+        Some((c, rest)) => format!("{c}{separator}{rest}"),
+        None => task.to_string(),
+    }
+}
+
+pub fn format_hm(d: &Duration) -> String {
+    format!("{} h {} min", d.num_hours(), d.num_minutes() % 60)
+}
+
+// like format_hm, but leaves out zero hours or minutes, like "3h", "45m", or "3h5m"
+pub fn format_hm_compact(d: &Duration) -> String {
+    match (d.num_hours(), d.num_minutes() % 60) {
+        (0, m) => format!("{m}m"),
+        (h, 0) => format!("{h}h"),
+        (h, m) => format!("{h}h{m}m"),
+    }
+}
+
+// like format_hm, but right-aligned hours and minutes, so that durations line up
+pub fn format_hm_aligned(d: &Duration, hours_width: usize) -> String {
+    format!(
+        "{:>hours_width$} h {:>2} min",
+        d.num_hours(),
+        d.num_minutes() % 60
+    )
+}
+
+// width of the hours for format_hm_aligned(), so that all durations line up
+pub fn hours_width<'a>(durations: impl IntoIterator<Item = &'a Duration>) -> usize {
+    durations
+        .into_iter()
+        .map(|d| d.num_hours().to_string().len())
+        .max()
+        .unwrap_or(0)
+        .max(2)
+}
+
+// append the seconds of a duration under a minute, which otherwise looks like zero
+fn add_seconds(formatted: String, d: &Duration, compact: bool) -> String {
+    match d.num_seconds() {
+        s @ 1..=59 if compact => format!("{formatted}{s}s"),
+        s @ 1..=59 => format!("{formatted} {s} s"),
+        _ => formatted,
+    }
+}
+
+// bar of width characters, with the given fraction (0 to 1) of them filled
+pub fn percentage_bar(fraction: f64, width: usize) -> String {
+    let filled = ((fraction.clamp(0.0, 1.0) * width as f64).round() as usize).min(width);
+    "█".repeat(filled) + &"░".repeat(width - filled)
+}
+
+// cut off line with an ellipsis if it is longer than width characters, to avoid wrapping
+pub fn truncate_line(line: &str, width: usize) -> String {
+    if line.chars().count() <= width {
+        return line.to_string();
+    }
+    match width {
+        0 => String::new(),
+        _ => line.chars().take(width - 1).chain(['…']).collect(),
+    }
+}
 
 /**
  * Activity: Duration of all Entry's with the same task
@@ -31,14 +138,286 @@ pub struct Activity {
 
 impl fmt::Display for Activity {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{:>2} h {:>2} min: {}",
-            self.duration.num_hours(),
-            self.duration.num_minutes() % 60,
-            self.name
+        write!(f, "{}: {}", format_hm_aligned(&self.duration, 2), self.name)
+    }
+}
+
+impl Activity {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    // duration rounded to a multiple of increment, e.g. for billing
+    pub fn rounded(&self, increment: Duration, mode: RoundingMode) -> Duration {
+        round_duration(&self.duration, increment, mode)
+    }
+
+    fn display(&self, separator: &str, compact: bool, seconds: bool, hours_width: usize) -> String {
+        let mut duration = if compact {
+            // wide enough for "23h59m" with the default width
+            let width = hours_width + 4;
+            format!("{:>width$}", format_hm_compact(&self.duration))
+        } else {
+            format_hm_aligned(&self.duration, hours_width)
+        };
+        if seconds {
+            duration = add_seconds(duration, &self.duration, compact);
+        }
+        format!("{duration}: {}", display_task(&self.name, separator))
+    }
+}
+
+/**
+ * Block: Time span from the previous entry's stop to an entry's stop
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub start: NaiveDateTime,
+    pub stop: NaiveDateTime,
+    // task name without continuation marker and duration hint
+    pub task: String,
+    pub continuation: bool,
+    // intended duration, see Entry::duration_hint()
+    pub hint: Option<Duration>,
+}
+
+impl Block {
+    pub fn duration(&self) -> Duration {
+        self.stop.signed_duration_since(self.start)
+    }
+}
+
+/**
+ * TimeWindow: Restrict blocks to a time of day range
+ *
+ * The range is within the (virtual) day of each block, so with a virtual midnight of
+ * 04:00, 02:00 is after 23:00.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeWindow {
+    // None for the start or end of the day
+    pub after: Option<NaiveTime>,
+    pub before: Option<NaiveTime>,
+    // cut blocks which straddle the window boundaries, instead of counting them completely
+    pub prorate: bool,
+}
+
+impl TimeWindow {
+    // start and end of the window on the given day
+    fn on_day(&self, day: &NaiveDate) -> (NaiveDateTime, NaiveDateTime) {
+        (
+            self.after
+                .map_or_else(|| clock::day_start(day), |t| clock::on_day(day, t)),
+            self.before.map_or_else(
+                || clock::day_start(&day.succ_opt().unwrap()),
+                |t| clock::on_day(day, t),
+            ),
         )
     }
+
+    // None if the block is outside of the window
+    pub fn apply(&self, block: Block) -> Option<Block> {
+        let (after, before) = self.on_day(&clock::logical_date(block.stop));
+        if self.prorate {
+            let start = block.start.max(after);
+            let stop = block.stop.min(before);
+            (start < stop).then_some(Block {
+                start,
+                stop,
+                ..block
+            })
+        } else {
+            (after <= block.stop && block.stop <= before).then_some(block)
+        }
+    }
+}
+
+/**
+ * Chronological blocks of the given entries
+ */
+pub fn get_blocks(entries: &[Entry]) -> Vec<Block> {
+    get_blocks_with_leaving(entries, None)
+}
+
+/**
+ * Like get_blocks, but an entry with the leaving_marker task (like "home") ends work
+ *
+ * Neither the block up to the marker nor the gap after it count; the entry after the
+ * marker just provides the start time, like the first entry of a day.
+ */
+pub fn get_blocks_with_leaving(entries: &[Entry], leaving_marker: Option<&str>) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut prev_stop: Option<NaiveDateTime> = None;
+    let mut prev_leaving = false;
+
+    for entry in entries {
+        let task = normalize_task(entry.name());
+        let leaving = leaving_marker == Some(task.as_str());
+        if let Some(prev_stop_time) = prev_stop {
+            // first entry of every day gets ignored, it just provides the start time
+            if clock::logical_date(prev_stop_time) == clock::logical_date(entry.stop)
+                && !leaving
+                && !prev_leaving
+            {
+                blocks.push(Block {
+                    start: prev_stop_time,
+                    stop: entry.stop,
+                    task,
+                    continuation: entry.is_continuation(),
+                    hint: entry.duration_hint(),
+                });
+            }
+        }
+        prev_stop = Some(entry.stop);
+        prev_leaving = leaving;
+    }
+
+    blocks
+}
+
+/**
+ * Blocks whose duration differs from their duration hint by more than tolerance
+ *
+ * This catches forgotten entries, which make a block much longer than intended.
+ */
+pub fn check_duration_hints(entries: &[Entry], tolerance: Duration) -> Vec<Block> {
+    get_blocks(entries)
+        .into_iter()
+        .filter(|b| b.hint.is_some_and(|h| (b.duration() - h).abs() > tolerance))
+        .collect()
+}
+
+/**
+ * Time spent on the last entry's task in the given entries, plus the time since then
+ *
+ * This is the total time if the current activity continues that task. None if there are
+ * no entries, or no time was spent on the last task yet (like after "arrived").
+ */
+pub fn time_on_last_task(entries: &[Entry], now: NaiveDateTime) -> Option<Duration> {
+    let last = entries.last()?;
+    let done: Duration = get_blocks(entries)
+        .iter()
+        .filter(|b| b.task == last.name())
+        .map(|b| b.duration())
+        .sum();
+    if done.is_zero() {
+        None
+    } else {
+        Some(done + now.signed_duration_since(last.stop))
+    }
+}
+
+/**
+ * Status line above the prompt, with the time since the last entry of today
+ *
+ * When paused, e.g. over lunch, now is the time of pausing, so that the times don't grow.
+ */
+pub fn status_line(today: &[Entry], now: NaiveDateTime, compact: bool, paused: bool) -> String {
+    let format_duration = if compact {
+        format_hm_compact
+    } else {
+        format_hm
+    };
+    let since = match today.last() {
+        None => "no entries yet today".to_string(),
+        Some(e) => {
+            let mut s = format!(
+                "{} since last entry",
+                format_duration(&now.signed_duration_since(e.stop))
+            );
+            if let Some(d) = time_on_last_task(today, now) {
+                write!(s, " ({} on {} today)", format_duration(&d), e.name())
+                    .expect("failed to format status");
+            }
+            if paused {
+                s.push_str(" (paused)");
+            }
+            s
+        }
+    };
+    format!("{since}; type command (:h for help) or entry")
+}
+
+/**
+ * Terminal control sequence to replace the status line in place
+ *
+ * This is for after entering an empty line: The cursor is below the prompt line, which
+ * is below the previous status line. Go up these two lines and clear them, instead of
+ * redrawing the whole screen.
+ */
+pub fn status_line_update(status: &str) -> String {
+    format!("\x1b[2A\r\x1b[J{status}\n")
+}
+
+/**
+ * How to round durations to an increment, for billing
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoundingMode {
+    // half an increment and more rounds up
+    Nearest,
+    #[default]
+    Up,
+    Down,
+}
+
+impl std::str::FromStr for RoundingMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<RoundingMode, String> {
+        match s {
+            "nearest" => Ok(RoundingMode::Nearest),
+            "up" => Ok(RoundingMode::Up),
+            "down" => Ok(RoundingMode::Down),
+            v => Err(format!("must be nearest, up, or down, not {v}")),
+        }
+    }
+}
+
+// round d to a multiple of increment (at least one minute); sub-minute parts are ignored
+pub fn round_duration(d: &Duration, increment: Duration, mode: RoundingMode) -> Duration {
+    let inc = increment.num_minutes().max(1);
+    let m = d.num_minutes();
+    let rounded = match mode {
+        RoundingMode::Nearest => (m + inc / 2) / inc * inc,
+        RoundingMode::Up => (m + inc - 1) / inc * inc,
+        RoundingMode::Down => m / inc * inc,
+    };
+    Duration::minutes(rounded)
+}
+
+/**
+ * Work of a category, compared to its daily target if it has one
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CategoryProgress {
+    pub category: String,
+    pub done: Duration,
+    pub target: Option<Duration>,
+}
+
+impl CategoryProgress {
+    pub fn is_over(&self) -> bool {
+        self.target.is_some_and(|t| self.done > t)
+    }
+}
+
+/**
+ * Order of the activities in Activities
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ActivityOrder {
+    // chronological feel
+    #[default]
+    FirstOccurrence,
+    // biggest time sinks first
+    Duration,
+    // like Duration, but slack after all work
+    DurationSlackLast,
 }
 
 /**
@@ -46,102 +425,682 @@ impl fmt::Display for Activity {
  */
 pub struct Activities {
     activities: Vec<Activity>,
-    total_work: Duration,
-    total_slack: Duration,
+    pub(crate) total_work: Duration,
+    pub(crate) total_slack: Duration,
+    // display settings
+    category_separator: String,
+    compact: bool,
+    bars: bool,
+    seconds: bool,
+    // grouping settings
+    category_case_insensitive: bool,
+    slack_prefix: String,
+    slack_categories: Vec<String>,
 }
 
 impl Activities {
     pub fn new_from_entries(entries: &[Entry]) -> Activities {
+        Activities::new_from_entries_filtered(entries, |_| true)
+    }
+
+    // only consider tasks for which filter is true; the time of all others becomes untracked
+    pub fn new_from_entries_filtered(
+        entries: &[Entry],
+        filter: impl Fn(&str) -> bool,
+    ) -> Activities {
+        Activities::new_from_blocks(get_blocks(entries).into_iter().filter(|b| filter(&b.task)))
+    }
+
+    // respects the leaving marker and display settings of the config
+    pub fn new_from_entries_with_config(entries: &[Entry], config: &Config) -> Activities {
+        Activities::new_from_blocks(get_blocks_with_leaving(
+            entries,
+            config.leaving_marker.as_deref(),
+        ))
+        .with_config(config)
+    }
+
+    // like new_from_entries_with_config(), but only tasks which contain keyword, ignoring case
+    pub fn new_from_entries_matching(
+        entries: &[Entry],
+        keyword: &str,
+        config: &Config,
+    ) -> Activities {
+        Activities::new_from_blocks(
+            get_blocks_with_leaving(entries, config.leaving_marker.as_deref())
+                .into_iter()
+                .filter(|b| matches_keyword(&b.task, keyword)),
+        )
+        .with_config(config)
+    }
+
+    pub fn new_from_blocks(blocks: impl IntoIterator<Item = Block>) -> Activities {
+        Activities::collect_blocks(blocks, true)
+    }
+
+    // every block as its own activity, for a chronological view of repeated tasks
+    pub fn new_from_blocks_unmerged(blocks: impl IntoIterator<Item = Block>) -> Activities {
+        Activities::collect_blocks(blocks, false)
+    }
+
+    fn collect_blocks(blocks: impl IntoIterator<Item = Block>, merge: bool) -> Activities {
         // don't use a hashmap here, we do want to keep this sorted by "first occurrence of task"
         let mut activities = Vec::new();
-        let mut total_work = Duration::minutes(0);
-        let mut total_slack = Duration::minutes(0);
-        let mut prev_stop: Option<NaiveDateTime> = None;
-
-        for entry in entries {
-            match prev_stop {
-                Some(prev_stop_time) => {
-                    // continue if not the same day
-                    // first entry of every day gets ignored
-                    if prev_stop_time.day() != entry.stop.day() {
-                        prev_stop = Some(entry.stop);
-                        continue;
-                    }
-
-                    let duration = entry.stop.signed_duration_since(prev_stop_time);
-                    if entry.task.contains("**") {
-                        total_slack = total_slack + duration;
-                    } else {
-                        total_work = total_work + duration;
-                    }
-
-                    // meh quadratic loop, but not important
-                    match activities
-                        .iter_mut()
-                        .find(|a: &&mut Activity| a.name == entry.task)
-                    {
-                        Some(a) => a.duration = a.duration + duration,
-                        None => activities.push(Activity {
-                            name: entry.task.to_string(),
-                            duration,
-                        }),
-                    }
-
-                    prev_stop = Some(entry.stop);
+
+        for block in blocks {
+            let duration = block.duration();
+            // meh quadratic loop, but not important
+            match activities
+                .iter_mut()
+                .find(|a: &&mut Activity| merge && a.name == block.task)
+            {
+                Some(a) => a.duration += duration,
+                None => activities.push(Activity {
+                    name: block.task,
+                    duration,
+                }),
+            }
+        }
+
+        let mut activities = Activities {
+            activities,
+            total_work: Duration::zero(),
+            total_slack: Duration::zero(),
+            category_separator: ": ".to_string(),
+            compact: false,
+            bars: false,
+            seconds: false,
+            category_case_insensitive: false,
+            slack_prefix: "**".to_string(),
+            slack_categories: Vec::new(),
+        };
+        activities.count_totals();
+        activities
+    }
+
+    // apply the display settings from the config
+    pub fn with_config(mut self, config: &Config) -> Activities {
+        self.category_separator = config.category_separator.clone();
+        self.compact = config.compact_durations;
+        self.seconds = config.show_seconds;
+        self.bars = config.percentage_bars;
+        self.category_case_insensitive = config.category_case_insensitive;
+        if self.slack_prefix != config.slack_prefix
+            || self.slack_categories != config.slack_categories
+        {
+            self.slack_prefix = config.slack_prefix.clone();
+            self.slack_categories = config.slack_categories.clone();
+            self.count_totals();
+        }
+        self.sort(config.activity_order);
+        self
+    }
+
+    // whether task is slack with the slack settings of the config, like Config::is_slack()
+    pub fn is_slack(&self, task: &str) -> bool {
+        is_slack_with(task, &self.slack_prefix, &self.slack_categories)
+    }
+
+    fn count_totals(&mut self) {
+        self.total_work = Duration::zero();
+        self.total_slack = Duration::zero();
+        for a in &self.activities {
+            if self.is_slack(&a.name) {
+                self.total_slack += a.duration;
+            } else {
+                self.total_work += a.duration;
+            }
+        }
+    }
+
+    /**
+     * Round every activity to a multiple of increment, e.g. for billing
+     *
+     * The totals become the sum of the rounded activities, so that they stay consistent
+     * with the listed durations.
+     */
+    pub fn round(&mut self, increment: Duration, mode: RoundingMode) {
+        for a in self.activities.iter_mut() {
+            a.duration = a.rounded(increment, mode);
+        }
+        self.count_totals();
+    }
+
+    // activities are in order of first occurrence when created, so that is a no-op
+    pub fn sort(&mut self, order: ActivityOrder) {
+        match order {
+            ActivityOrder::FirstOccurrence => (),
+            // stable, so that equal durations stay chronological
+            ActivityOrder::Duration => self.activities.sort_by_key(|a| -a.duration),
+            ActivityOrder::DurationSlackLast => {
+                let mut activities = std::mem::take(&mut self.activities);
+                activities.sort_by_key(|a| (self.is_slack(&a.name), -a.duration));
+                self.activities = activities;
+            }
+        }
+    }
+
+    fn display_activity(&self, a: &Activity, separator: &str, hours_width: usize) -> String {
+        let line = a.display(separator, self.compact, self.seconds, hours_width);
+        let total = self.total_tracked().num_seconds();
+        if !self.bars || total == 0 {
+            return line;
+        }
+        let fraction = a.duration.num_seconds() as f64 / total as f64;
+        format!(
+            "{} {:>3.0}%  {line}",
+            percentage_bar(fraction, 10),
+            fraction * 100.0
+        )
+    }
+
+    fn format_duration(&self, d: &Duration) -> String {
+        let formatted = if self.compact {
+            format_hm_compact(d)
+        } else {
+            format_hm(d)
+        };
+        if self.seconds {
+            add_seconds(formatted, d, self.compact)
+        } else {
+            formatted
+        }
+    }
+
+    // a block of days without entries can take hundreds of hours
+    fn hours_width(&self) -> usize {
+        hours_width(self.activities.iter().map(|a| &a.duration))
+    }
+
+    // for integrations like status bars, which need the numbers instead of the text
+    pub fn activities(&self) -> &[Activity] {
+        &self.activities
+    }
+
+    pub fn total_work(&self) -> Duration {
+        self.total_work
+    }
+
+    pub fn total_slack(&self) -> Duration {
+        self.total_slack
+    }
+
+    // for scripting
+    pub fn work_minutes(&self) -> i64 {
+        self.total_work.num_minutes()
+    }
+
+    pub fn slack_minutes(&self) -> i64 {
+        self.total_slack.num_minutes()
+    }
+
+    // work and slack, i.e. all time which is accounted to some task
+    pub fn total_tracked(&self) -> Duration {
+        self.total_work + self.total_slack
+    }
+
+    pub fn category_total(&self, category_name: &str) -> Duration {
+        self.activities
+            .iter()
+            .filter(|a| category(&a.name) == Some(category_name))
+            .map(|a| a.duration)
+            .sum()
+    }
+
+    pub fn focus_header(&self, category_name: &str) -> String {
+        format!(
+            "Focus on {}: {} of {} work",
+            category_name,
+            self.format_duration(&self.category_total(category_name)),
+            self.format_duration(&self.total_work)
+        )
+    }
+
+    // like Display, but with a header with the total of the focus category, and its activities marked
+    pub fn render_focus(&self, category_name: &str) -> String {
+        let width = self.hours_width();
+        let mut output = self.focus_header(category_name);
+        output.push_str("\n\n");
+        for a in &self.activities {
+            let marker = if category(&a.name) == Some(category_name) {
+                '>'
+            } else {
+                ' '
+            };
+            writeln!(
+                output,
+                "{marker} {}",
+                self.display_activity(a, &self.category_separator, width)
+            )
+            .expect("failed to format activity");
+        }
+        self.write_totals(&mut output)
+            .expect("failed to format totals");
+        output
+    }
+
+    // durations of all activities, work and slack, in the current order
+    pub fn by_task(&self) -> Vec<(String, Duration)> {
+        self.activities
+            .iter()
+            .map(|a| (a.name.clone(), a.duration))
+            .collect()
+    }
+
+    /**
+     * Work durations by category, in order of first occurrence
+     *
+     * Categories in merge are counted under the mapped category, others pass through.
+     * Tasks without category are counted as "uncategorized". With the
+     * category_case_insensitive setting, categories which only differ in case are
+     * grouped together, under the casing of their first occurrence.
+     */
+    pub fn by_category(&self, merge: &HashMap<String, String>) -> Vec<(String, Duration)> {
+        let mut categories: Vec<(String, Duration)> = Vec::new();
+        let same = |a: &str, b: &str| {
+            if self.category_case_insensitive {
+                a.to_lowercase() == b.to_lowercase()
+            } else {
+                a == b
+            }
+        };
+
+        for a in self.activities.iter().filter(|a| !self.is_slack(&a.name)) {
+            let cat = category(&a.name).unwrap_or("uncategorized");
+            let cat = merge
+                .iter()
+                .find(|(from, _)| same(from, cat))
+                .map(|(_, to)| to.as_str())
+                .unwrap_or(cat);
+            match categories.iter_mut().find(|(c, _)| same(c, cat)) {
+                Some((_, d)) => *d += a.duration,
+                None => categories.push((cat.to_string(), a.duration)),
+            }
+        }
+
+        categories
+    }
+
+    /**
+     * Work durations by tag, in order of first occurrence
+     *
+     * By default, every tag of an activity gets its complete duration, so that the tag
+     * totals overlap. With split, the tags share the duration equally, so that they add
+     * up to the total work: each tag gets the whole minutes of duration / number of tags,
+     * and the remaining minutes go to the first tag. Activities without tags are counted
+     * as "(untagged)".
+     */
+    pub fn by_tag(&self, split: bool) -> Vec<(String, Duration)> {
+        let mut totals: Vec<(String, Duration)> = Vec::new();
+        let mut add = |tag: &str, d: Duration| match totals.iter_mut().find(|(t, _)| t == tag) {
+            Some((_, total)) => *total += d,
+            None => totals.push((tag.to_string(), d)),
+        };
+
+        for a in self.activities.iter().filter(|a| !self.is_slack(&a.name)) {
+            let tags = tags(&a.name);
+            if tags.is_empty() {
+                add("(untagged)", a.duration);
+            } else if split {
+                let minutes = a.duration.num_minutes();
+                let n = tags.len() as i64;
+                for (i, tag) in tags.iter().enumerate() {
+                    let remainder = if i == 0 { minutes % n } else { 0 };
+                    add(tag, Duration::minutes(minutes / n + remainder));
                 }
-                None => {
-                    // first entry's task is ignored, it just provides the start time
-                    prev_stop = Some(entry.stop);
+            } else {
+                for tag in tags {
+                    add(tag, a.duration);
                 }
             }
         }
 
-        Activities {
-            activities,
-            total_work,
-            total_slack,
+        totals
+    }
+
+    /**
+     * Work durations by tag like by_tag(), with the activities which contribute to each
+     *
+     * An activity with several tags is listed under each of them.
+     */
+    pub fn by_tag_with_tasks(&self) -> Vec<(String, Duration, Vec<&Activity>)> {
+        self.by_tag(false)
+            .into_iter()
+            .map(|(tag, total)| {
+                let tasks = self
+                    .activities
+                    .iter()
+                    .filter(|a| !self.is_slack(&a.name))
+                    .filter(|a| {
+                        let task_tags = tags(&a.name);
+                        match tag.as_str() {
+                            "(untagged)" => task_tags.is_empty(),
+                            t => task_tags.contains(&t),
+                        }
+                    })
+                    .collect();
+                (tag, total, tasks)
+            })
+            .collect()
+    }
+
+    /**
+     * Work per category compared to the targets
+     *
+     * Categories are in order of first occurrence, followed by the targets without any
+     * work yet.
+     */
+    pub fn category_progress(&self, targets: &HashMap<String, Duration>) -> Vec<CategoryProgress> {
+        let mut progress: Vec<CategoryProgress> = self
+            .by_category(&HashMap::new())
+            .into_iter()
+            .map(|(category, done)| {
+                let target = targets.get(&category).copied();
+                CategoryProgress {
+                    category,
+                    done,
+                    target,
+                }
+            })
+            .collect();
+        let mut missing: Vec<(&String, &Duration)> = targets
+            .iter()
+            .filter(|(cat, _)| !progress.iter().any(|p| &p.category == *cat))
+            .collect();
+        missing.sort();
+        for (cat, target) in missing {
+            progress.push(CategoryProgress {
+                category: cat.clone(),
+                done: Duration::zero(),
+                target: Some(*target),
+            });
+        }
+        progress
+    }
+
+    // one line per category, with a progress bar for the ones with a target
+    pub fn render_category_progress(&self, targets: &HashMap<String, Duration>) -> String {
+        let mut output = String::new();
+        for p in self.category_progress(targets) {
+            let done = self.format_duration(&p.done);
+            match p.target {
+                Some(target) => {
+                    let fraction = p.done.num_seconds() as f64 / target.num_seconds() as f64;
+                    writeln!(
+                        output,
+                        "{} {:>3.0}%  {}: {done} of {}{}",
+                        percentage_bar(fraction, 10),
+                        fraction * 100.0,
+                        p.category,
+                        self.format_duration(&target),
+                        if p.is_over() { " ⚠ over target" } else { "" }
+                    )
+                }
+                None => writeln!(output, "{:17}{}: {done}", "", p.category),
+            }
+            .expect("failed to format category progress");
+        }
+        output
+    }
+
+    // like Display, but with the work per category instead of the activities
+    pub fn render_by_category(&self, merge: &HashMap<String, String>) -> String {
+        self.render_groups(self.by_category(merge))
+    }
+
+    // like Display, but with the work per tag instead of the activities
+    pub fn render_by_tag(&self, split: bool) -> String {
+        self.render_groups(self.by_tag(split))
+    }
+
+    // like render_by_tag(), with the activities of each tag indented below it
+    pub fn render_tag_summary(&self) -> String {
+        let summary = self.by_tag_with_tasks();
+        let width = hours_width(summary.iter().map(|(_, d, _)| d));
+        let mut output = String::new();
+        for (tag, duration, tasks) in &summary {
+            let a = Activity {
+                name: tag.clone(),
+                duration: *duration,
+            };
+            writeln!(output, "{}", self.display_activity(&a, ": ", width))
+                .expect("failed to format tag");
+            for task in tasks {
+                let line =
+                    task.display(&self.category_separator, self.compact, self.seconds, width);
+                writeln!(output, "    {line}").expect("failed to format task");
+            }
+        }
+        self.write_totals(&mut output)
+            .expect("failed to format totals");
+        output
+    }
+
+    /**
+     * Work durations by the text of the pattern's group, in order of first occurrence
+     *
+     * This extracts e.g. ticket numbers with "#(\d+)". Activities which don't match are
+     * counted as "(none)".
+     */
+    pub fn by_regex_group(&self, pattern: &Pattern) -> Vec<(String, Duration)> {
+        let mut groups: Vec<(String, Duration)> = Vec::new();
+        for a in self.activities.iter().filter(|a| !self.is_slack(&a.name)) {
+            let group = pattern
+                .captures(&a.name)
+                .unwrap_or_else(|| "(none)".to_string());
+            match groups.iter_mut().find(|(g, _)| *g == group) {
+                Some((_, d)) => *d += a.duration,
+                None => groups.push((group, a.duration)),
+            }
+        }
+        groups
+    }
+
+    // like Display, but with the work per group of the pattern instead of the activities
+    pub fn render_by_regex_group(&self, pattern: &Pattern) -> String {
+        self.render_groups(self.by_regex_group(pattern))
+    }
+
+    fn render_groups(&self, groups: Vec<(String, Duration)>) -> String {
+        let width = hours_width(groups.iter().map(|(_, d)| d));
+        let mut output = String::new();
+        for (name, duration) in groups {
+            let a = Activity { name, duration };
+            writeln!(output, "{}", self.display_activity(&a, ": ", width))
+                .expect("failed to format group");
         }
+        self.write_totals(&mut output)
+            .expect("failed to format totals");
+        output
+    }
+
+    // durations of slack activities by reason, without the ** marker or slack prefix
+    pub fn slack_breakdown(&self) -> Vec<(String, Duration)> {
+        let mut breakdown: Vec<(String, Duration)> = Vec::new();
+
+        for a in self.activities.iter().filter(|a| self.is_slack(&a.name)) {
+            let label = match a.name.strip_prefix(self.slack_prefix.as_str()) {
+                Some(reason) if !a.name.contains("**") => reason.to_string(),
+                _ => a.name.replacen("**", "", 1),
+            };
+            let label = match label.trim() {
+                "" => "unnamed".to_string(),
+                l => l.to_string(),
+            };
+            match breakdown.iter_mut().find(|(l, _)| *l == label) {
+                Some((_, d)) => *d += a.duration,
+                None => breakdown.push((label, a.duration)),
+            }
+        }
+
+        breakdown
     }
 }
 
 impl fmt::Display for Activities {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.hours_width();
         for a in &self.activities {
-            writeln!(f, "{a}")?;
+            writeln!(
+                f,
+                "{}",
+                self.display_activity(a, &self.category_separator, width)
+            )?;
         }
+        self.write_totals(f)
+    }
+}
+
+impl Activities {
+    fn write_totals(&self, f: &mut impl fmt::Write) -> fmt::Result {
         writeln!(f, "-------")?;
         writeln!(
             f,
-            "Total work done: {} h {} min",
-            self.total_work.num_hours(),
-            self.total_work.num_minutes() % 60
+            "Total work done: {}",
+            self.format_duration(&self.total_work)
         )?;
-        writeln!(
+        write!(
             f,
-            "Total slacking: {} h {} min",
-            self.total_slack.num_hours(),
-            self.total_slack.num_minutes() % 60
-        )
+            "Total slacking: {}",
+            self.format_duration(&self.total_slack)
+        )?;
+        let breakdown = self.slack_breakdown();
+        // only interesting with different reasons
+        if breakdown.len() > 1 {
+            let reasons: Vec<String> = breakdown
+                .iter()
+                .map(|(label, d)| format!("{label}: {}", self.format_duration(d)))
+                .collect();
+            write!(f, " ({})", reasons.join(", "))?;
+        }
+        writeln!(f)
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::store::Timelog;
-    use chrono::NaiveDate;
-    use pretty_assertions::assert_eq;
+// collect the output of a write_* function
+pub(crate) fn render(write: impl FnOnce(&mut Vec<u8>) -> io::Result<()>) -> String {
+    let mut output = Vec::new();
+    write(&mut output).expect("failed to render");
+    String::from_utf8(output).expect("rendered invalid UTF-8")
+}
 
-    #[test]
-    fn test_activity_display() {
-        assert_eq!(
-            &format!(
-                "{}",
-                Activity {
-                    name: "code this".to_string(),
-                    duration: Duration::minutes(3)
-                }
-            ),
+/**
+ * Write the activities of each day in a separate section, followed by the grand total
+ */
+pub fn write_multi_day(
+    entries: &[Entry],
+    config: &Config,
+    w: &mut impl io::Write,
+) -> io::Result<()> {
+    let mut total_work = Duration::minutes(0);
+    let mut total_slack = Duration::minutes(0);
+    let format_duration = if config.compact_durations {
+        format_hm_compact
+    } else {
+        format_hm
+    };
+
+    for day in Timelog::split_days(entries) {
+        let a = Activities::new_from_entries_with_config(day, config);
+        total_work += a.total_work;
+        total_slack += a.total_slack;
+        writeln!(
+            w,
+            "{}:\n{a}",
+            config.format_date(&clock::logical_date(day[0].stop))
+        )?;
+    }
+
+    writeln!(w, "=======")?;
+    writeln!(w, "Total work done: {}", format_duration(&total_work))?;
+    writeln!(w, "Total slacking: {}", format_duration(&total_slack))
+}
+
+pub fn render_multi_day(entries: &[Entry], config: &Config) -> String {
+    render(|w| write_multi_day(entries, config, w))
+}
+
+/**
+ * Write the chronological blocks, with a blank line between days
+ *
+ * A task which directly follows slack after a block of the same task gets marked as
+ * resumed after a break.
+ */
+pub fn write_timeline(
+    entries: &[Entry],
+    config: &Config,
+    w: &mut impl io::Write,
+) -> io::Result<()> {
+    // last stop time of every task, to link continuations to their previous block
+    let mut last_stop: HashMap<String, NaiveDateTime> = HashMap::new();
+    let mut prev: Option<NaiveDateTime> = None;
+    // last work task, and whether only slack followed it
+    let mut last_work: Option<String> = None;
+    let mut in_break = false;
+    let blocks = get_blocks(entries);
+    let durations: Vec<Duration> = blocks.iter().map(|b| b.duration()).collect();
+    let hours_width = hours_width(&durations);
+
+    for block in blocks {
+        if prev.is_some() && clock::logical_date(prev.unwrap()) != clock::logical_date(block.stop) {
+            writeln!(w)?;
+            last_stop.clear();
+            last_work = None;
+            in_break = false;
+        }
+        prev = Some(block.stop);
+
+        write!(
+            w,
+            "{}-{} {}: {}",
+            block.start.format("%H:%M"),
+            block.stop.format("%H:%M"),
+            format_hm_aligned(&block.duration(), hours_width),
+            block.task
+        )?;
+        if block.continuation {
+            match last_stop.get(&block.task) {
+                Some(t) => write!(w, " (cont. from {})", t.format("%H:%M"))?,
+                None => write!(w, " (cont.)")?,
+            }
+        }
+        if config.is_slack(&block.task) {
+            in_break = last_work.is_some();
+        } else {
+            if in_break && last_work.as_ref() == Some(&block.task) {
+                write!(w, " (resumed after break)")?;
+            }
+            last_work = Some(block.task.clone());
+            in_break = false;
+        }
+        writeln!(w)?;
+        last_stop.insert(block.task, block.stop);
+    }
+
+    Ok(())
+}
+
+pub fn render_timeline(entries: &[Entry], config: &Config) -> String {
+    render(|w| write_timeline(entries, config, w))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pattern::Pattern;
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_activity_display() {
+        assert_eq!(
+            &format!(
+                "{}",
+                Activity {
+                    name: "code this".to_string(),
+                    duration: Duration::minutes(3)
+                }
+            ),
             " 0 h  3 min: code this"
         );
         assert_eq!(
@@ -177,56 +1136,1066 @@ mod tests {
     }
 
     #[test]
-    fn test_activities_empty() {
-        let a = Activities::new_from_entries(&[]);
-        assert_eq!(a.activities.len(), 0);
-        assert_eq!(a.total_work, Duration::minutes(0));
-        assert_eq!(a.total_slack, Duration::minutes(0));
+    fn test_activities_empty() {
+        let a = Activities::new_from_entries(&[]);
+        assert_eq!(a.activities.len(), 0);
+        assert_eq!(a.total_work, Duration::minutes(0));
+        assert_eq!(a.total_slack, Duration::minutes(0));
+    }
+
+    const DAILY: &str = "
+2022-06-10 07:00: arrived
+2022-06-10 08:45: gtimelog: code
+2022-06-10 09:00: ** tea
+2022-06-10 12:05: gtimelog: code
+2022-06-10 12:35: customer joe: inquiry
+2022-06-10 13:15: ** lunch
+2022-06-10 14:00: code
+2022-06-10 15:00: bug triage
+2022-06-10 15:10: ** tea
+2022-06-10 16:00: customer joe: support
+";
+
+    #[test]
+    fn test_activities_daily() {
+        let tl = Timelog::new_from_string(DAILY);
+
+        let a = Activities::new_from_entries(
+            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1),
+        );
+        assert_eq!(a.total_work, Duration::minutes(475));
+        assert_eq!(a.total_slack, Duration::minutes(65));
+        assert_eq!(a.work_minutes().to_string(), "475");
+        assert_eq!(a.slack_minutes().to_string(), "65");
+        assert_eq!(a.activities.len(), 7);
+        assert_eq!(a.activities[0].name, "gtimelog: code");
+        // first block 1:45, second block 3:05
+        assert_eq!(
+            a.activities[0].duration,
+            Duration::hours(4) + Duration::minutes(50)
+        );
+
+        assert_eq!(
+            format!("{}", a),
+            " 4 h 50 min: gtimelog: code
+ 0 h 25 min: ** tea
+ 0 h 30 min: customer joe: inquiry
+ 0 h 40 min: ** lunch
+ 0 h 45 min: code
+ 1 h  0 min: bug triage
+ 0 h 50 min: customer joe: support
+-------
+Total work done: 7 h 55 min
+Total slacking: 1 h 5 min (tea: 0 h 25 min, lunch: 0 h 40 min)\n"
+        )
+    }
+
+    #[test]
+    fn test_accessors() {
+        let tl = Timelog::new_from_string(DAILY);
+        let a = Activities::new_from_entries(
+            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1),
+        );
+        // the same as "Total work done: 7 h 55 min" and "Total slacking: 1 h 5 min"
+        assert_eq!(a.total_work(), Duration::minutes(7 * 60 + 55));
+        assert_eq!(a.total_slack(), Duration::minutes(65));
+        assert_eq!(a.activities().len(), 7);
+        assert_eq!(a.activities()[0].name(), "gtimelog: code");
+        assert_eq!(a.activities()[0].duration(), Duration::minutes(4 * 60 + 50));
+        assert_eq!(a.activities()[6].name(), "customer joe: support");
+        assert_eq!(a.activities()[6].duration(), Duration::minutes(50));
+    }
+
+    #[test]
+    fn test_activities_matching() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-09 06:02: arrived
+2022-06-09 06:27: email
+2022-06-09 06:32: **tea
+2022-06-09 12:00: work
+
+2022-06-10 07:00: arrived
+2022-06-10 12:05: rtimelog: code
+2022-06-10 12:30: **lunch
+2022-06-10 14:00: rtimelog: code
+2022-06-10 15:00: bug triage
+2022-06-10 16:00: customer joe: support
+",
+        );
+        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 2);
+        let a = Activities::new_from_entries_matching(entries, "code", &Config::default());
+        assert_eq!(
+            a.to_string(),
+            " 6 h 35 min: rtimelog: code
+-------
+Total work done: 6 h 35 min
+Total slacking: 0 h 0 min
+"
+        );
+
+        // ignores case
+        let a = Activities::new_from_entries_matching(entries, "RTimelog", &Config::default());
+        assert_eq!(a.total_work(), Duration::minutes(6 * 60 + 35));
+        let a = Activities::new_from_entries_matching(entries, "T", &Config::default());
+        assert_eq!(a.activities().len(), 4);
+        assert_eq!(a.total_slack(), Duration::minutes(5));
+
+        let a = Activities::new_from_entries_matching(entries, "nothing", &Config::default());
+        assert!(a.activities().is_empty());
+        assert_eq!(a.total_work(), Duration::zero());
+    }
+
+    #[test]
+    fn test_activities_unmerged() {
+        let tl = Timelog::new_from_string(DAILY);
+        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1);
+        let a = Activities::new_from_blocks_unmerged(get_blocks(entries));
+        assert_eq!(a.activities.len(), 9);
+        assert_eq!(
+            format!("{}", a),
+            " 1 h 45 min: gtimelog: code
+ 0 h 15 min: ** tea
+ 3 h  5 min: gtimelog: code
+ 0 h 30 min: customer joe: inquiry
+ 0 h 40 min: ** lunch
+ 0 h 45 min: code
+ 1 h  0 min: bug triage
+ 0 h 10 min: ** tea
+ 0 h 50 min: customer joe: support
+-------
+Total work done: 7 h 55 min
+Total slacking: 1 h 5 min (tea: 0 h 25 min, lunch: 0 h 40 min)\n"
+        );
+    }
+
+    #[test]
+    fn test_activities_sorted() {
+        let tl = Timelog::new_from_string(DAILY);
+        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1);
+
+        let mut a = Activities::new_from_entries(entries);
+        a.sort(ActivityOrder::Duration);
+        assert_eq!(
+            format!("{}", a),
+            " 4 h 50 min: gtimelog: code
+ 1 h  0 min: bug triage
+ 0 h 50 min: customer joe: support
+ 0 h 45 min: code
+ 0 h 40 min: ** lunch
+ 0 h 30 min: customer joe: inquiry
+ 0 h 25 min: ** tea
+-------
+Total work done: 7 h 55 min
+Total slacking: 1 h 5 min (lunch: 0 h 40 min, tea: 0 h 25 min)\n"
+        );
+
+        let a = Activities::new_from_entries(entries).with_config(&Config {
+            activity_order: ActivityOrder::DurationSlackLast,
+            ..Config::default()
+        });
+        assert_eq!(
+            a.activities
+                .iter()
+                .map(|a| a.name.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "gtimelog: code",
+                "bug triage",
+                "customer joe: support",
+                "code",
+                "customer joe: inquiry",
+                "** lunch",
+                "** tea"
+            ]
+        );
+
+        // default keeps first occurrence
+        let mut a = Activities::new_from_entries(entries);
+        a.sort(ActivityOrder::FirstOccurrence);
+        assert_eq!(a.activities[1].name, "** tea");
+    }
+
+    #[test]
+    fn test_activities_filtered() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 08:45: gtimelog: code
+2022-06-10 09:00: ** tea
+2022-06-10 12:35: customer joe: inquiry
+2022-06-10 13:15: ** lunch
+2022-06-10 16:00: customer joe: support
+",
+        );
+        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1);
+
+        let p = Pattern::new("^customer .*").unwrap();
+        let a = Activities::new_from_entries_filtered(entries, |t| p.is_match(t));
+        assert_eq!(a.activities.len(), 2);
+        assert_eq!(a.activities[0].name, "customer joe: inquiry");
+        assert_eq!(a.activities[1].name, "customer joe: support");
+        assert_eq!(a.total_work, Duration::minutes(215 + 165));
+        assert_eq!(a.total_slack, Duration::minutes(0));
+
+        let p = Pattern::new("^nothing").unwrap();
+        let a = Activities::new_from_entries_filtered(entries, |t| p.is_match(t));
+        assert_eq!(a.activities.len(), 0);
+        assert_eq!(a.total_work, Duration::minutes(0));
+    }
+
+    #[test]
+    fn test_activities_normalized() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 08:00: project1:  code
+2022-06-10 09:00: ** tea
+2022-06-10 10:00: project1: code
+",
+        );
+        let a = Activities::new_from_entries(
+            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1),
+        );
+        assert_eq!(a.activities.len(), 2);
+        assert_eq!(a.activities[0].name, "project1: code");
+        assert_eq!(a.activities[0].duration, Duration::hours(2));
+    }
+
+    #[test]
+    fn test_time_window() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 09:00: morning
+2022-06-10 13:30: straddling
+2022-06-10 15:00: afternoon
+",
+        );
+        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1);
+        let mut window = TimeWindow {
+            after: NaiveTime::from_hms_opt(12, 0, 0),
+            before: None,
+            prorate: false,
+        };
+
+        // whole blocks which stop inside the window
+        let a = Activities::new_from_blocks(
+            get_blocks(entries)
+                .into_iter()
+                .filter_map(|b| window.apply(b)),
+        );
+        assert_eq!(a.activities.len(), 2);
+        assert_eq!(a.activities[0].name, "straddling");
+        assert_eq!(a.activities[0].duration, Duration::minutes(270));
+        assert_eq!(a.activities[1].name, "afternoon");
+        assert_eq!(a.activities[1].duration, Duration::minutes(90));
+
+        // only the part inside the window
+        window.prorate = true;
+        let a = Activities::new_from_blocks(
+            get_blocks(entries)
+                .into_iter()
+                .filter_map(|b| window.apply(b)),
+        );
+        assert_eq!(a.activities.len(), 2);
+        assert_eq!(a.activities[0].name, "straddling");
+        assert_eq!(a.activities[0].duration, Duration::minutes(90));
+        assert_eq!(a.activities[1].duration, Duration::minutes(90));
+        assert_eq!(a.total_work, Duration::hours(3));
+
+        // completely outside
+        window.before = NaiveTime::from_hms_opt(12, 30, 0);
+        window.after = NaiveTime::from_hms_opt(9, 30, 0);
+        let morning = get_blocks(entries).remove(0);
+        assert_eq!(window.apply(morning.clone()), None);
+        window.prorate = false;
+        assert_eq!(window.apply(morning), None);
+    }
+
+    #[test]
+    fn test_time_window_virtual_midnight() {
+        clock::set_virtual_midnight(NaiveTime::from_hms_opt(4, 0, 0));
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 20:00: arrived
+2022-06-10 23:00: evening
+2022-06-11 02:00: night
+2022-06-11 03:00: late night
+",
+        );
+        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1);
+        let total = |window: &TimeWindow| {
+            Activities::new_from_blocks(
+                get_blocks(entries)
+                    .into_iter()
+                    .filter_map(|b| window.apply(b)),
+            )
+            .total_work
+        };
+
+        // the whole block after midnight, not only until 23:59:59
+        let mut window = TimeWindow {
+            after: NaiveTime::from_hms_opt(21, 0, 0),
+            before: None,
+            prorate: true,
+        };
+        assert_eq!(total(&window), Duration::hours(6));
+        window.prorate = false;
+        assert_eq!(total(&window), Duration::hours(7));
+
+        // 02:00 is after 23:00 on that day
+        window.after = NaiveTime::from_hms_opt(23, 30, 0);
+        window.before = NaiveTime::from_hms_opt(2, 15, 0);
+        assert_eq!(total(&window), Duration::hours(3));
+        window.prorate = true;
+        assert_eq!(total(&window), Duration::minutes(165));
+
+        clock::set_virtual_midnight(None);
+    }
+
+    #[test]
+    fn test_time_on_last_task() {
+        let now = NaiveDate::from_ymd_opt(2022, 6, 10)
+            .unwrap()
+            .and_hms_opt(12, 30, 0)
+            .unwrap();
+        assert_eq!(time_on_last_task(&[], now), None);
+
+        let tl = Timelog::new_from_string("2022-06-10 07:00: arrived");
+        assert_eq!(time_on_last_task(tl.get_n_days(&now.date(), 1), now), None);
+
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 09:00: code
+2022-06-10 10:00: meeting
+2022-06-10 12:00: code (cont.)
+",
+        );
+        // 2 h + 2 h earlier, plus 30 min since the last entry
+        assert_eq!(
+            time_on_last_task(tl.get_n_days(&now.date(), 1), now),
+            Some(Duration::minutes(4 * 60 + 30))
+        );
+    }
+
+    #[test]
+    fn test_status_line() {
+        let now = NaiveDate::from_ymd_opt(2022, 6, 10)
+            .unwrap()
+            .and_hms_opt(12, 30, 0)
+            .unwrap();
+        assert_eq!(
+            status_line(&[], now, false, false),
+            "no entries yet today; type command (:h for help) or entry"
+        );
+
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 09:00: code
+2022-06-10 12:00: code (cont.)
+",
+        );
+        let today = tl.get_n_days(&now.date(), 1);
+        assert_eq!(
+            status_line(today, now, false, false),
+            "0 h 30 min since last entry (5 h 30 min on code today); type command (:h for help) or entry"
+        );
+        assert_eq!(
+            status_line(today, now, true, false),
+            "30m since last entry (5h30m on code today); type command (:h for help) or entry"
+        );
+        assert_eq!(
+            status_line(today, now, true, true),
+            "30m since last entry (5h30m on code today) (paused); type command (:h for help) or entry"
+        );
+        // nothing to freeze
+        assert_eq!(
+            status_line(&[], now, false, true),
+            "no entries yet today; type command (:h for help) or entry"
+        );
+
+        assert_eq!(
+            status_line_update("1 h 0 min since last entry"),
+            "\x1b[2A\r\x1b[J1 h 0 min since last entry\n"
+        );
+    }
+
+    #[test]
+    fn test_duration_hints() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 07:50: code [~45m]
+2022-06-10 09:00: review [~15m]
+2022-06-10 09:30: email
+2022-06-10 10:00: code [~2h]
+",
+        );
+        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1);
+
+        // hints are not part of the task
+        let a = Activities::new_from_entries(entries);
+        assert_eq!(a.activities[0].name, "code");
+        assert_eq!(a.activities[0].duration, Duration::minutes(80));
+
+        // 50 min for 45 is close enough; 70 min for 15 and 30 min for 2 h are not
+        let mismatches = check_duration_hints(entries, Duration::minutes(10));
+        assert_eq!(
+            mismatches
+                .iter()
+                .map(|b| (b.stop.format("%H:%M").to_string(), b.hint.unwrap()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("09:00".to_string(), Duration::minutes(15)),
+                ("10:00".to_string(), Duration::hours(2)),
+            ]
+        );
+
+        assert_eq!(check_duration_hints(entries, Duration::hours(2)), vec![]);
+    }
+
+    #[test]
+    fn test_category() {
+        assert_eq!(category("code"), None);
+        assert_eq!(category("rtimelog: code"), Some("rtimelog"));
+        assert_eq!(
+            category("customer joe: support: call"),
+            Some("customer joe")
+        );
+    }
+
+    #[test]
+    fn test_is_meeting() {
+        assert!(is_meeting("meeting", "meeting"));
+        assert!(is_meeting("meeting: standup", "meeting"));
+        assert!(is_meeting("standup -- team meeting", "meeting"));
+        assert!(!is_meeting("standup", "meeting"));
+        assert!(!is_meeting("prepare meeting", "meeting"));
+        assert!(!is_meeting("project: meeting", "meeting"));
+        assert!(is_meeting("calls: joe", "calls"));
+    }
+
+    #[test]
+    fn test_display_task() {
+        assert_eq!(display_task("code", " › "), "code");
+        assert_eq!(display_task("rtimelog: code", ": "), "rtimelog: code");
+        assert_eq!(display_task("rtimelog: code", " › "), "rtimelog › code");
+        assert_eq!(
+            display_task("customer joe: support: call", "/"),
+            "customer joe/support: call"
+        );
+    }
+
+    #[test]
+    fn test_category_separator() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 08:45: project1: code
+2022-06-10 09:00: ** tea
+",
+        );
+        let a = Activities::new_from_entries(
+            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1),
+        )
+        .with_config(&Config {
+            category_separator: " › ".to_string(),
+            ..Config::default()
+        });
+        assert_eq!(
+            format!("{a}"),
+            " 1 h 45 min: project1 › code
+ 0 h 15 min: ** tea
+-------
+Total work done: 1 h 45 min
+Total slacking: 0 h 15 min
+"
+        );
+        // categories still work
+        assert_eq!(a.category_total("project1"), Duration::minutes(105));
+        assert!(a
+            .render_focus("project1")
+            .contains(">  1 h 45 min: project1 › code\n"));
+    }
+
+    #[test]
+    fn test_leaving_marker() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 12:00: code
+2022-06-10 12:10: home
+2022-06-10 20:00: email
+2022-06-10 20:30: review
+
+2022-06-11 09:00: arrived
+2022-06-11 10:00: home
+",
+        );
+        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 11).unwrap(), 2);
+
+        // by default, "home" is an ordinary task
+        assert_eq!(get_blocks(entries).len(), 5);
+        let a = Activities::new_from_entries(entries);
+        assert_eq!(
+            a.total_work,
+            Duration::minutes(5 * 60 + 10 + 7 * 60 + 50 + 30 + 60)
+        );
+
+        // home and the gap after it are not attributed
+        let blocks = get_blocks_with_leaving(entries, Some("home"));
+        assert_eq!(
+            blocks.iter().map(|b| b.task.as_str()).collect::<Vec<_>>(),
+            vec!["code", "review"]
+        );
+        let config = Config {
+            leaving_marker: Some("home".to_string()),
+            ..Config::default()
+        };
+        let a = Activities::new_from_entries_with_config(entries, &config);
+        assert_eq!(a.total_work, Duration::minutes(5 * 60 + 30));
+        assert_eq!(a.total_slack, Duration::zero());
+    }
+
+    #[test]
+    fn test_slack_prefix() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 09:00: code
+2022-06-10 09:15: * tea
+2022-06-10 10:00: fix *important* bug
+2022-06-10 12:00: ** lunch
+",
+        );
+        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1);
+
+        // by default, only ** is slack
+        let a = Activities::new_from_entries(entries);
+        assert_eq!(a.total_work, Duration::minutes(120 + 15 + 45));
+        assert_eq!(a.total_slack, Duration::minutes(120));
+
+        let config = Config {
+            slack_prefix: "*".to_string(),
+            activity_order: ActivityOrder::DurationSlackLast,
+            ..Config::default()
+        };
+        let a = Activities::new_from_entries_with_config(entries, &config);
+        // only at the start, ** still is slack
+        assert_eq!(a.total_work, Duration::minutes(120 + 45));
+        assert_eq!(a.total_slack, Duration::minutes(120 + 15));
+        assert_eq!(
+            a.slack_breakdown(),
+            vec![
+                ("lunch".to_string(), Duration::minutes(120)),
+                ("tea".to_string(), Duration::minutes(15))
+            ]
+        );
+        assert_eq!(a.activities.last().unwrap().name, "* tea");
+        assert_eq!(a.by_category(&HashMap::new()).len(), 1);
+
+        // rounding keeps the split
+        let mut a = a;
+        a.round(Duration::minutes(60), RoundingMode::Up);
+        assert_eq!(a.total_work, Duration::minutes(120 + 60));
+        assert_eq!(a.total_slack, Duration::minutes(120 + 60));
+    }
+
+    #[test]
+    fn test_slack_categories() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 09:00: project1: code
+2022-06-10 09:15: break: coffee
+2022-06-10 10:00: project1: review
+2022-06-10 12:00: ** lunch
+2022-06-10 12:10: breaking: news
+",
+        );
+        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1);
+
+        let config = Config {
+            slack_categories: vec!["break".to_string()],
+            ..Config::default()
+        };
+        let a = Activities::new_from_entries_with_config(entries, &config);
+        // only the exact category, not "breaking"
+        assert_eq!(a.total_work, Duration::minutes(120 + 45 + 10));
+        assert_eq!(a.total_slack, Duration::minutes(15 + 120));
+        assert_eq!(
+            a.slack_breakdown(),
+            vec![
+                ("break: coffee".to_string(), Duration::minutes(15)),
+                ("lunch".to_string(), Duration::minutes(120))
+            ]
+        );
+        assert_eq!(
+            a.by_category(&HashMap::new()),
+            vec![
+                ("project1".to_string(), Duration::minutes(165)),
+                ("breaking".to_string(), Duration::minutes(10))
+            ]
+        );
+
+        // not slack by default
+        let a = Activities::new_from_entries_with_config(entries, &Config::default());
+        assert_eq!(a.total_slack, Duration::minutes(120));
+
+        // the config agrees with the activities
+        assert!(config.is_slack("break: coffee"));
+        assert!(config.is_slack("** lunch"));
+        assert!(!config.is_slack("breaking: news"));
+        assert!(!Config::default().is_slack("break: coffee"));
+        let tl = Timelog::new_from_string(
+            "2022-06-10 07:00: arrived\n2022-06-10 08:00: p: code\n2022-06-10 08:15: break: coffee\n2022-06-10 09:00: p: code\n",
+        );
+        assert!(render_timeline(
+            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1),
+            &config
+        )
+        .ends_with("p: code (resumed after break)\n"));
+        assert!(!render_timeline(
+            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1),
+            &Config::default()
+        )
+        .contains("resumed"));
+    }
+
+    #[test]
+    fn test_by_regex_group() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 08:00: arrived
+2022-06-10 09:00: fix #123: crash
+2022-06-10 09:30: review #45
+2022-06-10 10:00: ** tea #123
+2022-06-10 11:00: fix #123: crash (cont.)
+2022-06-10 11:15: email
+2022-06-10 11:45: #45 follow-up
+",
+        );
+        let a = Activities::new_from_entries(
+            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1),
+        );
+        let tickets = Pattern::new("#(\\d+)").unwrap();
+        assert_eq!(
+            a.by_regex_group(&tickets),
+            vec![
+                ("123".to_string(), Duration::minutes(120)),
+                ("45".to_string(), Duration::minutes(60)),
+                ("(none)".to_string(), Duration::minutes(15)),
+            ]
+        );
+        assert_eq!(
+            a.render_by_regex_group(&tickets),
+            " 2 h  0 min: 123
+ 1 h  0 min: 45
+ 0 h 15 min: (none)
+-------
+Total work done: 3 h 15 min
+Total slacking: 0 h 30 min
+"
+        );
+
+        // the full syntax of the regex crate
+        #[cfg(feature = "regex")]
+        assert_eq!(
+            a.by_regex_group(&Pattern::new("^(?:fix )?#([0-9]{2})\\b").unwrap()),
+            vec![
+                ("(none)".to_string(), Duration::minutes(165)),
+                ("45".to_string(), Duration::minutes(30)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_by_category() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 08:00: frontend: fix button
+2022-06-10 08:15: ** tea
+2022-06-10 09:30: backend: api
+2022-06-10 10:00: email
+2022-06-10 10:20: frontend: review
+2022-06-10 11:00: ops: deploy
+",
+        );
+        let a = Activities::new_from_entries(
+            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1),
+        );
+
+        assert_eq!(
+            a.by_category(&HashMap::new()),
+            vec![
+                ("frontend".to_string(), Duration::minutes(80)),
+                ("backend".to_string(), Duration::minutes(75)),
+                ("uncategorized".to_string(), Duration::minutes(30)),
+                ("ops".to_string(), Duration::minutes(40)),
+            ]
+        );
+
+        let merge = HashMap::from([
+            ("frontend".to_string(), "dev".to_string()),
+            ("backend".to_string(), "dev".to_string()),
+        ]);
+        assert_eq!(
+            a.by_category(&merge),
+            vec![
+                ("dev".to_string(), Duration::minutes(155)),
+                ("uncategorized".to_string(), Duration::minutes(30)),
+                ("ops".to_string(), Duration::minutes(40)),
+            ]
+        );
+        assert_eq!(
+            a.render_by_category(&merge),
+            " 2 h 35 min: dev
+ 0 h 30 min: uncategorized
+ 0 h 40 min: ops
+-------
+Total work done: 3 h 45 min
+Total slacking: 0 h 15 min
+"
+        );
+    }
+
+    #[test]
+    fn test_non_contiguous_days() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-01 08:00: arrived
+2022-06-01 10:00: customer: code
+
+2022-06-02 08:00: arrived
+2022-06-02 16:00: other: code
+
+2022-06-03 09:00: arrived
+2022-06-03 09:30: customer: review
+2022-06-03 10:00: ** tea
+
+2022-06-09 13:00: arrived
+2022-06-09 14:15: customer: code
+",
+        );
+        // the first entry of every day only provides the start time, so the days can be combined
+        let entries: Vec<Entry> = [(2022, 6, 1), (2022, 6, 3), (2022, 6, 9)]
+            .into_iter()
+            .flat_map(|(y, m, d)| tl.get_day(&NaiveDate::from_ymd_opt(y, m, d).unwrap()))
+            .cloned()
+            .collect();
+        let a = Activities::new_from_entries(&entries);
+        assert_eq!(
+            a.by_task(),
+            vec![
+                ("customer: code".to_string(), Duration::minutes(195)),
+                ("customer: review".to_string(), Duration::minutes(30)),
+                ("** tea".to_string(), Duration::minutes(30)),
+            ]
+        );
+        assert_eq!(a.total_work, Duration::minutes(225));
+    }
+
+    #[test]
+    fn test_category_progress() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 10:00: project1: code
+2022-06-10 12:30: meeting: planning
+2022-06-10 12:45: ** lunch
+2022-06-10 13:15: email
+",
+        );
+        let a = Activities::new_from_entries(
+            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1),
+        );
+        let targets = HashMap::from([
+            ("project1".to_string(), Duration::hours(4)),
+            ("meeting".to_string(), Duration::hours(2)),
+            ("project2".to_string(), Duration::hours(1)),
+        ]);
+
+        let progress = a.category_progress(&targets);
+        assert_eq!(
+            progress,
+            vec![
+                CategoryProgress {
+                    category: "project1".to_string(),
+                    done: Duration::hours(3),
+                    target: Some(Duration::hours(4)),
+                },
+                CategoryProgress {
+                    category: "meeting".to_string(),
+                    done: Duration::minutes(150),
+                    target: Some(Duration::hours(2)),
+                },
+                CategoryProgress {
+                    category: "uncategorized".to_string(),
+                    done: Duration::minutes(30),
+                    target: None,
+                },
+                CategoryProgress {
+                    category: "project2".to_string(),
+                    done: Duration::zero(),
+                    target: Some(Duration::hours(1)),
+                },
+            ]
+        );
+        // under, over, no target, nothing yet
+        assert_eq!(
+            progress.iter().map(|p| p.is_over()).collect::<Vec<_>>(),
+            vec![false, true, false, false]
+        );
+
+        assert_eq!(
+            a.render_category_progress(&targets),
+            "████████░░  75%  project1: 3 h 0 min of 4 h 0 min
+██████████ 125%  meeting: 2 h 30 min of 2 h 0 min ⚠ over target
+                 uncategorized: 0 h 30 min
+░░░░░░░░░░   0%  project2: 0 h 0 min of 1 h 0 min
+"
+        );
+        // no targets, no output needed
+        assert_eq!(
+            Activities::new_from_entries(&[]).category_progress(&HashMap::new()),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_by_category_case_insensitive() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 08:00: Project1: code
+2022-06-10 08:30: project1: code
+2022-06-10 09:00: PROJECT1: review
+2022-06-10 09:20: Frontend: fix button
+2022-06-10 09:30: frontend: fix button
+",
+        );
+        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1);
+        let merge = HashMap::from([("frontend".to_string(), "dev".to_string())]);
+
+        // case sensitive by default
+        let a = Activities::new_from_entries(entries);
+        assert_eq!(
+            a.by_category(&merge),
+            vec![
+                ("Project1".to_string(), Duration::minutes(60)),
+                ("project1".to_string(), Duration::minutes(30)),
+                ("PROJECT1".to_string(), Duration::minutes(30)),
+                ("Frontend".to_string(), Duration::minutes(20)),
+                ("dev".to_string(), Duration::minutes(10)),
+            ]
+        );
+
+        let config = Config {
+            category_case_insensitive: true,
+            ..Default::default()
+        };
+        let a = Activities::new_from_entries(entries).with_config(&config);
+        assert_eq!(
+            a.by_category(&merge),
+            vec![
+                ("Project1".to_string(), Duration::minutes(120)),
+                ("dev".to_string(), Duration::minutes(30)),
+            ]
+        );
+        // tasks stay distinct
+        assert_eq!(
+            a.by_task(),
+            vec![
+                ("Project1: code".to_string(), Duration::minutes(60)),
+                ("project1: code".to_string(), Duration::minutes(30)),
+                ("PROJECT1: review".to_string(), Duration::minutes(30)),
+                ("Frontend: fix button".to_string(), Duration::minutes(20)),
+                ("frontend: fix button".to_string(), Duration::minutes(10)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_by_tag() {
+        assert_eq!(tags("code"), Vec::<&str>::new());
+        assert_eq!(tags("fix server -- sysadmin www"), vec!["sysadmin", "www"]);
+        assert_eq!(tags("fix server --"), Vec::<&str>::new());
+
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 08:40: fix server -- sysadmin www dns
+2022-06-10 09:00: ** tea
+2022-06-10 09:30: update site -- www
+2022-06-10 10:00: email
+",
+        );
+        let a = Activities::new_from_entries(
+            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1),
+        );
+
+        // every tag gets the whole block
+        assert_eq!(
+            a.by_tag(false),
+            vec![
+                ("sysadmin".to_string(), Duration::minutes(100)),
+                ("www".to_string(), Duration::minutes(130)),
+                ("dns".to_string(), Duration::minutes(100)),
+                ("(untagged)".to_string(), Duration::minutes(30)),
+            ]
+        );
+
+        // 100 min across three tags: 33 each, remainder of 1 to the first
+        let split = a.by_tag(true);
+        assert_eq!(
+            split,
+            vec![
+                ("sysadmin".to_string(), Duration::minutes(34)),
+                ("www".to_string(), Duration::minutes(33 + 30)),
+                ("dns".to_string(), Duration::minutes(33)),
+                ("(untagged)".to_string(), Duration::minutes(30)),
+            ]
+        );
+        assert_eq!(
+            split.iter().map(|(_, d)| *d).sum::<Duration>(),
+            a.total_work
+        );
+    }
+
+    #[test]
+    fn test_tag_summary() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-06 07:00: arrived
+2022-06-06 09:00: fix server -- sysadmin www
+2022-06-06 09:30: ** tea
+2022-06-06 10:00: email
+
+2022-06-08 07:00: arrived
+2022-06-08 08:00: update site -- www
+2022-06-08 09:00: fix server -- sysadmin www
+2022-06-08 12:00: backup -- sysadmin
+
+2022-06-10 07:00: arrived
+2022-06-10 07:30: email
+2022-06-10 08:00: update site -- www
+",
+        );
+        let a = Activities::new_from_entries(
+            tl.get_n_weeks(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1),
+        );
+
+        let summary: Vec<(String, Duration, Vec<&str>)> = a
+            .by_tag_with_tasks()
+            .into_iter()
+            .map(|(tag, d, tasks)| {
+                let tasks = tasks.into_iter().map(|t| t.name()).collect();
+                (tag, d, tasks)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "sysadmin".to_string(),
+                    Duration::minutes(360),
+                    vec!["fix server -- sysadmin www", "backup -- sysadmin"]
+                ),
+                (
+                    "www".to_string(),
+                    Duration::minutes(270),
+                    vec!["fix server -- sysadmin www", "update site -- www"]
+                ),
+                (
+                    "(untagged)".to_string(),
+                    Duration::minutes(60),
+                    vec!["email"]
+                ),
+            ]
+        );
+
+        assert_eq!(
+            a.render_tag_summary(),
+            " 6 h  0 min: sysadmin
+     3 h  0 min: fix server -- sysadmin www
+     3 h  0 min: backup -- sysadmin
+ 4 h 30 min: www
+     3 h  0 min: fix server -- sysadmin www
+     1 h 30 min: update site -- www
+ 1 h  0 min: (untagged)
+     1 h  0 min: email
+-------
+Total work done: 8 h 30 min
+Total slacking: 0 h 30 min
+"
+        );
+    }
+
+    #[test]
+    fn test_focus() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 08:45: project1: code
+2022-06-10 09:00: ** tea
+2022-06-10 12:05: project1: review
+2022-06-10 12:35: project2: code
+",
+        );
+        let a = Activities::new_from_entries(
+            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1),
+        );
+        assert_eq!(a.category_total("project1"), Duration::minutes(105 + 185));
+        assert_eq!(a.category_total("project3"), Duration::minutes(0));
+        assert_eq!(
+            a.focus_header("project1"),
+            "Focus on project1: 4 h 50 min of 5 h 20 min work"
+        );
+        assert_eq!(
+            a.render_focus("project1"),
+            "Focus on project1: 4 h 50 min of 5 h 20 min work
+
+>  1 h 45 min: project1: code
+   0 h 15 min: ** tea
+>  3 h  5 min: project1: review
+   0 h 30 min: project2: code
+-------
+Total work done: 5 h 20 min
+Total slacking: 0 h 15 min
+"
+        );
     }
 
     #[test]
-    fn test_activities_daily() {
+    fn test_slack_breakdown() {
+        assert_eq!(Activities::new_from_entries(&[]).slack_breakdown(), vec![]);
+
         let tl = Timelog::new_from_string(
             "
 2022-06-10 07:00: arrived
-2022-06-10 08:45: gtimelog: code
+2022-06-10 08:45: code
 2022-06-10 09:00: ** tea
-2022-06-10 12:05: gtimelog: code
-2022-06-10 12:35: customer joe: inquiry
-2022-06-10 13:15: ** lunch
-2022-06-10 14:00: code
-2022-06-10 15:00: bug triage
-2022-06-10 15:10: ** tea
-2022-06-10 16:00: customer joe: support
+2022-06-10 12:05: code
+2022-06-10 12:45: ** lunch
+2022-06-10 15:00: **tea
+2022-06-10 15:10: **
 ",
         );
-
         let a = Activities::new_from_entries(
             tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1),
         );
-        assert_eq!(a.total_work, Duration::minutes(475));
-        assert_eq!(a.total_slack, Duration::minutes(65));
-        assert_eq!(a.activities.len(), 7);
-        assert_eq!(a.activities[0].name, "gtimelog: code");
-        // first block 1:45, second block 3:05
         assert_eq!(
-            a.activities[0].duration,
-            Duration::hours(4) + Duration::minutes(50)
+            a.slack_breakdown(),
+            vec![
+                ("tea".to_string(), Duration::minutes(15 + 135)),
+                ("lunch".to_string(), Duration::minutes(40)),
+                ("unnamed".to_string(), Duration::minutes(10)),
+            ]
         );
-
-        assert_eq!(
-            format!("{}", a),
-            " 4 h 50 min: gtimelog: code
- 0 h 25 min: ** tea
- 0 h 30 min: customer joe: inquiry
- 0 h 40 min: ** lunch
- 0 h 45 min: code
- 1 h  0 min: bug triage
- 0 h 50 min: customer joe: support
--------
-Total work done: 7 h 55 min
-Total slacking: 1 h 5 min\n"
-        )
     }
 
     #[test]
@@ -272,6 +2241,535 @@ Total slacking: 1 h 5 min\n"
 -------
 Total work done: 3 h 0 min
 Total slacking: 0 h 20 min
+"
+        );
+    }
+
+    #[test]
+    fn test_render_multi_day() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-09 06:02: arrived
+2022-06-09 06:27: email
+2022-06-09 06:32: **tea
+2022-06-09 12:00: work
+
+2022-06-10 07:00: arrived
+2022-06-10 12:05: rtimelog: code
+2022-06-10 12:30: **lunch
+2022-06-10 14:00: rtimelog: code
+2022-06-10 15:00: bug triage
+2022-06-10 16:00: customer joe: support
+",
+        );
+
+        assert_eq!(
+            render_multi_day(
+                tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 2),
+                &Config::default()
+            ),
+            "Thursday, 2022-06-09:
+ 0 h 25 min: email
+ 0 h  5 min: **tea
+ 5 h 28 min: work
+-------
+Total work done: 5 h 53 min
+Total slacking: 0 h 5 min
+
+Friday, 2022-06-10:
+ 6 h 35 min: rtimelog: code
+ 0 h 25 min: **lunch
+ 1 h  0 min: bug triage
+ 1 h  0 min: customer joe: support
+-------
+Total work done: 8 h 35 min
+Total slacking: 0 h 25 min
+
+=======
+Total work done: 14 h 28 min
+Total slacking: 0 h 30 min
+"
+        );
+
+        // custom date format only affects the display
+        let config = Config {
+            date_format: "%a %-d %b".to_string(),
+            ..Config::default()
+        };
+        let output = render_multi_day(
+            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 2),
+            &config,
+        );
+        assert!(output.starts_with("Thu 9 Jun:\n 0 h 25 min: email\n"));
+        assert!(output.contains("\nFri 10 Jun:\n"));
+        assert_eq!(
+            tl.get_all().next().unwrap().to_string(),
+            "2022-06-09 06:02: arrived"
+        );
+
+        assert_eq!(
+            render_multi_day(&[], &Config::default()),
+            "=======
+Total work done: 0 h 0 min
+Total slacking: 0 h 0 min
+"
+        );
+    }
+
+    #[test]
+    fn test_render_multi_day_weeks() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-01 06:00: arrived
+2022-06-01 07:00: workw1
+2022-06-01 07:10: ** tea
+
+2022-06-03 06:00: arrived
+2022-06-03 07:00: workw1
+2022-06-03 07:10: ** tea
+
+2022-06-08 06:00: arrived
+2022-06-08 07:00: workw2
+2022-06-08 07:10: ** tea
+
+2022-06-09 06:00: arrived
+2022-06-09 07:00: workw2
+
+2022-06-10 06:00: arrived
+2022-06-10 07:00: workw2
+",
+        );
+        let entries = tl.get_n_weeks(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 2);
+        let output = render_multi_day(entries, &Config::default());
+
+        // one section per day with entries, days without any are skipped
+        let headers: Vec<&str> = output.lines().filter(|l| l.ends_with(':')).collect();
+        assert_eq!(
+            headers,
+            [
+                "Wednesday, 2022-06-01:",
+                "Friday, 2022-06-03:",
+                "Wednesday, 2022-06-08:",
+                "Thursday, 2022-06-09:",
+                "Friday, 2022-06-10:"
+            ]
+        );
+        assert!(output.starts_with(
+            "Wednesday, 2022-06-01:
+ 1 h  0 min: workw1
+ 0 h 10 min: ** tea
+-------
+Total work done: 1 h 0 min
+Total slacking: 0 h 10 min
+
+"
+        ));
+        assert!(output.contains(
+            "Thursday, 2022-06-09:
+ 1 h  0 min: workw2
+-------
+Total work done: 1 h 0 min
+Total slacking: 0 h 0 min
+"
+        ));
+
+        // grand total is the sum of the days, and the same as for the week all together
+        let days: Vec<Activities> = Timelog::split_days(entries)
+            .map(Activities::new_from_entries)
+            .collect();
+        let work: Duration = days.iter().map(Activities::total_work).sum();
+        let slack: Duration = days.iter().map(Activities::total_slack).sum();
+        assert_eq!(work, Duration::hours(5));
+        assert_eq!(slack, Duration::minutes(30));
+        let all = Activities::new_from_entries(entries);
+        assert_eq!(all.total_work(), work);
+        assert_eq!(all.total_slack(), slack);
+        assert!(output.ends_with(
+            "=======
+Total work done: 5 h 0 min
+Total slacking: 0 h 30 min
+"
+        ));
+    }
+
+    #[test]
+    fn test_continuation() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 09:00: code
+2022-06-10 10:00: meeting
+2022-06-10 11:30: code (cont.)
+2022-06-10 12:00: review (cont.)
+",
+        );
+        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1);
+
+        let a = Activities::new_from_entries(entries);
+        assert_eq!(a.activities.len(), 3);
+        assert_eq!(a.activities[0].name, "code");
+        assert_eq!(
+            a.activities[0].duration,
+            Duration::hours(3) + Duration::minutes(30)
+        );
+
+        assert_eq!(
+            render_timeline(entries, &Config::default()),
+            "07:00-09:00  2 h  0 min: code
+09:00-10:00  1 h  0 min: meeting
+10:00-11:30  1 h 30 min: code (cont. from 09:00)
+11:30-12:00  0 h 30 min: review (cont.)
+"
+        );
+    }
+
+    #[test]
+    fn test_resumed_after_break() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-09 07:00: arrived
+2022-06-09 09:00: code
+2022-06-09 09:15: ** tea
+
+2022-06-10 07:00: arrived
+2022-06-10 09:00: code
+2022-06-10 09:15: ** tea
+2022-06-10 09:20: **
+2022-06-10 11:00: code
+2022-06-10 12:00: ** lunch
+2022-06-10 13:00: meeting
+2022-06-10 14:00: ** tea
+2022-06-10 15:00: code (cont.)
+",
+        );
+        assert_eq!(
+            render_timeline(
+                tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 2),
+                &Config::default()
+            ),
+            "07:00-09:00  2 h  0 min: code
+09:00-09:15  0 h 15 min: ** tea
+
+07:00-09:00  2 h  0 min: code
+09:00-09:15  0 h 15 min: ** tea
+09:15-09:20  0 h  5 min: **
+09:20-11:00  1 h 40 min: code (resumed after break)
+11:00-12:00  1 h  0 min: ** lunch
+12:00-13:00  1 h  0 min: meeting
+13:00-14:00  1 h  0 min: ** tea
+14:00-15:00  1 h  0 min: code (cont. from 11:00)
+"
+        );
+    }
+
+    #[test]
+    fn test_write_sinks() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 09:00: code
+",
+        );
+        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1);
+
+        let mut buf: Vec<u8> = Vec::new();
+        write_timeline(entries, &Config::default(), &mut buf).unwrap();
+        assert_eq!(buf, b"07:00-09:00  2 h  0 min: code\n");
+
+        let s: String = render_timeline(entries, &Config::default());
+        assert_eq!(s.as_bytes(), buf);
+
+        let mut buf: Vec<u8> = Vec::new();
+        write_multi_day(entries, &Config::default(), &mut buf).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            render_multi_day(entries, &Config::default())
+        );
+    }
+
+    #[test]
+    fn test_format_hm_compact() {
+        assert_eq!(format_hm_compact(&Duration::minutes(180)), "3h");
+        assert_eq!(format_hm_compact(&Duration::minutes(45)), "45m");
+        assert_eq!(format_hm_compact(&Duration::minutes(185)), "3h5m");
+        assert_eq!(format_hm_compact(&Duration::zero()), "0m");
+        // verbose default is unchanged
+        assert_eq!(format_hm(&Duration::minutes(180)), "3 h 0 min");
+    }
+
+    #[test]
+    fn test_compact_durations() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 10:00: project1: code
+2022-06-10 10:45: ** tea
+2022-06-10 10:50: project2: code
+",
+        );
+        let a = Activities::new_from_entries(
+            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1),
+        )
+        .with_config(&Config {
+            compact_durations: true,
+            ..Config::default()
+        });
+        assert_eq!(
+            format!("{a}"),
+            "    3h: project1: code
+   45m: ** tea
+    5m: project2: code
+-------
+Total work done: 3h5m
+Total slacking: 45m
+"
+        );
+        assert_eq!(
+            a.focus_header("project1"),
+            "Focus on project1: 3h of 3h5m work"
+        );
+    }
+
+    #[test]
+    fn test_show_seconds() {
+        // added entries have seconds until the file gets read again
+        let at = |h, m, s| {
+            NaiveDate::from_ymd_opt(2022, 6, 10)
+                .unwrap()
+                .and_hms_opt(h, m, s)
+                .unwrap()
+        };
+        let entries = [
+            Entry {
+                stop: at(10, 0, 0),
+                task: "arrived".to_string(),
+            },
+            Entry {
+                stop: at(10, 0, 45),
+                task: "typo".to_string(),
+            },
+            Entry {
+                stop: at(10, 2, 15),
+                task: "code".to_string(),
+            },
+        ];
+        let config = Config {
+            show_seconds: true,
+            ..Config::default()
+        };
+
+        // 45 s get their seconds, 90 s stay at minute resolution
+        let a = Activities::new_from_entries(&entries).with_config(&config);
+        assert_eq!(
+            format!("{a}"),
+            " 0 h  0 min 45 s: typo
+ 0 h  1 min: code
+-------
+Total work done: 0 h 2 min
+Total slacking: 0 h 0 min
+"
+        );
+        let a = Activities::new_from_entries(&entries).with_config(&Config {
+            compact_durations: true,
+            ..config
+        });
+        assert_eq!(format!("{a}").lines().next().unwrap(), "    0m45s: typo");
+
+        // off by default
+        let a = Activities::new_from_entries(&entries).with_config(&Config::default());
+        assert_eq!(format!("{a}").lines().next().unwrap(), " 0 h  0 min: typo");
+    }
+
+    #[test]
+    fn test_format_hm_aligned() {
+        assert_eq!(format_hm_aligned(&Duration::minutes(65), 2), " 1 h  5 min");
+        assert_eq!(format_hm_aligned(&Duration::minutes(750), 2), "12 h 30 min");
+        assert_eq!(
+            format_hm_aligned(&Duration::minutes(750), 3),
+            " 12 h 30 min"
+        );
+        // too narrow width does not cut off
+        assert_eq!(format_hm_aligned(&Duration::hours(120), 2), "120 h  0 min");
+    }
+
+    #[test]
+    fn test_long_blocks() {
+        let start = NaiveDate::from_ymd_opt(2022, 6, 1)
+            .unwrap()
+            .and_hms_opt(8, 0, 0)
+            .unwrap();
+        let block = |start, hours, minutes, task: &str| Block {
+            start,
+            stop: start + Duration::hours(hours) + Duration::minutes(minutes),
+            task: task.to_string(),
+            continuation: false,
+            hint: None,
+        };
+        // forgot to log for days
+        let blocks = vec![
+            block(start, 50, 5, "forgot"),
+            block(start + Duration::hours(51), 200, 0, "forgot longer"),
+            block(start + Duration::hours(252), 0, 30, "code"),
+        ];
+        assert_eq!(hours_width([&Duration::hours(50)]), 2);
+        assert_eq!(
+            hours_width([&Duration::hours(50), &Duration::hours(200)]),
+            3
+        );
+        assert_eq!(hours_width([]), 2);
+
+        let a = Activities::new_from_blocks(blocks.clone());
+        assert_eq!(
+            a.to_string(),
+            " 50 h  5 min: forgot
+200 h  0 min: forgot longer
+  0 h 30 min: code
+-------
+Total work done: 250 h 35 min
+Total slacking: 0 h 0 min
+"
+        );
+
+        let a = Activities::new_from_blocks(blocks.clone()).with_config(&Config {
+            compact_durations: true,
+            ..Config::default()
+        });
+        assert_eq!(
+            a.to_string().lines().take(3).collect::<Vec<_>>(),
+            vec!["  50h5m: forgot", "   200h: forgot longer", "    30m: code"]
+        );
+
+        // only 50 hours still use the default width
+        let a = Activities::new_from_blocks(blocks.into_iter().take(1));
+        assert_eq!(a.to_string().lines().next().unwrap(), "50 h  5 min: forgot");
+    }
+
+    #[test]
+    fn test_round_duration() {
+        let q = Duration::minutes(15);
+        let round = |m, mode| round_duration(&Duration::minutes(m), q, mode).num_minutes();
+
+        // 7 minutes are less than half of the increment
+        assert_eq!(round(7, RoundingMode::Nearest), 0);
+        assert_eq!(round(7, RoundingMode::Up), 15);
+        assert_eq!(round(7, RoundingMode::Down), 0);
+        assert_eq!(round(8, RoundingMode::Nearest), 15);
+        assert_eq!(round(8, RoundingMode::Up), 15);
+        assert_eq!(round(8, RoundingMode::Down), 0);
+
+        // exact multiples stay
+        for mode in [RoundingMode::Nearest, RoundingMode::Up, RoundingMode::Down] {
+            assert_eq!(round(0, mode), 0);
+            assert_eq!(round(30, mode), 30);
+        }
+        assert_eq!(round(37, RoundingMode::Nearest), 30);
+        assert_eq!(round(38, RoundingMode::Nearest), 45);
+        assert_eq!(round(31, RoundingMode::Up), 45);
+        assert_eq!(round(44, RoundingMode::Down), 30);
+
+        assert_eq!("nearest".parse(), Ok(RoundingMode::Nearest));
+        assert_eq!("down".parse(), Ok(RoundingMode::Down));
+        assert!("ceil".parse::<RoundingMode>().is_err());
+        assert_eq!(RoundingMode::default(), RoundingMode::Up);
+    }
+
+    #[test]
+    fn test_activities_round() {
+        let tl = Timelog::new_from_string(DAILY);
+        let mut a = Activities::new_from_entries(
+            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1),
+        );
+        a.round(Duration::minutes(15), RoundingMode::Up);
+        assert_eq!(
+            a.to_string(),
+            " 5 h  0 min: gtimelog: code
+ 0 h 30 min: ** tea
+ 0 h 30 min: customer joe: inquiry
+ 0 h 45 min: ** lunch
+ 0 h 45 min: code
+ 1 h  0 min: bug triage
+ 1 h  0 min: customer joe: support
+-------
+Total work done: 8 h 15 min
+Total slacking: 1 h 15 min (tea: 0 h 30 min, lunch: 0 h 45 min)
+"
+        );
+    }
+
+    #[test]
+    fn test_percentage_bar() {
+        assert_eq!(percentage_bar(0.4, 10), "████░░░░░░");
+        assert_eq!(percentage_bar(0.0, 10), "░░░░░░░░░░");
+        assert_eq!(percentage_bar(1.0, 10), "██████████");
+        // rounds to the nearest character
+        assert_eq!(percentage_bar(0.34, 6), "██░░░░");
+        assert_eq!(percentage_bar(0.25, 6), "██░░░░");
+        assert_eq!(percentage_bar(0.24, 6), "█░░░░░");
+        assert_eq!(percentage_bar(1.5, 4), "████");
+        assert_eq!(percentage_bar(0.5, 0), "");
+
+        let tl = Timelog::new_from_string(DAILY);
+        let config = Config {
+            percentage_bars: true,
+            ..Config::default()
+        };
+        let a = Activities::new_from_entries(
+            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1),
+        )
+        .with_config(&config);
+        assert_eq!(
+            a.to_string(),
+            "█████░░░░░  54%   4 h 50 min: gtimelog: code
+░░░░░░░░░░   5%   0 h 25 min: ** tea
+█░░░░░░░░░   6%   0 h 30 min: customer joe: inquiry
+█░░░░░░░░░   7%   0 h 40 min: ** lunch
+█░░░░░░░░░   8%   0 h 45 min: code
+█░░░░░░░░░  11%   1 h  0 min: bug triage
+█░░░░░░░░░   9%   0 h 50 min: customer joe: support
+-------
+Total work done: 7 h 55 min
+Total slacking: 1 h 5 min (tea: 0 h 25 min, lunch: 0 h 40 min)
+"
+        );
+    }
+
+    #[test]
+    fn test_truncate_line() {
+        let header = "Work done this week 2022, week 22 (May 30-5):";
+        assert_eq!(truncate_line(header, 80), header);
+        assert_eq!(truncate_line(header, header.len()), header);
+        assert_eq!(
+            truncate_line(header, header.len() - 1),
+            "Work done this week 2022, week 22 (May 30-5…"
+        );
+        assert_eq!(truncate_line(header, 20), "Work done this week…");
+        assert_eq!(truncate_line(header, 1), "…");
+        assert_eq!(truncate_line(header, 0), "");
+        // counts characters, not bytes
+        assert_eq!(truncate_line("Füße über", 5), "Füße…");
+    }
+
+    #[test]
+    fn test_timeline_alignment() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-09 07:00: arrived
+2022-06-09 07:05: email
+
+2022-06-10 07:00: arrived
+2022-06-10 19:30: very long task description
+2022-06-10 20:35: x
+",
+        );
+        assert_eq!(
+            render_timeline(
+                tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 2),
+                &Config::default()
+            ),
+            "07:00-07:05  0 h  5 min: email
+
+07:00-19:30 12 h 30 min: very long task description
+19:30-20:35  1 h  5 min: x
 "
         );
     }
@@ -0,0 +1,82 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+// copy text to the system clipboard, or the reason why that's not possible
+#[cfg(feature = "clipboard")]
+fn copy(text: &str) -> Result<(), String> {
+    let mut clipboard = arboard::Clipboard::new().map_err(|e| e.to_string())?;
+    clipboard.set_text(text).map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "clipboard"))]
+fn copy(_text: &str) -> Result<(), String> {
+    Err("built without the clipboard feature".to_string())
+}
+
+// try to copy text; on failure, print it with a warning instead
+fn copy_or_print_with(
+    text: &str,
+    copy: impl FnOnce(&str) -> Result<(), String>,
+) -> Result<(), String> {
+    if let Err(e) = copy(text) {
+        eprintln!("WARNING: No clipboard available ({e}), printing instead");
+        print!("{text}");
+        return Err(text.to_string());
+    }
+    Ok(())
+}
+
+/**
+ * Copy text to the system clipboard, or print it if there is none (e.g. headless)
+ *
+ * Returns whether the text got copied.
+ */
+pub fn copy_or_print(text: &str) -> bool {
+    copy_or_print_with(text, copy).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy() {
+        assert_eq!(copy_or_print_with("hello", |_| Ok(())), Ok(()));
+    }
+
+    #[test]
+    fn test_fallback() {
+        assert_eq!(
+            copy_or_print_with("hello\n", |_| Err("no display".to_string())),
+            Err("hello\n".to_string())
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "clipboard"))]
+    fn test_no_feature() {
+        assert_eq!(
+            copy("hello"),
+            Err("built without the clipboard feature".to_string())
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "clipboard")]
+    fn test_feature() {
+        // don't touch the developer's clipboard, just check that the arboard path builds
+        let _: fn(&str) -> Result<(), String> = copy;
+    }
+}
@@ -0,0 +1,202 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate chrono;
+
+use std::cell::Cell;
+use std::env;
+use std::sync::OnceLock;
+
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+use crate::store::TIME_FMT;
+
+thread_local! {
+    // unit tests run in parallel threads, so keep their fixed time per thread
+    static NOW_OVERRIDE: Cell<Option<NaiveDateTime>> = const { Cell::new(None) };
+    static VIRTUAL_MIDNIGHT_OVERRIDE: Cell<Option<NaiveTime>> = const { Cell::new(None) };
+}
+
+// $RTIMELOG_NOW and $RTIMELOG_VIRTUAL_MIDNIGHT do not change while running, so only
+// parse (and warn) once
+static ENV_NOW: OnceLock<Option<NaiveDateTime>> = OnceLock::new();
+static ENV_VIRTUAL_MIDNIGHT: OnceLock<NaiveTime> = OnceLock::new();
+
+/**
+ * Current local time, with second precision
+ *
+ * This can be overridden with $RTIMELOG_NOW (in timelog format "YYYY-MM-DD HH:MM")
+ * for reproducible runs.
+ */
+pub fn now() -> NaiveDateTime {
+    if let Some(t) = NOW_OVERRIDE.with(|o| o.get()) {
+        return t;
+    }
+
+    let env_now = ENV_NOW.get_or_init(|| {
+        let s = env::var("RTIMELOG_NOW").ok()?;
+        let t = NaiveDateTime::parse_from_str(&s, TIME_FMT).ok();
+        if t.is_none() {
+            eprintln!("WARNING: ignoring invalid $RTIMELOG_NOW: {s}");
+        }
+        t
+    });
+    if let Some(t) = env_now {
+        return *t;
+    }
+
+    Local::now().naive_local().with_nanosecond(0).unwrap()
+}
+
+pub fn today() -> NaiveDate {
+    logical_date(now())
+}
+
+// "HH:MM" of $RTIMELOG_VIRTUAL_MIDNIGHT
+fn parse_virtual_midnight(s: &str) -> Option<NaiveTime> {
+    let (h, m) = s.split_once(':')?;
+    if h.len() != 2 || m.len() != 2 {
+        return None;
+    }
+    NaiveTime::from_hms_opt(h.parse().ok()?, m.parse().ok()?, 0)
+}
+
+/**
+ * Time of day at which a new day begins
+ *
+ * This is midnight by default. Night owls can move it later with
+ * $RTIMELOG_VIRTUAL_MIDNIGHT="HH:MM", so that entries after midnight still count
+ * towards the previous day.
+ */
+pub fn virtual_midnight() -> NaiveTime {
+    if let Some(t) = VIRTUAL_MIDNIGHT_OVERRIDE.with(|o| o.get()) {
+        return t;
+    }
+
+    *ENV_VIRTUAL_MIDNIGHT.get_or_init(|| match env::var("RTIMELOG_VIRTUAL_MIDNIGHT") {
+        Ok(s) => parse_virtual_midnight(&s).unwrap_or_else(|| {
+            eprintln!("WARNING: ignoring invalid $RTIMELOG_VIRTUAL_MIDNIGHT: {s}");
+            NaiveTime::MIN
+        }),
+        Err(_) => NaiveTime::MIN,
+    })
+}
+
+// day which the given time belongs to, respecting the virtual midnight
+pub fn logical_date(t: NaiveDateTime) -> NaiveDate {
+    (t - (virtual_midnight() - NaiveTime::MIN)).date()
+}
+
+// first moment of the given day, respecting the virtual midnight
+pub fn day_start(day: &NaiveDate) -> NaiveDateTime {
+    day.and_time(virtual_midnight())
+}
+
+// the given time of day within the given day; times before the virtual midnight are after
+// the calendar midnight
+pub fn on_day(day: &NaiveDate, time: NaiveTime) -> NaiveDateTime {
+    if time < virtual_midnight() {
+        day.succ_opt().unwrap().and_time(time)
+    } else {
+        day.and_time(time)
+    }
+}
+
+#[cfg(test)]
+pub fn set_now(t: Option<NaiveDateTime>) {
+    NOW_OVERRIDE.with(|o| o.set(t));
+}
+
+#[cfg(test)]
+pub fn set_virtual_midnight(t: Option<NaiveTime>) {
+    VIRTUAL_MIDNIGHT_OVERRIDE.with(|o| o.set(t));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_now() {
+        let t = NaiveDate::from_ymd_opt(2022, 6, 10)
+            .unwrap()
+            .and_hms_opt(14, 30, 0)
+            .unwrap();
+        set_now(Some(t));
+        assert_eq!(now(), t);
+        assert_eq!(today(), NaiveDate::from_ymd_opt(2022, 6, 10).unwrap());
+
+        set_now(None);
+        assert_ne!(now(), t);
+    }
+
+    #[test]
+    fn test_virtual_midnight() {
+        let day = NaiveDate::from_ymd_opt(2022, 6, 10).unwrap();
+        let after_midnight = day.succ_opt().unwrap().and_hms_opt(1, 30, 0).unwrap();
+        assert_eq!(virtual_midnight(), NaiveTime::MIN);
+        assert_eq!(logical_date(after_midnight), day.succ_opt().unwrap());
+
+        set_virtual_midnight(Some(NaiveTime::from_hms_opt(4, 0, 0).unwrap()));
+        assert_eq!(logical_date(after_midnight), day);
+        assert_eq!(
+            logical_date(day.and_hms_opt(4, 0, 0).unwrap()),
+            day,
+            "virtual midnight itself starts the new day"
+        );
+        assert_eq!(day_start(&day), day.and_hms_opt(4, 0, 0).unwrap());
+        set_now(Some(after_midnight));
+        assert_eq!(today(), day);
+
+        set_now(None);
+        set_virtual_midnight(None);
+    }
+
+    #[test]
+    fn test_on_day() {
+        let day = NaiveDate::from_ymd_opt(2022, 6, 10).unwrap();
+        let time = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        assert_eq!(on_day(&day, time(0, 30)), day.and_time(time(0, 30)));
+        assert_eq!(on_day(&day, time(23, 30)), day.and_time(time(23, 30)));
+
+        // at 01:00 it's still the previous day, so ":at 23:30" is before and ":at 00:30" after
+        // the calendar midnight
+        set_virtual_midnight(Some(time(3, 0)));
+        let next = day.succ_opt().unwrap();
+        set_now(Some(next.and_time(time(1, 0))));
+        assert_eq!(on_day(&today(), time(23, 30)), day.and_time(time(23, 30)));
+        assert_eq!(on_day(&today(), time(0, 30)), next.and_time(time(0, 30)));
+        assert_eq!(on_day(&today(), time(3, 0)), day.and_time(time(3, 0)));
+        assert_eq!(on_day(&today(), time(2, 59)), next.and_time(time(2, 59)));
+
+        set_now(None);
+        set_virtual_midnight(None);
+    }
+
+    #[test]
+    fn test_parse_virtual_midnight() {
+        assert_eq!(
+            parse_virtual_midnight("04:00"),
+            NaiveTime::from_hms_opt(4, 0, 0)
+        );
+        assert_eq!(
+            parse_virtual_midnight("23:30"),
+            NaiveTime::from_hms_opt(23, 30, 0)
+        );
+        for invalid in ["", "4:00", "04", "24:00", "04:60", "ab:cd", "04:00:00"] {
+            assert_eq!(parse_virtual_midnight(invalid), None, "{invalid}");
+        }
+    }
+}
@@ -13,23 +13,60 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use chrono::NaiveTime;
+
 #[derive(PartialEq, Debug)]
 pub enum TimeMode {
     Day(u32),
     Week(u32),
+    Month(u32),
 }
 
 #[derive(PartialEq, Debug)]
 pub enum Command {
     Nothing,
     Quit,
-    Help,
+    Help(Option<String>),
     Edit,
+    Undo,
+    Timeline,
+    Pause,
+    Resume,
+    Focus(Option<String>),
+    // only show tasks containing the keyword
+    Filter(Option<String>),
+    // switch to the timelog of a profile, or the default one
+    Profile(Option<String>),
     SwitchMode(TimeMode),
     Add(String),
+    // entry at the given time of today, instead of now
+    AddAt(NaiveTime, String),
+    // entry the given number of minutes ago
+    AddAgo(u32, String),
     Error(String),
 }
 
+// leading "-N " of an entry for N minutes ago, like in "-15 coffee break"
+fn split_minutes_ago(input: &str) -> Option<(u32, &str)> {
+    let (offset, task) = input.strip_prefix('-')?.split_once(' ')?;
+    if offset.is_empty() || !offset.bytes().all(|b| b.is_ascii_digit()) || task.trim().is_empty() {
+        return None;
+    }
+    Some((offset.parse().ok()?, task.trim()))
+}
+
+// leading "HH:MM " (or "H:MM ") of an entry, like in "10:45 fixed the build"
+fn split_time(input: &str) -> Option<(NaiveTime, &str)> {
+    let (time, task) = input.split_once(' ')?;
+    let (h, m) = time.split_once(':')?;
+    let digits = |s: &str, len| s.len() == len && s.bytes().all(|b| b.is_ascii_digit());
+    if !(digits(h, 1) || digits(h, 2)) || !digits(m, 2) || task.trim().is_empty() {
+        return None;
+    }
+    let time = NaiveTime::from_hms_opt(h.parse().ok()?, m.parse().ok()?, 0)?;
+    Some((time, task.trim()))
+}
+
 impl Command {
     pub fn parse(input: String) -> Command {
         match input.chars().next() {
@@ -37,13 +74,35 @@ impl Command {
 
             Some(':') => match input.as_str() {
                 ":q" => Command::Quit,
-                ":h" => Command::Help,
+                ":h" => Command::Help(None),
                 ":e" => Command::Edit,
+                ":u" => Command::Undo,
+                ":t" => Command::Timeline,
+                ":pause" => Command::Pause,
+                ":resume" => Command::Resume,
                 ":w" => Command::SwitchMode(TimeMode::Week(1)),
                 ":d" => Command::SwitchMode(TimeMode::Day(1)),
+                ":m" => Command::SwitchMode(TimeMode::Month(1)),
 
                 _ => {
-                    if let Some(arg) = input.strip_prefix(":d") {
+                    if let Some(cat) = input.strip_prefix(":focus-cat") {
+                        match cat.trim() {
+                            "" => Command::Focus(None),
+                            c if cat.starts_with(' ') => Command::Focus(Some(c.to_string())),
+                            _ => Command::Error("Unknown command".to_string()),
+                        }
+                    } else if let Some(name) = input.strip_prefix(":profile") {
+                        match name.trim() {
+                            "" => Command::Profile(None),
+                            n if name.starts_with(' ') => Command::Profile(Some(n.to_string())),
+                            _ => Command::Error("Unknown command".to_string()),
+                        }
+                    } else if let Some(topic) = input.strip_prefix(":h ") {
+                        match topic.trim() {
+                            "" => Command::Help(None),
+                            t => Command::Help(Some(t.to_string())),
+                        }
+                    } else if let Some(arg) = input.strip_prefix(":d") {
                         match arg.parse::<u32>() {
                             Ok(n) => Command::SwitchMode(TimeMode::Day(n)),
                             Err(_) => Command::Error("Invalid day number".to_string()),
@@ -53,13 +112,31 @@ impl Command {
                             Ok(week) => Command::SwitchMode(TimeMode::Week(week)),
                             Err(_) => Command::Error("Invalid week number".to_string()),
                         }
+                    } else if let Some(arg) = input.strip_prefix(":m") {
+                        match arg.parse::<u32>() {
+                            Ok(month) => Command::SwitchMode(TimeMode::Month(month)),
+                            Err(_) => Command::Error("Invalid month number".to_string()),
+                        }
                     } else {
                         Command::Error("Unknown command".to_string())
                     }
                 }
             },
 
-            Some(_) => Command::Add(input),
+            Some('/') => match input[1..].trim() {
+                "" => Command::Filter(None),
+                keyword => Command::Filter(Some(keyword.to_string())),
+            },
+
+            Some(_) => {
+                if let Some((time, task)) = split_time(&input) {
+                    Command::AddAt(time, task.to_string())
+                } else if let Some((minutes, task)) = split_minutes_ago(&input) {
+                    Command::AddAgo(minutes, task.to_string())
+                } else {
+                    Command::Add(input)
+                }
+            }
         }
     }
 }
@@ -72,8 +149,37 @@ mod tests {
     fn test_parse() {
         assert_eq!(Command::parse("".to_string()), Command::Nothing);
         assert_eq!(Command::parse(":q".to_string()), Command::Quit);
-        assert_eq!(Command::parse(":h".to_string()), Command::Help);
+        assert_eq!(Command::parse(":h".to_string()), Command::Help(None));
+        assert_eq!(
+            Command::parse(":h add".to_string()),
+            Command::Help(Some("add".to_string()))
+        );
+        assert_eq!(Command::parse(":h  ".to_string()), Command::Help(None));
         assert_eq!(Command::parse(":e".to_string()), Command::Edit);
+        assert_eq!(Command::parse(":u".to_string()), Command::Undo);
+        assert_eq!(Command::parse(":t".to_string()), Command::Timeline);
+        assert_eq!(Command::parse(":pause".to_string()), Command::Pause);
+        assert_eq!(Command::parse(":resume".to_string()), Command::Resume);
+        assert_eq!(
+            Command::parse(":focus-cat project1".to_string()),
+            Command::Focus(Some("project1".to_string()))
+        );
+        assert_eq!(
+            Command::parse(":focus-cat".to_string()),
+            Command::Focus(None)
+        );
+        assert_eq!(
+            Command::parse(":profile work".to_string()),
+            Command::Profile(Some("work".to_string()))
+        );
+        assert_eq!(
+            Command::parse(":profile".to_string()),
+            Command::Profile(None)
+        );
+        assert_eq!(
+            Command::parse(":profilework".to_string()),
+            Command::Error("Unknown command".to_string())
+        );
         assert_eq!(
             Command::parse(":w".to_string()),
             Command::SwitchMode(TimeMode::Week(1))
@@ -90,10 +196,74 @@ mod tests {
             Command::parse(":d7".to_string()),
             Command::SwitchMode(TimeMode::Day(7))
         );
+        assert_eq!(
+            Command::parse(":m".to_string()),
+            Command::SwitchMode(TimeMode::Month(1))
+        );
+        assert_eq!(
+            Command::parse(":m2".to_string()),
+            Command::SwitchMode(TimeMode::Month(2))
+        );
+        assert_eq!(
+            Command::parse("/rtimelog".to_string()),
+            Command::Filter(Some("rtimelog".to_string()))
+        );
+        assert_eq!(
+            Command::parse("/ code review ".to_string()),
+            Command::Filter(Some("code review".to_string()))
+        );
+        assert_eq!(Command::parse("/".to_string()), Command::Filter(None));
         assert_eq!(
             Command::parse("foo".to_string()),
             Command::Add("foo".to_string())
         );
+        // entry with time
+        assert_eq!(
+            Command::parse("10:45 fixed the build".to_string()),
+            Command::AddAt(
+                NaiveTime::from_hms_opt(10, 45, 0).unwrap(),
+                "fixed the build".to_string()
+            )
+        );
+        assert_eq!(
+            Command::parse("9:05  code".to_string()),
+            Command::AddAt(
+                NaiveTime::from_hms_opt(9, 5, 0).unwrap(),
+                "code".to_string()
+            )
+        );
+        // no task, invalid time, or not a time: normal entry
+        for input in [
+            "10:45",
+            "10:45 ",
+            "25:00 code",
+            "10:5 code",
+            "1:30pm code",
+            "a:bc code",
+        ] {
+            assert_eq!(
+                Command::parse(input.to_string()),
+                Command::Add(input.to_string())
+            );
+        }
+        // entry some minutes ago
+        assert_eq!(
+            Command::parse("-15 coffee break".to_string()),
+            Command::AddAgo(15, "coffee break".to_string())
+        );
+        for input in [
+            "-15",
+            "-15 ",
+            "- 15 code",
+            "-x code",
+            "-1h code",
+            "-99999999999 code",
+        ] {
+            assert_eq!(
+                Command::parse(input.to_string()),
+                Command::Add(input.to_string())
+            );
+        }
         // unknown command letter
         assert_eq!(
             Command::parse(":x".to_string()),
@@ -104,6 +274,10 @@ mod tests {
             Command::parse(":e2".to_string()),
             Command::Error("Unknown command".to_string())
         );
+        assert_eq!(
+            Command::parse(":focus-catx".to_string()),
+            Command::Error("Unknown command".to_string())
+        );
         // invalid day/week args
         assert_eq!(
             Command::parse(":da".to_string()),
@@ -113,5 +287,9 @@ mod tests {
             Command::parse(":w ".to_string()),
             Command::Error("Invalid week number".to_string())
         );
+        assert_eq!(
+            Command::parse(":mx".to_string()),
+            Command::Error("Invalid month number".to_string())
+        );
     }
 }
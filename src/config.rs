@@ -0,0 +1,762 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate dirs;
+
+use std::collections::HashMap;
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
+use std::fmt::Write as _; // import without risk of name clashing
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{Duration, NaiveDate};
+
+use crate::activity::{format_hm_compact, is_slack_with, ActivityOrder};
+use crate::report::ReportPeriod;
+use crate::store::Timelog;
+
+// all settings, with their environment variable being RTIMELOG_ plus the upper case key
+pub const KEYS: [&str; 20] = [
+    "category_separator",
+    "compact_durations",
+    "merge_categories",
+    "leaving_marker",
+    "activity_order",
+    "show_arrival",
+    "percentage_bars",
+    "meeting_marker",
+    "date_format",
+    "untracked_tolerance",
+    "category_case_insensitive",
+    "post_add_hook",
+    "default_report_period",
+    "category_targets",
+    "base_file",
+    "show_seconds",
+    "slack_prefix",
+    "slack_categories",
+    "profiles",
+    "daily_subtotals",
+];
+
+/**
+ * Where the value of a setting came from
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    File(PathBuf),
+    Env(String),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::File(path) => write!(f, "{}", path.display()),
+            Source::Env(var) => write!(f, "${var}"),
+        }
+    }
+}
+
+/**
+ * Settings from the config file and environment
+ *
+ * The file has "key = value" lines; values can be put into double quotes to keep
+ * leading or trailing spaces. Empty lines and lines starting with # are ignored.
+ * Environment variables like $RTIMELOG_CATEGORY_SEPARATOR override the file.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    // shown between category and task, instead of the stored ": "
+    pub category_separator: String,
+    // "3h5m" instead of "3 h 5 min"
+    pub compact_durations: bool,
+    // "0 h 0 min 45 s" for durations under a minute, instead of "0 h 0 min"
+    pub show_seconds: bool,
+    // category -> umbrella category for grouping by category
+    pub merge_categories: HashMap<String, String>,
+    // tasks starting with this are slack; "**" anywhere in a task always is
+    pub slack_prefix: String,
+    // tasks in these categories are slack, like "break" for "break: coffee"
+    pub slack_categories: Vec<String>,
+    // task which ends work, like "home"; neither it nor the gap after it count
+    pub leaving_marker: Option<String>,
+    pub activity_order: ActivityOrder,
+    // show the first entry of the day in the daily view header
+    pub show_arrival: bool,
+    // show a section with totals for each day in the weekly and monthly views
+    pub daily_subtotals: bool,
+    // bar with the share of the tracked time in front of each activity
+    pub percentage_bars: bool,
+    // category or tag of meetings, which are separated from focused work
+    pub meeting_marker: String,
+    // chrono format of dates in day headers; the file always has ISO dates
+    pub date_format: String,
+    // untracked time of a day up to this does not cause a warning
+    pub untracked_tolerance: Duration,
+    // group "Project1:" and "project1:" into one category; tasks stay distinct
+    pub category_case_insensitive: bool,
+    // shell command to run after adding an entry
+    pub post_add_hook: Option<String>,
+    // period of rtimelog-cmd report without a range
+    pub default_report_period: ReportPeriod,
+    // category -> daily work target
+    pub category_targets: HashMap<String, Duration>,
+    // read-only log whose entries are shown along with the own ones, but never written
+    pub base_file: Option<PathBuf>,
+    // profile name -> timelog file, like "work" and "personal"
+    pub profiles: HashMap<String, PathBuf>,
+    // keys which don't have their default value
+    pub(crate) sources: HashMap<String, Source>,
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        v => Err(format!("must be true or false, not {v}")),
+    }
+}
+
+// parse "frontend=dev,backend=dev" into a category map
+pub fn parse_category_map(spec: &str) -> Result<HashMap<String, String>, String> {
+    spec.split(',')
+        .filter(|item| !item.trim().is_empty())
+        .map(|item| match item.split_once('=') {
+            Some((from, to)) if !from.trim().is_empty() && !to.trim().is_empty() => {
+                Ok((from.trim().to_string(), to.trim().to_string()))
+            }
+            _ => Err(format!(
+                "invalid category mapping '{item}', expected FROM=TO"
+            )),
+        })
+        .collect()
+}
+
+// parse durations like format_hm_compact() writes them, like "4h", "45m", or "1h30m"
+fn parse_duration(spec: &str) -> Option<Duration> {
+    let (hours, minutes) = match spec.split_once('h') {
+        Some((h, m)) => (h.parse::<u32>().ok()?, m),
+        None => (0, spec),
+    };
+    let minutes = match minutes {
+        "" => 0,
+        m => m.strip_suffix('m')?.parse::<u32>().ok()?,
+    };
+    Some(Duration::minutes(hours as i64 * 60 + minutes as i64)).filter(|d| !d.is_zero())
+}
+
+// parse "project1=4h, meeting=1h30m" into a map of category targets
+pub fn parse_category_targets(spec: &str) -> Result<HashMap<String, Duration>, String> {
+    parse_category_map(spec)?
+        .into_iter()
+        .map(|(cat, target)| match parse_duration(&target) {
+            Some(d) => Ok((cat, d)),
+            None => Err(format!(
+                "invalid target '{target}' for {cat}, expected a duration like 4h or 1h30m"
+            )),
+        })
+        .collect()
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            category_separator: ": ".to_string(),
+            compact_durations: false,
+            show_seconds: false,
+            merge_categories: HashMap::new(),
+            slack_prefix: "**".to_string(),
+            slack_categories: Vec::new(),
+            leaving_marker: None,
+            activity_order: ActivityOrder::default(),
+            show_arrival: true,
+            daily_subtotals: false,
+            percentage_bars: false,
+            meeting_marker: "meeting".to_string(),
+            date_format: "%A, %F".to_string(),
+            untracked_tolerance: Duration::minutes(10),
+            category_case_insensitive: false,
+            post_add_hook: None,
+            default_report_period: ReportPeriod::default(),
+            category_targets: HashMap::new(),
+            base_file: None,
+            profiles: HashMap::new(),
+            sources: HashMap::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn new_from_default_file() -> Config {
+        let mut config = match Config::default_file() {
+            Some(path) => Config::new_from_file(&path),
+            None => Config::default(),
+        };
+        config.apply_env(|var| env::var(var).ok());
+        config
+    }
+
+    pub fn new_from_file(path: &PathBuf) -> Config {
+        match fs::read_to_string(path) {
+            Ok(contents) => Config::parse(&contents, path),
+            Err(e) => {
+                if e.kind() != io::ErrorKind::NotFound {
+                    eprintln!("WARNING: Failed to read config {}: {}", path.display(), e);
+                }
+                Config::default()
+            }
+        }
+    }
+
+    // None if the home directory is unknown
+    pub fn default_file() -> Option<PathBuf> {
+        Config::get_default_file(env::var_os("XDG_CONFIG_HOME"), dirs::home_dir())
+    }
+
+    pub fn get_default_file(
+        config_home: Option<OsString>,
+        home: Option<PathBuf>,
+    ) -> Option<PathBuf> {
+        let mut path = match config_home {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => {
+                let mut dir = home?;
+                dir.push(".config");
+                dir
+            }
+        };
+        path.push("rtimelog");
+        path.push("config");
+        Some(path)
+    }
+
+    // path is only used for warnings and as source
+    pub fn parse(contents: &str, path: &Path) -> Config {
+        let mut config = Config::default();
+
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                eprintln!("WARNING: {}: ignoring invalid line: {line}", path.display());
+                continue;
+            };
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+
+            if let Err(e) = config.set(key.trim(), value, Source::File(path.to_path_buf())) {
+                eprintln!("WARNING: {}: ignoring {}: {e}", path.display(), key.trim());
+            }
+        }
+
+        config
+    }
+
+    // override settings from environment variables; get_var returns a variable's value
+    pub fn apply_env(&mut self, get_var: impl Fn(&str) -> Option<String>) {
+        for key in KEYS {
+            let var = format!("RTIMELOG_{}", key.to_uppercase());
+            if let Some(value) = get_var(&var) {
+                if let Err(e) = self.set(key, &value, Source::Env(var.clone())) {
+                    eprintln!("WARNING: ignoring ${var}: {e}");
+                }
+            }
+        }
+    }
+
+    fn set(&mut self, key: &str, value: &str, source: Source) -> Result<(), String> {
+        match key {
+            "category_separator" => self.category_separator = value.to_string(),
+            "compact_durations" => self.compact_durations = parse_bool(value)?,
+            "show_seconds" => self.show_seconds = parse_bool(value)?,
+            "show_arrival" => self.show_arrival = parse_bool(value)?,
+            "daily_subtotals" => self.daily_subtotals = parse_bool(value)?,
+            "percentage_bars" => self.percentage_bars = parse_bool(value)?,
+            "category_case_insensitive" => self.category_case_insensitive = parse_bool(value)?,
+            "meeting_marker" if value.is_empty() => return Err("must not be empty".to_string()),
+            "meeting_marker" => self.meeting_marker = value.to_string(),
+            // everything would be slack
+            "slack_prefix" if value.is_empty() => return Err("must not be empty".to_string()),
+            "slack_prefix" => self.slack_prefix = value.to_string(),
+            "slack_categories" => {
+                self.slack_categories = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|c| !c.is_empty())
+                    .map(String::from)
+                    .collect()
+            }
+            "untracked_tolerance" => {
+                self.untracked_tolerance = Duration::minutes(
+                    value
+                        .parse::<u32>()
+                        .map_err(|_| format!("must be a number of minutes, not {value}"))?
+                        .into(),
+                )
+            }
+            "date_format" => {
+                // chrono panics when displaying an invalid format, or a time of a date
+                let sample = NaiveDate::from_ymd_opt(2022, 6, 9).unwrap();
+                let mut formatted = String::new();
+                if write!(formatted, "{}", sample.format(value)).is_err() {
+                    return Err(format!("invalid date format {value}"));
+                }
+                self.date_format = value.to_string()
+            }
+            "merge_categories" => self.merge_categories = parse_category_map(value)?,
+            "category_targets" => self.category_targets = parse_category_targets(value)?,
+            "profiles" => {
+                self.profiles = parse_category_map(value)?
+                    .into_iter()
+                    .map(|(name, path)| (name, PathBuf::from(path)))
+                    .collect()
+            }
+            "leaving_marker" => {
+                self.leaving_marker = Some(value).filter(|v| !v.is_empty()).map(String::from)
+            }
+            "post_add_hook" => {
+                self.post_add_hook = Some(value).filter(|v| !v.is_empty()).map(String::from)
+            }
+            "base_file" => {
+                self.base_file = Some(value).filter(|v| !v.is_empty()).map(PathBuf::from)
+            }
+            "activity_order" => {
+                self.activity_order = match value {
+                    "first" => ActivityOrder::FirstOccurrence,
+                    "duration" => ActivityOrder::Duration,
+                    "duration-slack-last" => ActivityOrder::DurationSlackLast,
+                    v => {
+                        return Err(format!(
+                            "must be first, duration, or duration-slack-last, not {v}"
+                        ))
+                    }
+                }
+            }
+            "default_report_period" => {
+                self.default_report_period = match value {
+                    "today" => ReportPeriod::Today,
+                    "week" => ReportPeriod::Week,
+                    "month" => ReportPeriod::Month,
+                    v => return Err(format!("must be today, week, or month, not {v}")),
+                }
+            }
+            _ => return Err("unknown key".to_string()),
+        }
+        self.sources.insert(key.to_string(), source);
+        Ok(())
+    }
+
+    // value of a setting in config file syntax
+    pub fn get(&self, key: &str) -> Option<String> {
+        Some(match key {
+            "category_separator" => format!("\"{}\"", self.category_separator),
+            "compact_durations" => self.compact_durations.to_string(),
+            "show_seconds" => self.show_seconds.to_string(),
+            "show_arrival" => self.show_arrival.to_string(),
+            "daily_subtotals" => self.daily_subtotals.to_string(),
+            "percentage_bars" => self.percentage_bars.to_string(),
+            "category_case_insensitive" => self.category_case_insensitive.to_string(),
+            "meeting_marker" => self.meeting_marker.clone(),
+            "slack_prefix" => self.slack_prefix.clone(),
+            "slack_categories" => self.slack_categories.join(", "),
+            "date_format" => format!("\"{}\"", self.date_format),
+            "untracked_tolerance" => self.untracked_tolerance.num_minutes().to_string(),
+            "merge_categories" => {
+                let mut items: Vec<String> = self
+                    .merge_categories
+                    .iter()
+                    .map(|(from, to)| format!("{from}={to}"))
+                    .collect();
+                items.sort();
+                items.join(", ")
+            }
+            "category_targets" => {
+                let mut items: Vec<String> = self
+                    .category_targets
+                    .iter()
+                    .map(|(cat, target)| format!("{cat}={}", format_hm_compact(target)))
+                    .collect();
+                items.sort();
+                items.join(", ")
+            }
+            "profiles" => {
+                let mut items: Vec<String> = self
+                    .profiles
+                    .iter()
+                    .map(|(name, path)| format!("{name}={}", path.display()))
+                    .collect();
+                items.sort();
+                items.join(", ")
+            }
+            "leaving_marker" => self.leaving_marker.clone().unwrap_or_default(),
+            "post_add_hook" => self.post_add_hook.clone().unwrap_or_default(),
+            "base_file" => self
+                .base_file
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default(),
+            "activity_order" => match self.activity_order {
+                ActivityOrder::FirstOccurrence => "first",
+                ActivityOrder::Duration => "duration",
+                ActivityOrder::DurationSlackLast => "duration-slack-last",
+            }
+            .to_string(),
+            "default_report_period" => match self.default_report_period {
+                ReportPeriod::Today => "today",
+                ReportPeriod::Week => "week",
+                ReportPeriod::Month => "month",
+            }
+            .to_string(),
+            _ => return None,
+        })
+    }
+
+    // whether task is slack: containing "**", starting with slack_prefix, or in slack_categories
+    pub fn is_slack(&self, task: &str) -> bool {
+        is_slack_with(task, &self.slack_prefix, &self.slack_categories)
+    }
+
+    /**
+     * Timelog file of the given profile, or the default file without a profile
+     *
+     * Profiles are set with "profiles = work=/path/work.txt, personal=/path/personal.txt".
+     */
+    pub fn timelog_file(&self, profile: Option<&str>) -> Result<PathBuf, String> {
+        let Some(name) = profile else {
+            return Timelog::get_default_file().map_err(|e| e.to_string());
+        };
+        self.profiles.get(name).cloned().ok_or_else(|| {
+            let mut names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            names.sort();
+            match names.as_slice() {
+                [] => format!("Unknown profile {name}, the profiles setting is empty"),
+                names => format!("Unknown profile {name}, known: {}", names.join(", ")),
+            }
+        })
+    }
+
+    pub fn format_date(&self, date: &NaiveDate) -> String {
+        date.format(&self.date_format).to_string()
+    }
+
+    // bars are decoration, so leave them out with $NO_COLOR and when not writing to a terminal
+    pub fn adjust_to_output(&mut self, no_color: bool, is_terminal: bool) {
+        if no_color || !is_terminal {
+            self.percentage_bars = false;
+        }
+    }
+
+    pub fn source(&self, key: &str) -> Source {
+        self.sources.get(key).cloned().unwrap_or(Source::Default)
+    }
+
+    // all settings with their value and source, for debugging
+    pub fn describe(&self) -> String {
+        let mut output = String::new();
+        for key in KEYS {
+            writeln!(
+                output,
+                "{key} = {}  # {}",
+                self.get(key).unwrap_or_default(),
+                self.source(key)
+            )
+            .expect("failed to format setting");
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const PATH: &str = "/conf/rtimelog/config";
+
+    fn parse(contents: &str) -> Config {
+        Config::parse(contents, Path::new(PATH))
+    }
+
+    #[test]
+    fn test_get_default_file() {
+        assert_eq!(
+            Config::get_default_file(Some(OsString::from("/conf")), None),
+            Some(PathBuf::from("/conf/rtimelog/config"))
+        );
+        assert_eq!(
+            Config::get_default_file(None, Some(PathBuf::from("/home/me"))),
+            Some(PathBuf::from("/home/me/.config/rtimelog/config"))
+        );
+        assert_eq!(Config::get_default_file(None, None), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(""), Config::default());
+        assert_eq!(Config::default().category_separator, ": ");
+
+        let config = parse(
+            "
+# nicer look
+category_separator = \" › \"
+unknown = 1
+invalid
+",
+        );
+        assert_eq!(config.category_separator, " › ");
+        assert!(!config.compact_durations);
+        assert!(config.show_arrival);
+        assert!(!parse("show_arrival = false").show_arrival);
+        assert_eq!(config.leaving_marker, None);
+        assert_eq!(config.activity_order, ActivityOrder::FirstOccurrence);
+        assert_eq!(
+            parse("activity_order = duration-slack-last").activity_order,
+            ActivityOrder::DurationSlackLast
+        );
+        assert_eq!(config.default_report_period, ReportPeriod::Today);
+        assert_eq!(
+            parse("default_report_period = week").default_report_period,
+            ReportPeriod::Week
+        );
+        assert_eq!(
+            parse("default_report_period = year").default_report_period,
+            ReportPeriod::Today
+        );
+        assert_eq!(
+            parse("leaving_marker = home").leaving_marker,
+            Some("home".to_string())
+        );
+        assert_eq!(config.slack_prefix, "**");
+        assert_eq!(parse("slack_prefix = *").slack_prefix, "*");
+        assert_eq!(parse("slack_prefix = \"\"").slack_prefix, "**");
+        assert!(config.slack_categories.is_empty());
+        assert_eq!(
+            parse("slack_categories = break, lunch,").slack_categories,
+            vec!["break", "lunch"]
+        );
+
+        assert!(parse("compact_durations = true").compact_durations);
+        assert_eq!(
+            parse("untracked_tolerance = 0").untracked_tolerance,
+            Duration::zero()
+        );
+        assert_eq!(
+            parse("untracked_tolerance = -5").untracked_tolerance,
+            Duration::minutes(10)
+        );
+        assert!(!parse("compact_durations = yes").compact_durations);
+
+        let config = parse("category_separator=/");
+        assert_eq!(config.category_separator, "/");
+    }
+
+    #[test]
+    fn test_merge_categories() {
+        let config = parse("merge_categories = frontend=dev, backend = dev");
+        assert_eq!(
+            config.merge_categories,
+            HashMap::from([
+                ("frontend".to_string(), "dev".to_string()),
+                ("backend".to_string(), "dev".to_string())
+            ])
+        );
+
+        assert_eq!(parse_category_map(""), Ok(HashMap::new()));
+    }
+
+    #[test]
+    fn test_category_targets() {
+        assert_eq!(
+            parse("category_targets = project1=4h, meeting = 1h30m, email=45m").category_targets,
+            HashMap::from([
+                ("project1".to_string(), Duration::hours(4)),
+                ("meeting".to_string(), Duration::minutes(90)),
+                ("email".to_string(), Duration::minutes(45)),
+            ])
+        );
+        assert!(parse_category_targets("project1=4").is_err());
+        assert!(parse_category_targets("project1=0h").is_err());
+        assert!(parse_category_targets("project1=-1h").is_err());
+        assert!(parse_category_targets("project1=h").is_err());
+        assert_eq!(parse_category_targets(""), Ok(HashMap::new()));
+        assert!(parse_category_map("frontend").is_err());
+        assert!(parse_category_map("frontend=").is_err());
+        assert!(parse("merge_categories = x").merge_categories.is_empty());
+    }
+
+    #[test]
+    fn test_sources() {
+        let mut config = parse("category_separator = /\nshow_arrival = false");
+        config.apply_env(|var| match var {
+            "RTIMELOG_CATEGORY_SEPARATOR" => Some(" › ".to_string()),
+            "RTIMELOG_COMPACT_DURATIONS" => Some("maybe".to_string()),
+            _ => None,
+        });
+
+        // environment shadows the file
+        assert_eq!(config.category_separator, " › ");
+        assert_eq!(
+            config.source("category_separator"),
+            Source::Env("RTIMELOG_CATEGORY_SEPARATOR".to_string())
+        );
+        assert_eq!(
+            config.source("show_arrival"),
+            Source::File(PathBuf::from(PATH))
+        );
+        // invalid value is ignored
+        assert!(!config.compact_durations);
+        assert_eq!(config.source("compact_durations"), Source::Default);
+
+        assert_eq!(
+            config.describe(),
+            "category_separator = \" › \"  # $RTIMELOG_CATEGORY_SEPARATOR
+compact_durations = false  # default
+merge_categories =   # default
+leaving_marker =   # default
+activity_order = first  # default
+show_arrival = false  # /conf/rtimelog/config
+percentage_bars = false  # default
+meeting_marker = meeting  # default
+date_format = \"%A, %F\"  # default
+untracked_tolerance = 10  # default
+category_case_insensitive = false  # default
+post_add_hook =   # default
+default_report_period = today  # default
+category_targets =   # default
+base_file =   # default
+show_seconds = false  # default
+slack_prefix = **  # default
+slack_categories =   # default
+profiles =   # default
+daily_subtotals = false  # default
+"
+        );
+        assert_eq!(config.get("unknown"), None);
+    }
+
+    #[test]
+    fn test_get_roundtrip() {
+        let config = parse(
+            "category_separator = \" › \"
+compact_durations = true
+merge_categories = b=x, a=x
+leaving_marker = home
+activity_order = duration
+show_arrival = false
+percentage_bars = true
+meeting_marker = calls
+date_format = \"%a %-d %b\"
+untracked_tolerance = 5
+category_case_insensitive = true
+post_add_hook = notify-send \"$RTIMELOG_TASK\"
+default_report_period = month
+category_targets = project1=4h, meeting=1h30m
+base_file = /shared/team/timelog.txt
+show_seconds = true
+slack_prefix = *
+slack_categories = break, private
+profiles = work=/home/me/work.txt, personal=/home/me/timelog.txt
+daily_subtotals = true",
+        );
+        let mut roundtrip = String::new();
+        for key in KEYS {
+            roundtrip.push_str(&format!("{key} = {}\n", config.get(key).unwrap()));
+        }
+        assert_eq!(parse(&roundtrip), config);
+    }
+
+    #[test]
+    fn test_timelog_file() {
+        let config = parse("profiles = work=/home/me/work.txt, personal = /home/me/timelog.txt");
+        assert_eq!(
+            config.timelog_file(Some("work")),
+            Ok(PathBuf::from("/home/me/work.txt"))
+        );
+        assert_eq!(
+            config.timelog_file(Some("personal")),
+            Ok(PathBuf::from("/home/me/timelog.txt"))
+        );
+        assert_eq!(
+            config.timelog_file(Some("play")),
+            Err("Unknown profile play, known: personal, work".to_string())
+        );
+        assert_eq!(
+            Config::default().timelog_file(Some("work")),
+            Err("Unknown profile work, the profiles setting is empty".to_string())
+        );
+
+        // without profile, the usual default file
+        assert_eq!(
+            config.timelog_file(None),
+            Timelog::get_default_file().map_err(|e| e.to_string())
+        );
+    }
+
+    #[test]
+    fn test_date_format() {
+        let date = NaiveDate::from_ymd_opt(2022, 6, 9).unwrap();
+        assert_eq!(Config::default().format_date(&date), "Thursday, 2022-06-09");
+        assert_eq!(
+            parse("date_format = \"%a %-d %b\"").format_date(&date),
+            "Thu 9 Jun"
+        );
+        // invalid format is ignored
+        assert_eq!(
+            parse("date_format = %Q").date_format,
+            Config::default().date_format
+        );
+        // dates have no time
+        assert_eq!(
+            parse("date_format = %H:%M").date_format,
+            Config::default().date_format
+        );
+        assert_eq!(
+            Config::default().set("date_format", "%F %H", Source::Default),
+            Err("invalid date format %F %H".to_string())
+        );
+    }
+
+    #[test]
+    fn test_adjust_to_output() {
+        let mut config = parse("percentage_bars = true");
+        assert!(config.percentage_bars);
+        config.adjust_to_output(false, true);
+        assert!(config.percentage_bars);
+        config.adjust_to_output(true, true);
+        assert!(!config.percentage_bars);
+
+        let mut config = parse("percentage_bars = true");
+        config.adjust_to_output(false, false);
+        assert!(!config.percentage_bars);
+    }
+
+    #[test]
+    fn test_nonexisting_file() {
+        assert_eq!(
+            Config::new_from_file(&PathBuf::from("/nonexisting")),
+            Config::default()
+        );
+    }
+}
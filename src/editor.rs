@@ -0,0 +1,112 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{self, ExitStatus};
+
+// editors which understand "+LINE" to jump to a line
+const LINE_EDITORS: [&str; 6] = ["vi", "vim", "nvim", "nano", "emacs", "micro"];
+
+/**
+ * Build the command to edit file, jumping to the given line if the editor supports it
+ *
+ * Prefers $VISUAL over $EDITOR, and falls back to vi. The editor variable can contain
+ * arguments, like "code --wait".
+ */
+pub fn editor_command(
+    visual: Option<String>,
+    editor: Option<String>,
+    file: &Path,
+    line: Option<usize>,
+) -> process::Command {
+    let editor = visual
+        .into_iter()
+        .chain(editor)
+        .find(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    let mut words = editor.split_whitespace();
+    // cannot fail, editor is not empty
+    let program = words.next().unwrap();
+
+    let mut cmd = process::Command::new(program);
+    cmd.args(words);
+    let basename = Path::new(program).file_name().and_then(|n| n.to_str());
+    if let (Some(n), Some(name)) = (line, basename) {
+        if LINE_EDITORS.contains(&name) {
+            cmd.arg(format!("+{n}"));
+        }
+    }
+    cmd.arg(file);
+    cmd
+}
+
+/**
+ * Run $VISUAL or $EDITOR on the timelog file, at the last line (i.e. today)
+ */
+pub fn run_editor(fname: &PathBuf) -> Result<ExitStatus, io::Error> {
+    let last_line = fs::read_to_string(fname)
+        .ok()
+        .map(|contents| contents.lines().count())
+        .filter(|&n| n > 0);
+    editor_command(
+        env::var("VISUAL").ok(),
+        env::var("EDITOR").ok(),
+        fname,
+        last_line,
+    )
+    .status()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    fn args(cmd: &process::Command) -> Vec<&OsStr> {
+        cmd.get_args().collect()
+    }
+
+    #[test]
+    fn test_editor_command() {
+        let file = Path::new("/tmp/timelog.txt");
+
+        let cmd = editor_command(None, None, file, None);
+        assert_eq!(cmd.get_program(), "vi");
+        assert_eq!(args(&cmd), vec!["/tmp/timelog.txt"]);
+
+        // jump to line
+        let cmd = editor_command(None, Some("/usr/bin/vim".to_string()), file, Some(42));
+        assert_eq!(cmd.get_program(), "/usr/bin/vim");
+        assert_eq!(args(&cmd), vec!["+42", "/tmp/timelog.txt"]);
+
+        // $VISUAL wins, with arguments; unknown editor does not get a line
+        let cmd = editor_command(
+            Some("code --wait".to_string()),
+            Some("vim".to_string()),
+            file,
+            Some(42),
+        );
+        assert_eq!(cmd.get_program(), "code");
+        assert_eq!(args(&cmd), vec!["--wait", "/tmp/timelog.txt"]);
+
+        // empty $VISUAL is ignored
+        let cmd = editor_command(Some(" ".to_string()), Some("nano".to_string()), file, None);
+        assert_eq!(cmd.get_program(), "nano");
+        assert_eq!(args(&cmd), vec!["/tmp/timelog.txt"]);
+    }
+}
@@ -0,0 +1,577 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate chrono;
+
+use std::io;
+
+use chrono::Duration;
+
+use crate::activity::{category, get_blocks, render, Activities, Block};
+use crate::config::Config;
+use crate::store::{Entry, Timelog};
+
+pub const COLUMNS: [&str; 5] = ["date", "start", "stop", "duration_minutes", "task"];
+
+// one row per block, in the order of COLUMNS
+pub fn row(block: &Block) -> [String; 5] {
+    [
+        block.start.format("%F").to_string(),
+        block.start.format("%H:%M").to_string(),
+        block.stop.format("%H:%M").to_string(),
+        block.duration().num_minutes().to_string(),
+        block.task.clone(),
+    ]
+}
+
+// tabs can't be quoted in TSV, so use backslash escapes
+fn escape_tsv(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/**
+ * Write the blocks of the entries as tab separated values, with a header line
+ */
+pub fn write_tsv(entries: &[Entry], w: &mut impl io::Write) -> io::Result<()> {
+    writeln!(w, "{}", COLUMNS.join("\t"))?;
+    for block in get_blocks(entries) {
+        let fields: Vec<String> = row(&block).iter().map(|f| escape_tsv(f)).collect();
+        writeln!(w, "{}", fields.join("\t"))?;
+    }
+    Ok(())
+}
+
+pub fn render_tsv(entries: &[Entry]) -> String {
+    render(|w| write_tsv(entries, w))
+}
+
+// quote fields with separators or quotes, like spreadsheets expect
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/**
+ * Write the raw entries as comma separated values, with a header line of COLUMNS
+ *
+ * Unlike write_tsv(), this has a row for every entry: The first entry of a day only
+ * provides the start time, so it has an empty start and duration.
+ */
+pub fn write_csv(entries: &[Entry], w: &mut impl io::Write) -> io::Result<()> {
+    writeln!(w, "{}", COLUMNS.join(","))?;
+    for day in Timelog::split_days(entries) {
+        let mut start: Option<&Entry> = None;
+        for entry in day {
+            let fields = [
+                entry.stop.format("%F").to_string(),
+                start.map_or(String::new(), |s| s.stop.format("%H:%M").to_string()),
+                entry.stop.format("%H:%M").to_string(),
+                start.map_or(String::new(), |s| {
+                    (entry.stop - s.stop).num_minutes().to_string()
+                }),
+                escape_csv(&entry.task),
+            ];
+            writeln!(w, "{}", fields.join(","))?;
+            start = Some(entry);
+        }
+    }
+    Ok(())
+}
+
+pub fn render_csv(entries: &[Entry]) -> String {
+    render(|w| write_csv(entries, w))
+}
+
+// JSON string literal, with the mandatory escapes
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// JSON array of already encoded values; pretty puts each item on its own line, indented
+// for the given nesting depth
+fn json_array(items: &[String], pretty: bool, depth: usize) -> String {
+    if items.is_empty() {
+        return "[]".to_string();
+    }
+    if !pretty {
+        return format!("[{}]", items.join(","));
+    }
+    let indent = "  ".repeat(depth + 1);
+    let items: Vec<String> = items.iter().map(|i| format!("{indent}{i}")).collect();
+    format!("[\n{}\n{}]", items.join(",\n"), "  ".repeat(depth))
+}
+
+// JSON object of keys with already encoded values, like json_array()
+fn json_object(fields: &[(&str, String)], pretty: bool, depth: usize) -> String {
+    let (indent, sep) = if pretty {
+        ("  ".repeat(depth + 1), " ")
+    } else {
+        (String::new(), "")
+    };
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| format!("{indent}{}:{sep}{value}", json_string(key)))
+        .collect();
+    if pretty {
+        format!("{{\n{}\n{}}}", fields.join(",\n"), "  ".repeat(depth))
+    } else {
+        format!("{{{}}}", fields.join(","))
+    }
+}
+
+/**
+ * Write the blocks of the entries as a JSON array of objects with the COLUMNS as keys
+ *
+ * This is compact for machines by default; pretty puts each field on its own indented line.
+ */
+pub fn write_json(entries: &[Entry], pretty: bool, w: &mut impl io::Write) -> io::Result<()> {
+    let objects: Vec<String> = get_blocks(entries)
+        .iter()
+        .map(|block| {
+            let fields: Vec<(&str, String)> = COLUMNS
+                .iter()
+                .zip(row(block))
+                .map(|(key, value)| {
+                    // the only number
+                    if *key == "duration_minutes" {
+                        (*key, value)
+                    } else {
+                        (*key, json_string(&value))
+                    }
+                })
+                .collect();
+            json_object(&fields, pretty, 1)
+        })
+        .collect();
+    writeln!(w, "{}", json_array(&objects, pretty, 0))
+}
+
+pub fn render_json(entries: &[Entry], pretty: bool) -> String {
+    render(|w| write_json(entries, pretty, w))
+}
+
+/**
+ * Write the activities with their durations and the totals as a JSON object
+ *
+ * Durations are in seconds, as they are in memory; like write_json(), this is compact
+ * unless pretty.
+ */
+pub fn write_activities_json(
+    activities: &Activities,
+    pretty: bool,
+    w: &mut impl io::Write,
+) -> io::Result<()> {
+    let items: Vec<String> = activities
+        .activities()
+        .iter()
+        .map(|a| {
+            json_object(
+                &[
+                    ("name", json_string(a.name())),
+                    ("seconds", a.duration().num_seconds().to_string()),
+                ],
+                pretty,
+                2,
+            )
+        })
+        .collect();
+    let report = json_object(
+        &[
+            ("activities", json_array(&items, pretty, 1)),
+            (
+                "total_work_seconds",
+                activities.total_work().num_seconds().to_string(),
+            ),
+            (
+                "total_slack_seconds",
+                activities.total_slack().num_seconds().to_string(),
+            ),
+        ],
+        pretty,
+        0,
+    );
+    writeln!(w, "{report}")
+}
+
+pub fn render_activities_json(activities: &Activities, pretty: bool) -> String {
+    render(|w| write_activities_json(activities, pretty, w))
+}
+
+// like gtimelog's format_duration_long(), e.g. "1 hour 5 min" or "2 hours"
+fn format_duration_long(d: &Duration) -> String {
+    let (h, m) = (d.num_hours(), d.num_minutes() % 60);
+    let hours = format!("{h} hour{}", if h == 1 { "" } else { "s" });
+    match (h, m) {
+        (0, m) => format!("{m} min"),
+        (_, 0) => hours,
+        (_, m) => format!("{hours} {m} min"),
+    }
+}
+
+/**
+ * Write gtimelog's plain weekly report, as it sends it by mail
+ *
+ * This lists the work per task (alphabetically, without slack) and per category, with
+ * the same layout as gtimelog.
+ */
+pub fn write_gtimelog_week(
+    entries: &[Entry],
+    week: u32,
+    name: &str,
+    email: &str,
+    config: &Config,
+    w: &mut impl io::Write,
+) -> io::Result<()> {
+    writeln!(w, "To: {email}")?;
+    writeln!(w, "Subject: Weekly report for {name} (week {week:02})")?;
+    writeln!(w)?;
+
+    let activities = Activities::new_from_entries(entries).with_config(config);
+    let mut work: Vec<(String, Duration)> = activities
+        .by_task()
+        .into_iter()
+        .filter(|(task, d)| !activities.is_slack(task) && !d.is_zero())
+        .collect();
+    work.sort_by_key(|(task, _)| task.to_lowercase());
+
+    // None for tasks without category; gtimelog lists these last as "(none)"
+    let mut categories: Vec<(Option<&str>, Duration)> = Vec::new();
+    for (task, d) in &work {
+        let cat = category(task);
+        match categories.iter_mut().find(|(c, _)| *c == cat) {
+            Some((_, total)) => *total += *d,
+            None => categories.push((cat, *d)),
+        }
+    }
+    categories.sort_by_key(|(c, _)| (c.is_none(), c.map(str::to_string)));
+
+    if !work.is_empty() {
+        for (task, d) in &work {
+            // gtimelog capitalizes the first letter
+            let mut chars = task.chars();
+            let task: String = chars
+                .next()
+                .into_iter()
+                .flat_map(char::to_uppercase)
+                .chain(chars)
+                .collect();
+            writeln!(w, "{task:<62}  {}", format_duration_long(d))?;
+        }
+        writeln!(w)?;
+    }
+    let total: Duration = work.iter().map(|(_, d)| *d).sum();
+    writeln!(
+        w,
+        "Total work done this week: {}",
+        format_duration_long(&total)
+    )?;
+
+    if categories.iter().any(|(c, _)| c.is_some()) {
+        writeln!(w)?;
+        writeln!(w, "By category:")?;
+        writeln!(w)?;
+        for (cat, d) in &categories {
+            writeln!(
+                w,
+                "{:<62}  {}",
+                cat.unwrap_or("(none)"),
+                format_duration_long(d)
+            )?;
+        }
+        writeln!(w)?;
+    }
+    Ok(())
+}
+
+pub fn render_gtimelog_week(
+    entries: &[Entry],
+    week: u32,
+    name: &str,
+    email: &str,
+    config: &Config,
+) -> String {
+    render(|w| write_gtimelog_week(entries, week, name, email, config, w))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_tsv() {
+        assert_eq!(
+            render_tsv(&[]),
+            "date\tstart\tstop\tduration_minutes\ttask\n"
+        );
+
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 08:45: customer joe: support, call
+2022-06-10 09:00: ** tea
+",
+        );
+        // commas need no quoting
+        assert_eq!(
+            render_tsv(tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1)),
+            "date\tstart\tstop\tduration_minutes\ttask
+2022-06-10\t07:00\t08:45\t105\tcustomer joe: support, call
+2022-06-10\t08:45\t09:00\t15\t** tea
+"
+        );
+    }
+
+    #[test]
+    fn test_csv() {
+        assert_eq!(render_csv(&[]), "date,start,stop,duration_minutes,task\n");
+
+        let tl = Timelog::new_from_string(
+            r#"
+2022-06-09 07:00: arrived
+2022-06-09 08:45: customer joe: support, call
+2022-06-09 09:00: ** tea
+
+2022-06-10 10:00: arrived
+2022-06-10 10:20: say "hi"
+"#,
+        );
+        // the start goes back to empty on the next day
+        assert_eq!(
+            render_csv(tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 2)),
+            r#"date,start,stop,duration_minutes,task
+2022-06-09,,07:00,,arrived
+2022-06-09,07:00,08:45,105,"customer joe: support, call"
+2022-06-09,08:45,09:00,15,** tea
+2022-06-10,,10:00,,arrived
+2022-06-10,10:00,10:20,20,"say ""hi"""
+"#
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        assert_eq!(render_json(&[], false), "[]\n");
+        assert_eq!(render_json(&[], true), "[]\n");
+
+        let tl = Timelog::new_from_string(
+            r#"
+2022-06-10 07:00: arrived
+2022-06-10 08:45: customer joe: support, "call"
+2022-06-10 09:00: ** tea\break
+"#,
+        );
+        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1);
+        let compact = render_json(entries, false);
+        assert_eq!(
+            compact,
+            r#"[{"date":"2022-06-10","start":"07:00","stop":"08:45","duration_minutes":105,"task":"customer joe: support, \"call\""},{"date":"2022-06-10","start":"08:45","stop":"09:00","duration_minutes":15,"task":"** tea\\break"}]
+"#
+        );
+
+        let pretty = render_json(entries, true);
+        assert_eq!(
+            pretty,
+            r#"[
+  {
+    "date": "2022-06-10",
+    "start": "07:00",
+    "stop": "08:45",
+    "duration_minutes": 105,
+    "task": "customer joe: support, \"call\""
+  },
+  {
+    "date": "2022-06-10",
+    "start": "08:45",
+    "stop": "09:00",
+    "duration_minutes": 15,
+    "task": "** tea\\break"
+  }
+]
+"#
+        );
+        // same structure, only formatted differently
+        let unformatted: String = pretty
+            .lines()
+            .map(|l| l.trim_start().replacen("\": ", "\":", 1))
+            .collect();
+        assert_eq!(unformatted + "\n", compact);
+
+        assert_eq!(json_string("a\u{1}b"), r#""a\u0001b""#);
+    }
+
+    // the number after "key": in JSON, to check the totals without a full parser
+    fn json_number(json: &str, key: &str) -> i64 {
+        let value = json.split(&format!("\"{key}\":")).nth(1).unwrap();
+        value
+            .trim_start()
+            .split(|c: char| !c.is_ascii_digit())
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_activities_json() {
+        assert_eq!(
+            render_activities_json(&Activities::new_from_entries(&[]), false),
+            r#"{"activities":[],"total_work_seconds":0,"total_slack_seconds":0}
+"#
+        );
+
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 08:45: gtimelog: code
+2022-06-10 09:00: ** tea
+2022-06-10 09:30: gtimelog: code
+",
+        );
+        let a = Activities::new_from_entries(
+            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1),
+        );
+        let compact = render_activities_json(&a, false);
+        assert_eq!(
+            compact,
+            r#"{"activities":[{"name":"gtimelog: code","seconds":8100},{"name":"** tea","seconds":900}],"total_work_seconds":8100,"total_slack_seconds":900}
+"#
+        );
+        let pretty = render_activities_json(&a, true);
+        assert_eq!(
+            pretty,
+            r#"{
+  "activities": [
+    {
+      "name": "gtimelog: code",
+      "seconds": 8100
+    },
+    {
+      "name": "** tea",
+      "seconds": 900
+    }
+  ],
+  "total_work_seconds": 8100,
+  "total_slack_seconds": 900
+}
+"#
+        );
+        for json in [&compact, &pretty] {
+            assert_eq!(json_number(json, "total_work_seconds"), 135 * 60);
+            assert_eq!(json_number(json, "total_slack_seconds"), 15 * 60);
+        }
+    }
+
+    // golden output in the layout of gtimelog's weekly report
+    const GTIMELOG_WEEK: &str = "To: activity@example.com
+Subject: Weekly report for Joe Developer (week 23)
+
+Bug triage                                                      1 hour
+Customer joe: support                                           2 hours 15 min
+Gtimelog: code                                                  6 hours 40 min
+Meeting                                                         45 min
+
+Total work done this week: 10 hours 40 min
+
+By category:
+
+customer joe                                                    2 hours 15 min
+gtimelog                                                        6 hours 40 min
+(none)                                                          1 hour 45 min
+
+";
+
+    #[test]
+    fn test_gtimelog_week() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-06 08:00: arrived
+2022-06-06 12:00: gtimelog: code
+2022-06-06 12:45: ** lunch
+2022-06-06 13:30: meeting
+2022-06-06 15:00: customer joe: support
+
+2022-06-10 07:00: arrived
+2022-06-10 09:20: gtimelog: code
+2022-06-10 09:40: **tea
+2022-06-10 10:40: bug triage
+2022-06-10 11:00: gtimelog: code (cont.)
+2022-06-10 11:45: customer joe: support
+",
+        );
+        let week = tl.get_period(&NaiveDate::from_ymd_opt(2022, 6, 6).unwrap(), 7);
+        assert_eq!(
+            render_gtimelog_week(
+                week,
+                23,
+                "Joe Developer",
+                "activity@example.com",
+                &Config::default()
+            ),
+            GTIMELOG_WEEK
+        );
+
+        // no work, no categories
+        assert_eq!(
+            render_gtimelog_week(&[], 1, "Anonymous", "a@example.com", &Config::default()),
+            "To: a@example.com
+Subject: Weekly report for Anonymous (week 01)
+
+Total work done this week: 0 min
+"
+        );
+    }
+
+    #[test]
+    fn test_format_duration_long() {
+        assert_eq!(format_duration_long(&Duration::minutes(0)), "0 min");
+        assert_eq!(format_duration_long(&Duration::minutes(5)), "5 min");
+        assert_eq!(format_duration_long(&Duration::minutes(60)), "1 hour");
+        assert_eq!(format_duration_long(&Duration::minutes(65)), "1 hour 5 min");
+        assert_eq!(
+            format_duration_long(&Duration::minutes(125)),
+            "2 hours 5 min"
+        );
+    }
+
+    #[test]
+    fn test_escape_tsv() {
+        assert_eq!(escape_tsv("a, b"), "a, b");
+        assert_eq!(escape_tsv("a\tb"), "a\\tb");
+        assert_eq!(escape_tsv("a\nb\r"), "a\\nb\\r");
+        assert_eq!(escape_tsv("C:\\dir"), "C:\\\\dir");
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate dirs;
+
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+// don't let the history file grow without bounds
+const MAX_LINES: usize = 1000;
+
+/**
+ * Persistent history of all inputs, across sessions
+ */
+#[derive(Default, Debug)]
+pub struct History {
+    lines: Vec<String>,
+    pub filename: Option<PathBuf>,
+}
+
+impl History {
+    pub fn new_from_default_file() -> History {
+        match History::get_default_file(env::var_os("XDG_STATE_HOME"), dirs::home_dir()) {
+            Some(path) => History::new_from_file(&path),
+            None => History::default(),
+        }
+    }
+
+    pub fn new_from_file(path: &PathBuf) -> History {
+        let lines = match fs::read_to_string(path) {
+            Ok(contents) => contents.lines().map(String::from).collect(),
+            Err(e) => {
+                if e.kind() != io::ErrorKind::NotFound {
+                    eprintln!("WARNING: Failed to read history {}: {}", path.display(), e);
+                }
+                Vec::new()
+            }
+        };
+
+        History {
+            lines,
+            filename: Some(path.clone()),
+        }
+    }
+
+    pub fn get_default_file(
+        state_home: Option<OsString>,
+        home: Option<PathBuf>,
+    ) -> Option<PathBuf> {
+        let mut path = match state_home {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => {
+                let mut dir = home?;
+                dir.push(".local");
+                dir.push("state");
+                dir
+            }
+        };
+        path.push("rtimelog");
+        path.push("history");
+        Some(path)
+    }
+
+    pub fn add(&mut self, line: &str) {
+        if !line.is_empty() {
+            self.lines.push(line.to_string());
+        }
+    }
+
+    pub fn get_lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn save(&self) -> Result<(), io::Error> {
+        if let Some(filename) = &self.filename {
+            if let Some(parent) = filename.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let start = self.lines.len().saturating_sub(MAX_LINES);
+            let mut contents = self.lines[start..].join("\n");
+            contents.push('\n');
+            fs::write(filename, contents)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_get_default_file() {
+        assert_eq!(
+            History::get_default_file(
+                Some(OsString::from("/state")),
+                Some(PathBuf::from("/home/me"))
+            ),
+            Some(PathBuf::from("/state/rtimelog/history"))
+        );
+        // empty XDG_STATE_HOME is treated as unset
+        assert_eq!(
+            History::get_default_file(Some(OsString::new()), Some(PathBuf::from("/home/me"))),
+            Some(PathBuf::from("/home/me/.local/state/rtimelog/history"))
+        );
+        assert_eq!(
+            History::get_default_file(None, Some(PathBuf::from("/home/me"))),
+            Some(PathBuf::from("/home/me/.local/state/rtimelog/history"))
+        );
+        assert_eq!(History::get_default_file(None, None), None);
+    }
+
+    #[test]
+    fn test_save_load() {
+        let mut path = env::temp_dir();
+        path.push(format!("rtimelog-test-history-{}", std::process::id()));
+        path.push("history");
+
+        let mut h = History::new_from_file(&path);
+        assert!(h.get_lines().is_empty());
+        h.add("code");
+        h.add("");
+        h.add(":w");
+        h.save().unwrap();
+
+        let h = History::new_from_file(&path);
+        assert_eq!(h.get_lines(), &["code", ":w"]);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::process::{self, Stdio};
+use std::thread;
+
+use crate::store::Entry;
+
+/**
+ * Build the shell command for the post_add_hook setting
+ *
+ * The new entry is passed as $RTIMELOG_TASK and $RTIMELOG_STOP (like "2022-06-10 08:15").
+ */
+pub fn hook_command(command: &str, entry: &Entry) -> process::Command {
+    let mut cmd = process::Command::new("sh");
+    cmd.args(["-c", command])
+        .env("RTIMELOG_TASK", &entry.task)
+        .env(
+            "RTIMELOG_STOP",
+            entry.stop.format("%Y-%m-%d %H:%M").to_string(),
+        )
+        .stdin(Stdio::null());
+    cmd
+}
+
+/**
+ * Run the post_add_hook in the background
+ *
+ * This does not wait for the hook, and its failure does not affect the added entry,
+ * it just causes a warning.
+ */
+pub fn run_post_add_hook(command: &str, entry: &Entry) {
+    match hook_command(command, entry).spawn() {
+        Ok(mut child) => {
+            let command = command.to_string();
+            // reap the hook, so that it does not stay around as a zombie
+            thread::spawn(move || match child.wait() {
+                Ok(status) if status.success() => (),
+                Ok(status) => eprintln!("WARNING: post_add_hook '{command}' failed: {status}"),
+                Err(e) => eprintln!("WARNING: post_add_hook '{command}' failed: {e}"),
+            });
+        }
+        Err(e) => eprintln!("WARNING: Failed to run post_add_hook '{command}': {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn test_hook_command() {
+        let entry = Entry {
+            stop: NaiveDate::from_ymd_opt(2022, 6, 10)
+                .unwrap()
+                .and_hms_opt(8, 15, 0)
+                .unwrap(),
+            task: "project1: code".to_string(),
+        };
+        let out = env::temp_dir().join(format!("rtimelog-hook-test-{}", process::id()));
+        let status = hook_command(
+            &format!("echo \"$RTIMELOG_STOP|$RTIMELOG_TASK\" > {}", out.display()),
+            &entry,
+        )
+        .status()
+        .unwrap();
+        assert!(status.success());
+        assert_eq!(
+            fs::read_to_string(&out).unwrap(),
+            "2022-06-10 08:15|project1: code\n"
+        );
+        fs::remove_file(&out).unwrap();
+    }
+}
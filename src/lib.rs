@@ -1,3 +1,12 @@
 pub mod activity;
+pub mod clipboard;
+pub mod clock;
 pub mod commands;
+pub mod config;
+pub mod editor;
+pub mod export;
+pub mod history;
+pub mod hook;
+pub mod pattern;
+pub mod report;
 pub mod store;
@@ -15,15 +15,26 @@
 
 use std::env;
 use std::error::Error;
-use std::io;
+use std::fs;
+use std::io::{self, IsTerminal};
 use std::path::PathBuf;
 use std::process;
 
-use chrono::prelude::*;
-use rustyline::{error::ReadlineError, Editor};
+use chrono::NaiveDateTime;
 
+use rustyline::{config::Behavior, error::ReadlineError, Editor};
+
+use rtimelog::activity::{
+    render_multi_day, status_line, status_line_update, truncate_line, write_timeline, Activities,
+};
+use rtimelog::clock;
 use rtimelog::commands::{Command, TimeMode};
-use rtimelog::store::Timelog;
+use rtimelog::config::Config;
+use rtimelog::editor::run_editor;
+use rtimelog::history::History;
+use rtimelog::hook::run_post_add_hook;
+use rtimelog::report::arrival_header;
+use rtimelog::store::{Entry, StoreError, Timelog};
 
 fn clear_screen() {
     print!("{esc}c", esc = 27 as char);
@@ -43,108 +54,432 @@ fn get_input(rl: &mut Editor<()>) -> Result<String, ReadlineError> {
     }
 }
 
-fn show_help() {
-    println!(
-        "
-:w      - switch to weekly mode
+// (topic, text) for :h <topic>; plain :h shows all of them
+const HELP_TOPICS: [(&str, &str); 3] = [
+    (
+        "modes",
+        ":w      - switch to weekly mode
 :w<num> - last <num> weeks
 :d      - switch to daily mode
 :d<num> - last <num> days
-:q      - quit
+:m      - switch to monthly mode
+:m<num> - last <num> months",
+    ),
+    (
+        "commands",
+        ":q      - quit
 :h      - show this help
+:h <topic> - show help about one topic (modes, commands, add)
 :e      - open timelog.txt in $EDITOR
-^r      - history search (like in bash) through currently shown activities
+:u      - undo: remove the last entry
+:t      - show timeline of currently shown entries
+:pause  - freeze the time since the last entry, e.g. over lunch; :resume to continue
+:focus-cat <category> - highlight category and show its total; without category: stop
+/<text> - only show tasks containing text, ignoring case; / alone shows all again
+:profile <name> - switch to the timelog of that profile; without name: the default timelog
+^r      - history search (like in bash) through currently shown activities",
+    ),
+    (
+        "add",
+        "Any other input is the description of a task that you just finished.
+Start it with ** for slack time, end it with (cont.) to mark a resumed task.
+Start it with a time like 10:45 if you finished it earlier today, or with -15
+if you finished it 15 minutes ago.",
+    ),
+];
 
-Any other input is the description of a task that you just finished."
-    );
+fn show_help(topic: Option<String>) {
+    match topic {
+        None => {
+            for (_, text) in HELP_TOPICS {
+                println!("\n{text}");
+            }
+        }
+        Some(topic) => match HELP_TOPICS.iter().find(|(name, _)| *name == topic) {
+            Some((_, text)) => println!("\n{text}"),
+            None => {
+                println!("\nNo help about '{topic}', showing general help:");
+                show_help(None);
+            }
+        },
+    }
 }
 
-fn show(timelog: &Timelog, mode: &TimeMode, rl_editor: &mut Editor<()>) {
+fn get_entries<'a>(timelog: &'a Timelog, mode: &TimeMode) -> &'a [Entry] {
+    let today = clock::today();
+    match mode {
+        TimeMode::Day(n) => timelog.get_n_days(&today, *n),
+        TimeMode::Week(n) => timelog.get_n_weeks(&today, *n),
+        TimeMode::Month(n) => timelog.get_n_months(&today, *n),
+    }
+}
+
+fn show(
+    timelog: &Timelog,
+    mode: &TimeMode,
+    focus: &Option<String>,
+    filter: &Option<String>,
+    config: &Config,
+    history: &History,
+    rl_editor: &mut Editor<()>,
+) {
     clear_screen();
-    let today = Local::now().date_naive();
-    let entries = match mode {
+    let header = match mode {
         TimeMode::Day(n) => {
             if *n == 1 {
-                println!("Work done today {}:", timelog.get_today_as_string());
+                format!("Work done today {}:", timelog.get_today_as_string())
             } else {
-                println!("Work done in the last {n} days:");
+                format!("Work done in the last {n} days:")
             }
-            timelog.get_n_days(&today, *n)
         }
         TimeMode::Week(n) => {
             if *n == 1 {
-                println!("Work done this week {}:", timelog.get_this_week_as_string());
+                format!("Work done this week {}:", timelog.get_this_week_as_string())
+            } else {
+                format!("Work done in the last {n} weeks:")
+            }
+        }
+        TimeMode::Month(n) => {
+            if *n == 1 {
+                format!("Work done this month ({}):", clock::today().format("%B %Y"))
             } else {
-                println!("Work done in the last {n} weeks:");
+                format!("Work done in the last {n} months:")
             }
-            timelog.get_n_weeks(&today, *n)
         }
     };
+    // keep the header on one line in narrow terminals
+    match rl_editor.dimensions() {
+        Some((width, _)) => println!("{}", truncate_line(&header, width)),
+        None => println!("{header}"),
+    }
+
+    let entries = get_entries(timelog, mode);
+    if *mode == TimeMode::Day(1) && config.show_arrival {
+        if let Some(header) = arrival_header(entries) {
+            println!("{header}");
+        }
+    }
 
-    let a = rtimelog::activity::Activities::new_from_entries(entries);
-    println!("{a}");
+    let activities = match filter {
+        Some(keyword) => {
+            println!("Only tasks containing '{keyword}'; / shows all again");
+            Activities::new_from_entries_matching(entries, keyword, config)
+        }
+        None => Activities::new_from_entries_with_config(entries, config),
+    };
+    if *mode == TimeMode::Day(1) && !config.category_targets.is_empty() {
+        println!(
+            "\n{}",
+            activities.render_category_progress(&config.category_targets)
+        );
+    }
+    // the days are not filtered, so show the filtered activities all together
+    let multi_day = match mode {
+        TimeMode::Day(n) => *n > 1,
+        TimeMode::Week(_) | TimeMode::Month(_) => config.daily_subtotals,
+    } && filter.is_none();
+    match (multi_day, focus) {
+        (true, Some(cat)) => println!(
+            "{}\n\n{}",
+            activities.focus_header(cat),
+            render_multi_day(entries, config)
+        ),
+        (true, None) => println!("{}", render_multi_day(entries, config)),
+        (_, Some(cat)) => println!("{}", activities.render_focus(cat)),
+        (_, None) => println!("{activities}"),
+    }
 
     rl_editor.clear_history();
+    // older inputs first, so that currently shown activities are found first
+    for line in history.get_lines() {
+        rl_editor.add_history_entry(line);
+    }
     for a in Timelog::get_history(entries) {
         rl_editor.add_history_entry(a);
     }
 }
 
-fn show_prompt(timelog: &Timelog) -> Result<(), io::Error> {
-    let since_last = timelog
-        .get_n_days(&Local::now().date_naive(), 1)
-        .last()
-        .map(|e| Local::now().naive_local().signed_duration_since(e.stop));
-
-    let since_str = match since_last {
-        None => "no entries yet today".to_string(),
-        Some(d) => format!(
-            "{} h {} min since last entry",
-            d.num_hours(),
-            d.num_minutes() % 60
-        ),
+// reload the timelog if another program changed its file; returns the notice about the changes
+fn reload_if_changed(
+    timelog: &mut Timelog,
+    load: impl Fn(&PathBuf) -> Result<Timelog, StoreError>,
+) -> Result<Option<String>, StoreError> {
+    let Some(fname) = timelog
+        .filename
+        .clone()
+        .filter(|_| timelog.changed_externally())
+    else {
+        return Ok(None);
     };
+    let reloaded = load(&fname)?;
+    let notice = format!("Reloaded: {}", timelog.diff(&reloaded));
+    *timelog = reloaded;
+    Ok(Some(notice))
+}
+
+// paused is the time of :pause, which freezes the status line
+fn status(timelog: &Timelog, config: &Config, paused: Option<NaiveDateTime>) -> String {
+    status_line(
+        timelog.get_n_days(&clock::today(), 1),
+        paused.unwrap_or_else(clock::now),
+        config.compact_durations,
+        paused.is_some(),
+    )
+}
 
-    println!("\n{since_str}; type command (:h for help) or entry");
+fn show_prompt(
+    timelog: &Timelog,
+    config: &Config,
+    paused: Option<NaiveDateTime>,
+) -> Result<(), io::Error> {
+    println!("\n{}", status(timelog, config, paused));
     Ok(())
 }
 
-fn run_editor(fname: &PathBuf) {
-    let editor = env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
-    if let Err(e) = process::Command::new(&editor).arg(fname).status() {
-        println!("Failed to run {} on {:?}: {:?}", &editor, fname, e);
-    }
+// "-" reads the timelog from stdin, for demos and reproducing bugs; that does not get saved
+// Returns the file, whether to check it for problems at startup, and whether to always
+// redraw the whole screen.
+fn parse_args() -> (PathBuf, bool, bool) {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let mut take_flag = |name: &str| match args.iter().position(|a| a == name) {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+    let lint = !take_flag("--no-lint");
+    let simple = take_flag("--simple");
+    let file = match args.as_slice() {
+        [] => Timelog::get_default_file().unwrap_or_else(|e| {
+            eprintln!("{e}");
+            process::exit(1);
+        }),
+        [opt, path] if opt == "--file" => PathBuf::from(path),
+        [opt, name] if opt == "--profile" => Config::new_from_default_file()
+            .timelog_file(Some(name))
+            .unwrap_or_else(|e| {
+                eprintln!("{e}");
+                process::exit(1);
+            }),
+        _ => {
+            eprintln!("Usage: rtimelog [--file PATH|-|--profile NAME] [--no-lint] [--simple]");
+            process::exit(1);
+        }
+    };
+    (file, lint, simple)
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let mut timelog = Timelog::new_from_default_file();
+    let (file, lint, simple) = parse_args();
+    // cursor movement needs a terminal
+    let incremental = !simple && io::stdout().is_terminal();
+    let from_stdin = file.as_os_str() == "-";
+    let mut config = Config::new_from_default_file();
+    config.adjust_to_output(
+        env::var_os("NO_COLOR").is_some(),
+        io::stdout().is_terminal(),
+    );
+    let load = |path: &PathBuf| {
+        Timelog::new_from_file(path).map(|t| t.with_base_file(config.base_file.as_ref()))
+    };
+    let mut timelog = if from_stdin {
+        Timelog::new_from_reader(io::stdin()).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            process::exit(1);
+        })
+    } else {
+        load(&file).unwrap_or_else(|e| {
+            eprintln!("{e}, e.g. with rtimelog-cmd edit");
+            process::exit(1);
+        })
+    };
     let mut running = true;
     let mut time_mode = TimeMode::Day(1);
-    let mut readline = Editor::<()>::new()?;
+    let mut focus = None;
+    let mut filter = None;
+    let mut paused = None;
+    // stdin is used up by the timelog, so read commands from the terminal
+    let behavior = if from_stdin {
+        Behavior::PreferTerm
+    } else {
+        Behavior::Stdio
+    };
+    let mut readline =
+        Editor::<()>::with_config(rustyline::Config::builder().behavior(behavior).build())?;
     let mut do_show = true;
+    // only the status line changed, update it in place
+    let mut update_status = false;
+    let mut history = History::new_from_default_file();
+    // point out problems in the file once, as they may make the times shown wrong
+    let mut notice = if lint && !from_stdin {
+        Timelog::lint_banner(&fs::read_to_string(&file).unwrap_or_default())
+    } else {
+        None
+    };
 
     while running {
         if do_show {
-            show(&timelog, &time_mode, &mut readline);
+            show(
+                &timelog,
+                &time_mode,
+                &focus,
+                &filter,
+                &config,
+                &history,
+                &mut readline,
+            );
         }
         do_show = true;
-        show_prompt(&timelog)?;
+        // shown once below the redrawn activities
+        if let Some(n) = notice.take() {
+            println!("\n{n}");
+        }
+        if timelog.changed_externally() {
+            println!(
+                "\n{} was changed by another program; it will be reloaded before adding the next entry",
+                timelog.filename.as_ref().unwrap().display()
+            );
+        }
+        if update_status {
+            print!("{}", status_line_update(&status(&timelog, &config, paused)));
+            update_status = false;
+        } else {
+            show_prompt(&timelog, &config, paused)?;
+        }
+
+        let input = get_input(&mut readline)?;
+        history.add(&input);
 
-        match Command::parse(get_input(&mut readline)?) {
+        match Command::parse(input) {
+            // nothing changed but the time, so avoid the flicker of a full redraw
+            Command::Nothing if incremental && !timelog.changed_externally() => {
+                do_show = false;
+                update_status = true;
+            }
             Command::Nothing => (),
             Command::Quit => running = false,
-            Command::Help => {
-                show_help();
+            Command::Help(topic) => {
+                show_help(topic);
                 do_show = false;
             }
-            Command::Edit => {
-                run_editor(&timelog.filename.unwrap());
-                timelog = Timelog::new_from_default_file();
+            Command::Edit => match timelog.filename.clone() {
+                Some(fname) => {
+                    if let Err(e) = run_editor(&fname) {
+                        println!("Failed to run editor on {:?}: {:?}", fname, e);
+                    }
+                    match load(&fname) {
+                        Ok(edited) => {
+                            notice = Some(format!("Edited: {}", timelog.diff(&edited)));
+                            timelog = edited;
+                        }
+                        Err(e) => {
+                            println!("Error: {e} with :e");
+                            do_show = false;
+                        }
+                    }
+                }
+                None => {
+                    println!("Error: Cannot edit a timelog read from stdin");
+                    do_show = false;
+                }
+            },
+            Command::Undo => {
+                // don't clobber changes from another program
+                match reload_if_changed(&mut timelog, load) {
+                    Ok(reloaded) => notice = reloaded.or(notice),
+                    Err(e) => {
+                        println!("Error: Cannot reload the changed timelog: {e}");
+                        do_show = false;
+                        continue;
+                    }
+                }
+                match timelog.remove_last() {
+                    Some(entry) => {
+                        notice = Some(format!("Removed: {}", entry.task));
+                        if timelog.filename.is_some() {
+                            if let Err(e) = timelog.save() {
+                                println!("Error: {e}");
+                                do_show = false;
+                            }
+                        }
+                    }
+                    None => {
+                        println!("Error: No entries to undo");
+                        do_show = false;
+                    }
+                }
+            }
+            Command::Timeline => {
+                clear_screen();
+                write_timeline(
+                    get_entries(&timelog, &time_mode),
+                    &config,
+                    &mut io::stdout(),
+                )?;
+                do_show = false;
             }
+            Command::Pause => paused = paused.or(Some(clock::now())),
+            Command::Resume => paused = None,
+            Command::Focus(cat) => focus = cat,
+            Command::Filter(keyword) => filter = keyword,
+            Command::Profile(name) => match config.timelog_file(name.as_deref()) {
+                Ok(path) => match load(&path) {
+                    Ok(switched) => {
+                        notice = Some(format!("Switched to {}", path.display()));
+                        timelog = switched;
+                    }
+                    Err(e) => {
+                        println!("Error: {e}");
+                        do_show = false;
+                    }
+                },
+                Err(e) => {
+                    println!("Error: {e}");
+                    do_show = false;
+                }
+            },
             Command::SwitchMode(m) => time_mode = m,
-            Command::Add(a) => {
-                timelog.add(a);
-                timelog.save()?;
+            command @ (Command::Add(_) | Command::AddAt(..) | Command::AddAgo(..)) => {
+                // don't clobber changes from another program, apply the new entry on top of them
+                match reload_if_changed(&mut timelog, load) {
+                    Ok(reloaded) => notice = reloaded.or(notice),
+                    Err(e) => {
+                        println!("Error: Cannot reload the changed timelog: {e}");
+                        do_show = false;
+                        continue;
+                    }
+                }
+                let added = match command {
+                    Command::AddAt(time, a) => timelog
+                        .add_at(a, clock::on_day(&clock::today(), time))
+                        .cloned(),
+                    Command::AddAgo(minutes, a) => timelog.add_minutes_ago(a, minutes).cloned(),
+                    Command::Add(a) => Ok(timelog.add(a).clone()),
+                    _ => unreachable!(),
+                };
+                let entry = match added {
+                    // a new entry is the end of the break
+                    Ok(entry) => {
+                        paused = None;
+                        entry
+                    }
+                    Err(e) => {
+                        println!("Error: {e}");
+                        do_show = false;
+                        continue;
+                    }
+                };
+                // a timelog from stdin only lives in memory
+                if timelog.filename.is_none() {
+                    continue;
+                }
+                if let Err(e) = timelog.save() {
+                    println!("Error: {e}");
+                    do_show = false;
+                } else if let Some(hook) = &config.post_add_hook {
+                    run_post_add_hook(hook, &entry);
+                }
             }
             Command::Error(e) => {
                 println!("Error: {}", e);
@@ -152,5 +487,13 @@ fn main() -> Result<(), Box<dyn Error>> {
             }
         }
     }
+
+    if let Err(e) = history.save() {
+        println!(
+            "Failed to save history to {:?}: {}",
+            history.filename.unwrap(),
+            e
+        );
+    }
     Ok(())
 }
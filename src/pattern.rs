@@ -0,0 +1,339 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+// metacharacters of full regexps which we don't support
+#[cfg(not(feature = "regex"))]
+const UNSUPPORTED: &str = "[]{}?|";
+
+#[cfg(not(feature = "regex"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Atom {
+    Char(char),
+    // .
+    Any,
+    // \d
+    Digit,
+}
+
+#[cfg(not(feature = "regex"))]
+impl Atom {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Atom::Char(a) => *a == c,
+            Atom::Any => true,
+            Atom::Digit => c.is_ascii_digit(),
+        }
+    }
+}
+
+#[cfg(not(feature = "regex"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    // atom with the minimum and whether it can repeat: once, * or +
+    Atom(Atom, usize, bool),
+    GroupStart,
+    GroupEnd,
+    // $
+    End,
+}
+
+/**
+ * Minimal regular expression: literal characters, `.`, `\d`, `*`, `+`, `^`, `$`, and one
+ * `(group)`
+ *
+ * This is enough for filtering tasks and extracting e.g. ticket numbers without pulling in
+ * a regex dependency. `\` also escapes the metacharacters. With the `regex` feature, this
+ * uses the full syntax of the regex crate instead.
+ */
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    #[cfg(not(feature = "regex"))]
+    tokens: Vec<Token>,
+    #[cfg(not(feature = "regex"))]
+    anchored: bool,
+    #[cfg(feature = "regex")]
+    regex: regex::Regex,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PatternError(String);
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+#[cfg(feature = "regex")]
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Pattern, PatternError> {
+        match regex::Regex::new(pattern) {
+            Ok(regex) => Ok(Pattern { regex }),
+            Err(e) => Err(PatternError(format!("Invalid pattern {pattern}: {e}"))),
+        }
+    }
+
+    // search anywhere in text, unless anchored with ^
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+
+    // the text of the first group in the leftmost match, or the whole match without a group
+    pub fn captures(&self, text: &str) -> Option<String> {
+        let captures = self.regex.captures(text)?;
+        let group = if self.regex.captures_len() > 1 { 1 } else { 0 };
+        captures.get(group).map(|m| m.as_str().to_string())
+    }
+}
+
+#[cfg(not(feature = "regex"))]
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Pattern, PatternError> {
+        let error = |msg: &str| Err(PatternError(format!("Invalid pattern {pattern}: {msg}")));
+        let (anchored, body) = match pattern.strip_prefix('^') {
+            Some(body) => (true, body),
+            None => (false, pattern),
+        };
+        let mut tokens = Vec::new();
+        let mut groups = 0;
+        let mut chars = body.chars().peekable();
+        while let Some(c) = chars.next() {
+            let token = match c {
+                c if UNSUPPORTED.contains(c) => {
+                    return error(&format!(
+                        "'{c}' is not supported, only . \\d * + ^ $ and one (group)"
+                    ))
+                }
+                '.' => Token::Atom(Atom::Any, 1, false),
+                '\\' => match chars.next() {
+                    Some('d') => Token::Atom(Atom::Digit, 1, false),
+                    // \w, \s, \b etc. would silently become literal letters
+                    Some(c) if c.is_alphanumeric() => {
+                        return error(&format!(
+                            "'\\{c}' is not supported, only . \\d * + ^ $ and one (group)"
+                        ))
+                    }
+                    Some(c) => Token::Atom(Atom::Char(c), 1, false),
+                    None => return error("trailing '\\'"),
+                },
+                '*' | '+' => match tokens.pop() {
+                    Some(Token::Atom(atom, _, false)) => {
+                        Token::Atom(atom, if c == '*' { 0 } else { 1 }, true)
+                    }
+                    _ => return error(&format!("'{c}' must follow a character")),
+                },
+                '(' if groups > 0 => return error("only one group is supported"),
+                '(' => {
+                    groups += 1;
+                    Token::GroupStart
+                }
+                ')' if tokens.contains(&Token::GroupStart)
+                    && !tokens.contains(&Token::GroupEnd) =>
+                {
+                    Token::GroupEnd
+                }
+                ')' => return error("unbalanced ')'"),
+                '$' if chars.peek().is_none() => Token::End,
+                c => Token::Atom(Atom::Char(c), 1, false),
+            };
+            tokens.push(token);
+        }
+        if tokens.contains(&Token::GroupStart) && !tokens.contains(&Token::GroupEnd) {
+            return error("unbalanced '('");
+        }
+        Ok(Pattern { tokens, anchored })
+    }
+
+    // search anywhere in text, unless anchored with ^
+    pub fn is_match(&self, text: &str) -> bool {
+        let text: Vec<char> = text.chars().collect();
+        self.find(&text).is_some()
+    }
+
+    /**
+     * The text of the group in the leftmost match, or the whole match without a group
+     *
+     * Repetitions are greedy, so that "#(\d+)" gets all digits.
+     */
+    pub fn captures(&self, text: &str) -> Option<String> {
+        let text: Vec<char> = text.chars().collect();
+        let (start, end) = self.find(&text)?;
+        Some(text[start..end].iter().collect())
+    }
+
+    // start and end of the group in the leftmost match, or of the whole match
+    fn find(&self, text: &[char]) -> Option<(usize, usize)> {
+        let starts = if self.anchored { 0..=0 } else { 0..=text.len() };
+        for start in starts {
+            let mut group = (0, 0);
+            if let Some(end) = match_here(&self.tokens, text, start, &mut group) {
+                if self.tokens.contains(&Token::GroupStart) {
+                    return Some(group);
+                }
+                return Some((start, end));
+            }
+        }
+        None
+    }
+}
+
+// end of the match of re at position i of text; group gets the group's start and end
+#[cfg(not(feature = "regex"))]
+fn match_here(re: &[Token], text: &[char], i: usize, group: &mut (usize, usize)) -> Option<usize> {
+    match re {
+        [] => Some(i),
+        [Token::End, ..] => Some(i).filter(|i| *i == text.len()),
+        [Token::GroupStart, rest @ ..] => {
+            group.0 = i;
+            match_here(rest, text, i, group)
+        }
+        [Token::GroupEnd, rest @ ..] => {
+            group.1 = i;
+            match_here(rest, text, i, group)
+        }
+        [Token::Atom(atom, min, repeat), rest @ ..] => {
+            let available = text[i..].iter().take_while(|c| atom.matches(**c)).count();
+            let max = if *repeat { available } else { available.min(1) };
+            // try the longest repetition first
+            (*min..=max)
+                .rev()
+                .find_map(|n| match_here(rest, text, i + n, group))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match() {
+        let p = Pattern::new("^customer .*").unwrap();
+        assert!(p.is_match("customer joe: support"));
+        assert!(!p.is_match("call customer joe"));
+        assert!(!p.is_match("code"));
+
+        let p = Pattern::new("code").unwrap();
+        assert!(p.is_match("rtimelog: code"));
+        assert!(p.is_match("code review"));
+        assert!(!p.is_match("coding"));
+
+        let p = Pattern::new("co.e$").unwrap();
+        assert!(p.is_match("rtimelog: code"));
+        assert!(p.is_match("come"));
+        assert!(!p.is_match("code review"));
+
+        let p = Pattern::new("^a*b").unwrap();
+        assert!(p.is_match("b"));
+        assert!(p.is_match("aaab"));
+        assert!(!p.is_match("cb"));
+
+        let p = Pattern::new("^a+b").unwrap();
+        assert!(!p.is_match("b"));
+        assert!(p.is_match("aaab"));
+
+        let p = Pattern::new("v\\d\\.\\d").unwrap();
+        assert!(p.is_match("release v1.2"));
+        assert!(!p.is_match("release v1x2"));
+
+        // $ in the middle is literal
+        #[cfg(not(feature = "regex"))]
+        assert!(Pattern::new("a$b").unwrap().is_match("pay a$b"));
+
+        assert!(Pattern::new("").unwrap().is_match("anything"));
+    }
+
+    #[test]
+    fn test_captures() {
+        let p = Pattern::new("#(\\d+)").unwrap();
+        assert_eq!(p.captures("fix #123: crash"), Some("123".to_string()));
+        assert_eq!(p.captures("review #45 and #67"), Some("45".to_string()));
+        assert_eq!(p.captures("fix # crash"), None);
+        assert!(p.is_match("fix #1"));
+
+        let p = Pattern::new("^(.*): ").unwrap();
+        assert_eq!(
+            p.captures("project: fix: bug"),
+            Some("project: fix".to_string())
+        );
+
+        // without group, the whole match
+        let p = Pattern::new("X+-\\d+").unwrap();
+        assert_eq!(p.captures("fix XX-12 now"), Some("XX-12".to_string()));
+        assert_eq!(
+            Pattern::new("x()y").unwrap().captures("axyb"),
+            Some("".to_string())
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "regex"))]
+    fn test_invalid() {
+        assert_eq!(
+            Pattern::new("[0-9]+").unwrap_err(),
+            PatternError(
+                "Invalid pattern [0-9]+: '[' is not supported, only . \\d * + ^ $ and one (group)"
+                    .to_string()
+            )
+        );
+        assert!(Pattern::new("*code").is_err());
+        assert!(Pattern::new("^*code").is_err());
+        assert!(Pattern::new("a**").is_err());
+        assert!(Pattern::new("a*+").is_err());
+        assert!(Pattern::new("(*)").is_err());
+        assert!(Pattern::new("(a)(b)").is_err());
+        assert!(Pattern::new("(a").is_err());
+        assert!(Pattern::new("a)").is_err());
+        assert!(Pattern::new("a\\").is_err());
+
+        // unsupported escapes are not taken literally
+        assert_eq!(
+            Pattern::new("\\w+").unwrap_err(),
+            PatternError(
+                "Invalid pattern \\w+: '\\w' is not supported, only . \\d * + ^ $ and one (group)"
+                    .to_string()
+            )
+        );
+        assert!(Pattern::new("a\\sb").is_err());
+        assert!(Pattern::new("\\bcode").is_err());
+        assert!(Pattern::new("\\D").is_err());
+        // escaped metacharacters and punctuation are fine
+        assert!(Pattern::new("\\(\\$\\d\\)\\-\\#")
+            .unwrap()
+            .is_match("fee ($5)-#"));
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn test_regex() {
+        let p = Pattern::new("^\\w+ [0-9]{2}\\b").unwrap();
+        assert!(p.is_match("customer 42: call"));
+        assert!(!p.is_match("customer 423: call"));
+        assert_eq!(
+            Pattern::new("(?:fix|review) #(\\d+)")
+                .unwrap()
+                .captures("review #45"),
+            Some("45".to_string())
+        );
+        assert!(Pattern::new("[0-9")
+            .unwrap_err()
+            .to_string()
+            .starts_with("Invalid pattern [0-9: "));
+    }
+}
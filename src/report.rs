@@ -0,0 +1,1798 @@
+// Copyright (C) 2023 Martin Pitt <martin@piware.de>
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate chrono;
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Write as _; // import without risk of name clashing
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+use crate::activity::{
+    category, format_hm, format_hm_aligned, get_blocks, get_blocks_with_leaving, hours_width,
+    is_meeting, Activities, Block,
+};
+use crate::clock;
+use crate::config::{parse_category_map, Config};
+use crate::store::{normalize_task, Entry, Timelog};
+
+/**
+ * Period of a report without an explicit range
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportPeriod {
+    #[default]
+    Today,
+    // since Monday
+    Week,
+    // since the first of the month
+    Month,
+}
+
+impl ReportPeriod {
+    // first day of the period which ends with today
+    pub fn start(&self, today: NaiveDate) -> NaiveDate {
+        match self {
+            ReportPeriod::Today => today,
+            ReportPeriod::Week => {
+                today - Duration::days(today.weekday().num_days_from_monday().into())
+            }
+            ReportPeriod::Month => today.with_day(1).unwrap(),
+        }
+    }
+}
+
+// first and last entry time of a day
+pub fn day_span(day: &[Entry]) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    Some((day.first()?.stop, day.last()?.stop))
+}
+
+/**
+ * Time from the first entry with task from to the next entry with task to
+ *
+ * Like "how long from arrived to the first code". Tasks are compared by name, so
+ * "code (cont.)" also counts as "code". Err if either task is missing.
+ */
+pub fn time_between(day: &[Entry], from: &str, to: &str) -> Result<Duration, String> {
+    let matches = |e: &Entry, task: &str| normalize_task(e.name()) == normalize_task(task);
+    let start = day
+        .iter()
+        .position(|e| matches(e, from))
+        .ok_or(format!("No entry with task '{from}'"))?;
+    let end = day[start + 1..]
+        .iter()
+        .find(|e| matches(e, to))
+        .ok_or(format!("No entry with task '{to}' after '{from}'"))?;
+    Ok(end.stop - day[start].stop)
+}
+
+// tasks which are meant to only record the arrival, like "arrived" or gtimelog's "arrived**"
+pub fn is_arrival(task: &str) -> bool {
+    let task = task.trim().to_lowercase();
+    task.ends_with("**") || ["arrived", "arrive", "start", "started"].contains(&task.as_str())
+}
+
+/**
+ * Header line for the first entry of the first day, which only provides the start time
+ *
+ * Its task is not counted. If it does not look like an arrival, point that out, as it
+ * was probably meant as a real task.
+ */
+pub fn arrival_header(entries: &[Entry]) -> Option<String> {
+    let first = Timelog::split_days(entries).next()?.first()?;
+    let mut header = format!("Arrived {} ({})", first.stop.format("%H:%M"), first.task);
+    if !is_arrival(&first.task) {
+        header.push_str(
+            " - the first entry of a day only records the start, its task is not counted",
+        );
+    }
+    Some(header)
+}
+
+fn average(secs: &[u32]) -> u32 {
+    (secs.iter().map(|&s| s as u64).sum::<u64>() / secs.len() as u64) as u32
+}
+
+fn median(secs: &mut [u32]) -> u32 {
+    secs.sort_unstable();
+    let mid = secs.len() / 2;
+    if secs.len().is_multiple_of(2) {
+        (secs[mid - 1] + secs[mid]) / 2
+    } else {
+        secs[mid]
+    }
+}
+
+// seconds since the start of the day, respecting the virtual midnight
+fn secs_into_day(t: NaiveDateTime, day: &NaiveDate) -> u32 {
+    (t - clock::day_start(day)).num_seconds() as u32
+}
+
+fn time_from_secs(secs: u32) -> NaiveTime {
+    clock::virtual_midnight() + Duration::seconds(secs.into())
+}
+
+/**
+ * Rhythm: Average and median start/end of the work days
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct Rhythm {
+    pub days: usize,
+    pub avg_start: NaiveTime,
+    pub median_start: NaiveTime,
+    pub avg_end: NaiveTime,
+    pub median_end: NaiveTime,
+    pub avg_span: Duration,
+}
+
+impl Rhythm {
+    // None if there are no entries
+    pub fn new_from_entries(entries: &[Entry], config: &Config) -> Option<Rhythm> {
+        Rhythm::new_from_entries_with_min(entries, Duration::zero(), config)
+    }
+
+    // ignore days with less work than min_day_work, like quickly checking email on a day off
+    pub fn new_from_entries_with_min(
+        entries: &[Entry],
+        min_day_work: Duration,
+        config: &Config,
+    ) -> Option<Rhythm> {
+        let mut starts = Vec::new();
+        let mut ends = Vec::new();
+
+        for day in Timelog::split_days(entries) {
+            if Activities::new_from_entries_with_config(day, config).total_work < min_day_work {
+                continue;
+            }
+            let (start, end) = day_span(day)?;
+            let date = clock::logical_date(start);
+            starts.push(secs_into_day(start, &date));
+            ends.push(secs_into_day(end, &date));
+        }
+
+        if starts.is_empty() {
+            return None;
+        }
+
+        let avg_start = average(&starts);
+        let avg_end = average(&ends);
+        Some(Rhythm {
+            days: starts.len(),
+            avg_start: time_from_secs(avg_start),
+            median_start: time_from_secs(median(&mut starts)),
+            avg_end: time_from_secs(avg_end),
+            median_end: time_from_secs(median(&mut ends)),
+            avg_span: Duration::seconds(avg_end as i64 - avg_start as i64),
+        })
+    }
+}
+
+impl fmt::Display for Rhythm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Days: {}", self.days)?;
+        writeln!(
+            f,
+            "Start: average {}, median {}",
+            self.avg_start.format("%H:%M"),
+            self.median_start.format("%H:%M")
+        )?;
+        writeln!(
+            f,
+            "End: average {}, median {}",
+            self.avg_end.format("%H:%M"),
+            self.median_end.format("%H:%M")
+        )?;
+        writeln!(f, "Workday span: average {}", format_hm(&self.avg_span))
+    }
+}
+
+/**
+ * Stats: Work done per day, and average
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct Stats {
+    pub days: Vec<(NaiveDate, Duration)>,
+    // shown, but not counted in the average; useful for the incomplete current day
+    pub excluded: Option<NaiveDate>,
+    // days with less work are shown, but not counted in the average
+    pub min_day_work: Duration,
+    // None if there are no counted days
+    pub average: Option<Duration>,
+}
+
+impl Stats {
+    pub fn new_from_entries(
+        entries: &[Entry],
+        excluded: Option<NaiveDate>,
+        config: &Config,
+    ) -> Stats {
+        Stats::new_from_entries_with_min(entries, excluded, Duration::zero(), config)
+    }
+
+    pub fn new_from_entries_with_min(
+        entries: &[Entry],
+        excluded: Option<NaiveDate>,
+        min_day_work: Duration,
+        config: &Config,
+    ) -> Stats {
+        let days: Vec<(NaiveDate, Duration)> = Timelog::split_days(entries)
+            .map(|day| {
+                (
+                    clock::logical_date(day[0].stop),
+                    Activities::new_from_entries_with_config(day, config).total_work,
+                )
+            })
+            .collect();
+
+        let mut stats = Stats {
+            days,
+            excluded,
+            min_day_work,
+            average: None,
+        };
+        let counted: Vec<Duration> = stats
+            .days
+            .iter()
+            .filter(|(date, work)| stats.is_counted(date, work))
+            .map(|(_, work)| *work)
+            .collect();
+        if !counted.is_empty() {
+            stats.average = Some(counted.iter().sum::<Duration>() / counted.len() as i32);
+        }
+        stats
+    }
+
+    fn is_counted(&self, date: &NaiveDate, work: &Duration) -> bool {
+        Some(*date) != self.excluded && *work >= self.min_day_work
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (date, work) in &self.days {
+            write!(f, "{}: {}", date.format("%a %F"), format_hm(work))?;
+            if !self.is_counted(date, work) {
+                write!(f, " (not counted)")?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "-------")?;
+        match self.average {
+            Some(avg) => writeln!(f, "Average work per day: {}", format_hm(&avg)),
+            None => writeln!(f, "No days to average"),
+        }
+    }
+}
+
+/**
+ * Presence: Time between first and last entry of a day, compared to the tracked time
+ *
+ * A difference means that some time was not accounted to any activity, e.g. when
+ * filtering tasks.
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct Presence {
+    pub present: Duration,
+    pub tracked: Duration,
+}
+
+impl Presence {
+    pub fn new(day: &[Entry], activities: &Activities) -> Option<Presence> {
+        let (start, end) = day_span(day)?;
+        Some(Presence {
+            present: end.signed_duration_since(start),
+            tracked: activities.total_tracked(),
+        })
+    }
+
+    pub fn untracked(&self) -> Duration {
+        self.present - self.tracked
+    }
+
+    // warning about untracked time above tolerance, to not nag about tiny gaps
+    pub fn warning(&self, tolerance: Duration) -> Option<String> {
+        if self.untracked() > tolerance {
+            Some(format!(
+                "WARNING: {} of your time is not tracked",
+                format_hm(&self.untracked())
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Presence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Present {}, tracked {}, untracked {}",
+            format_hm(&self.present),
+            format_hm(&self.tracked),
+            format_hm(&self.untracked())
+        )
+    }
+}
+
+/**
+ * WeekGrid: Work hours per category and weekday, with row and column totals
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct WeekGrid {
+    // category and work from Monday to Sunday, in order of first occurrence
+    pub rows: Vec<(String, [Duration; 7])>,
+}
+
+impl WeekGrid {
+    // entries of several weeks add up on the same weekday
+    pub fn new_from_entries(
+        entries: &[Entry],
+        merge: &HashMap<String, String>,
+        config: &Config,
+    ) -> WeekGrid {
+        let mut rows: Vec<(String, [Duration; 7])> = Vec::new();
+
+        for day in Timelog::split_days(entries) {
+            let weekday = day[0].stop.weekday().num_days_from_monday() as usize;
+            let activities = Activities::new_from_entries_with_config(day, config);
+            for (cat, duration) in activities.by_category(merge) {
+                let i = match rows.iter().position(|(c, _)| *c == cat) {
+                    Some(i) => i,
+                    None => {
+                        rows.push((cat, [Duration::zero(); 7]));
+                        rows.len() - 1
+                    }
+                };
+                rows[i].1[weekday] += duration;
+            }
+        }
+
+        WeekGrid { rows }
+    }
+
+    pub fn column_totals(&self) -> [Duration; 7] {
+        let mut totals = [Duration::zero(); 7];
+        for (_, days) in &self.rows {
+            for (total, d) in totals.iter_mut().zip(days) {
+                *total += *d;
+            }
+        }
+        totals
+    }
+}
+
+fn format_hours(d: &Duration) -> String {
+    format!("{:.1}", d.num_minutes() as f64 / 60.0)
+}
+
+impl fmt::Display for WeekGrid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label_width = self
+            .rows
+            .iter()
+            .map(|(c, _)| c.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max("Total".len());
+
+        let write_row = |f: &mut fmt::Formatter<'_>, label: &str, days: &[Duration; 7]| {
+            write!(f, "{label:<label_width$}")?;
+            for d in days {
+                write!(f, " {:>5}", format_hours(d))?;
+            }
+            writeln!(f, " {:>6}", format_hours(&days.iter().sum()))
+        };
+
+        write!(f, "{:label_width$}", "")?;
+        for day in ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"] {
+            write!(f, " {day:>5}")?;
+        }
+        writeln!(f, " {:>6}", "Total")?;
+        for (cat, days) in &self.rows {
+            write_row(f, cat, days)?;
+        }
+        writeln!(f, "{}", "-".repeat(label_width + 7 * 6 + 7))?;
+        write_row(f, "Total", &self.column_totals())
+    }
+}
+
+/**
+ * MonthCalendar: Work hours of each day of a month, for a calendar grid
+ *
+ * Days without entries are blank. Days with only slack, like "** vacation", are off.
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct MonthCalendar {
+    pub first: NaiveDate,
+    // work of each day of the month; None for days without work or slack
+    pub days: Vec<Option<Duration>>,
+    // days with slack, but no work
+    pub off: Vec<NaiveDate>,
+}
+
+impl MonthCalendar {
+    // entries of the month which starts on first
+    pub fn new_from_entries(entries: &[Entry], first: NaiveDate, config: &Config) -> MonthCalendar {
+        let next = first
+            .checked_add_months(chrono::Months::new(1))
+            .unwrap_or(NaiveDate::MAX);
+        let mut days = vec![None; (next - first).num_days() as usize];
+        let mut off = Vec::new();
+
+        for day in Timelog::split_days(entries) {
+            let date = clock::logical_date(day[0].stop);
+            if date < first || date >= next {
+                continue;
+            }
+            let activities = Activities::new_from_entries_with_config(day, config);
+            if !activities.total_work().is_zero() {
+                days[date.day0() as usize] = Some(activities.total_work());
+            } else if !activities.total_slack().is_zero() {
+                off.push(date);
+            }
+        }
+
+        MonthCalendar { first, days, off }
+    }
+}
+
+impl fmt::Display for MonthCalendar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.first.format("%B %Y"))?;
+        let header: Vec<String> = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"]
+            .iter()
+            .map(|d| format!("{d:>7}"))
+            .collect();
+        writeln!(f, "{}", header.join(" "))?;
+
+        // blank cells before the first day
+        let mut cells = vec![" ".repeat(7); self.first.weekday().num_days_from_monday() as usize];
+        for (i, work) in self.days.iter().enumerate() {
+            let date = self.first + Duration::days(i as i64);
+            let value = match work {
+                Some(d) => format_hours(d),
+                None if self.off.contains(&date) => "off".to_string(),
+                None => String::new(),
+            };
+            cells.push(format!("{:>2} {value:>4}", i + 1));
+        }
+        for week in cells.chunks(7) {
+            writeln!(f, "{}", week.join(" ").trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/**
+ * MeetingLoad: Work in meetings and focused work per day
+ *
+ * Meetings are recognized with is_meeting() and the configured meeting_marker; all
+ * other work is focused. Slack does not count.
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct MeetingLoad {
+    // day, meetings, focused work
+    pub days: Vec<(NaiveDate, Duration, Duration)>,
+}
+
+// share of meetings in all work, in percent
+fn load_percent(meetings: &Duration, focused: &Duration) -> i64 {
+    let work = (*meetings + *focused).num_minutes();
+    if work == 0 {
+        0
+    } else {
+        (meetings.num_minutes() * 100 + work / 2) / work
+    }
+}
+
+impl MeetingLoad {
+    pub fn new_from_entries(entries: &[Entry], config: &Config) -> MeetingLoad {
+        let days = Timelog::split_days(entries)
+            .map(|day| {
+                let mut meetings = Duration::zero();
+                let mut focused = Duration::zero();
+                let activities = Activities::new_from_entries_with_config(day, config);
+                for (task, d) in activities.by_task() {
+                    if activities.is_slack(&task) {
+                        continue;
+                    }
+                    if is_meeting(&task, &config.meeting_marker) {
+                        meetings += d;
+                    } else {
+                        focused += d;
+                    }
+                }
+                (clock::logical_date(day[0].stop), meetings, focused)
+            })
+            .collect();
+        MeetingLoad { days }
+    }
+
+    pub fn totals(&self) -> (Duration, Duration) {
+        self.days.iter().fold(
+            (Duration::zero(), Duration::zero()),
+            |(m, f), (_, dm, df)| (m + *dm, f + *df),
+        )
+    }
+}
+
+impl fmt::Display for MeetingLoad {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let write_line = |f: &mut fmt::Formatter<'_>, label: &str, m: &Duration, w: &Duration| {
+            writeln!(
+                f,
+                "{label}: meetings {}, focused {}, meeting load {}%",
+                format_hm(m),
+                format_hm(w),
+                load_percent(m, w)
+            )
+        };
+        for (day, meetings, focused) in &self.days {
+            write_line(f, &day.format("%a %F").to_string(), meetings, focused)?;
+        }
+        // a single day already is its own total
+        if self.days.len() > 1 {
+            let (meetings, focused) = self.totals();
+            writeln!(f, "-------")?;
+            write_line(f, "Total", &meetings, &focused)?;
+        }
+        Ok(())
+    }
+}
+
+/**
+ * TaskTotals: Cumulative duration of every distinct task or category, biggest first
+ *
+ * Tasks include slack, categories only count work.
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct TaskTotals {
+    pub rows: Vec<(String, Duration)>,
+}
+
+impl TaskTotals {
+    pub fn new_from_entries(entries: &[Entry], by_category: bool, config: &Config) -> TaskTotals {
+        let activities = Activities::new_from_entries_with_config(entries, config);
+        let mut rows = if by_category {
+            activities.by_category(&config.merge_categories)
+        } else {
+            activities.by_task()
+        };
+        // stable, so that equal durations stay in order of first occurrence
+        rows.sort_by_key(|(_, d)| Reverse(*d));
+        TaskTotals { rows }
+    }
+}
+
+impl fmt::Display for TaskTotals {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the whole file can add up to thousands of hours
+        let hours_width = self
+            .rows
+            .first()
+            .map_or(0, |(_, d)| d.num_hours().to_string().len())
+            .max(2);
+        for (name, d) in &self.rows {
+            writeln!(f, "{}: {name}", format_hm_aligned(d, hours_width))?;
+        }
+        Ok(())
+    }
+}
+
+// parse an amount of money like "80" or "80.50" into cents
+pub fn parse_amount(s: &str) -> Option<i64> {
+    let (whole, fraction) = s.split_once('.').unwrap_or((s, "00"));
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !digits(whole) || !digits(fraction) || fraction.len() > 2 {
+        return None;
+    }
+    let cents = if fraction.len() == 1 {
+        fraction.parse::<i64>().ok()? * 10
+    } else {
+        fraction.parse().ok()?
+    };
+    whole
+        .parse::<i64>()
+        .ok()?
+        .checked_mul(100)?
+        .checked_add(cents)
+}
+
+// cents as amount of money with two decimals, like "1050.00"
+pub fn format_amount(cents: i64) -> String {
+    format!("{}.{:02}", cents / 100, cents % 100)
+}
+
+// parse "project1=100, project2=80.50" into a map of hourly rates in cents
+pub fn parse_category_rates(spec: &str) -> Result<HashMap<String, i64>, String> {
+    parse_category_map(spec)?
+        .into_iter()
+        .map(|(cat, rate)| match parse_amount(&rate) {
+            Some(cents) => Ok((cat, cents)),
+            None => Err(format!(
+                "invalid rate '{rate}' for {cat}, expected an amount like 80 or 80.50"
+            )),
+        })
+        .collect()
+}
+
+/**
+ * Invoice: Billable amount of the work per category, from hourly rates
+ *
+ * Money is counted in cents, to avoid the rounding errors of floats. Each category's
+ * amount is rounded to the nearest cent, and the total is the sum of these.
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct Invoice {
+    // category, work, hourly rate in cents, amount in cents
+    pub rows: Vec<(String, Duration, i64, i64)>,
+    pub currency: String,
+}
+
+impl Invoice {
+    /**
+     * Bill the work of activities with the category rate, or the default rate
+     *
+     * Slack is not billed. It is an error if a category has neither.
+     */
+    pub fn new(
+        activities: &Activities,
+        rate: Option<i64>,
+        category_rates: &HashMap<String, i64>,
+        currency: &str,
+    ) -> Result<Invoice, String> {
+        let rows = activities
+            .by_category(&HashMap::new())
+            .into_iter()
+            .map(|(cat, d)| {
+                let rate = category_rates
+                    .get(&cat)
+                    .copied()
+                    .or(rate)
+                    .ok_or(format!("No rate for category {cat}"))?;
+                // round to the nearest cent
+                let amount = (rate * d.num_seconds() + 1800).div_euclid(3600);
+                Ok((cat, d, rate, amount))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Invoice {
+            rows,
+            currency: currency.to_string(),
+        })
+    }
+
+    pub fn total_work(&self) -> Duration {
+        self.rows.iter().map(|(_, d, _, _)| *d).sum()
+    }
+
+    pub fn total_amount(&self) -> i64 {
+        self.rows.iter().map(|(_, _, _, amount)| amount).sum()
+    }
+}
+
+impl fmt::Display for Invoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let currency = &self.currency;
+        let width = hours_width(self.rows.iter().map(|(_, d, _, _)| d));
+        for (cat, d, rate, amount) in &self.rows {
+            writeln!(
+                f,
+                "{}: {cat}: {} {currency}/h = {} {currency}",
+                format_hm_aligned(d, width),
+                format_amount(*rate),
+                format_amount(*amount)
+            )?;
+        }
+        writeln!(f, "-------")?;
+        writeln!(
+            f,
+            "Total: {} = {} {currency}",
+            format_hm(&self.total_work()),
+            format_amount(self.total_amount())
+        )
+    }
+}
+
+/**
+ * ContextSwitches: How often the category of consecutive work blocks changed
+ *
+ * Slack does not count as a switch, so "project1" → "**lunch" → "project1" is no switch.
+ * Tasks without category are counted as "uncategorized".
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct ContextSwitches {
+    pub count: usize,
+    // categories and how often they were switched into, most frequent first
+    pub into: Vec<(String, usize)>,
+}
+
+impl ContextSwitches {
+    pub fn new_from_entries(entries: &[Entry], config: &Config) -> ContextSwitches {
+        // keep the order of first occurrence for equal counts
+        let mut into: Vec<(String, usize)> = Vec::new();
+
+        // don't count the change from the last task of a day to the first one of the next
+        for day in Timelog::split_days(entries) {
+            let mut prev: Option<String> = None;
+            for b in get_blocks_with_leaving(day, config.leaving_marker.as_deref()) {
+                if config.is_slack(&b.task) {
+                    continue;
+                }
+                let cat = category(&b.task).unwrap_or("uncategorized").to_string();
+                if prev.as_ref().is_some_and(|p| *p != cat) {
+                    match into.iter_mut().find(|(c, _)| *c == cat) {
+                        Some((_, n)) => *n += 1,
+                        None => into.push((cat.clone(), 1)),
+                    }
+                }
+                prev = Some(cat);
+            }
+        }
+
+        into.sort_by_key(|(_, n)| Reverse(*n));
+        ContextSwitches {
+            count: into.iter().map(|(_, n)| n).sum(),
+            into,
+        }
+    }
+}
+
+impl fmt::Display for ContextSwitches {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Context switches: {}", self.count)?;
+        for (cat, n) in &self.into {
+            writeln!(f, "{n:>4}: {cat}")?;
+        }
+        Ok(())
+    }
+}
+
+// SVG timeline geometry, in pixels; one pixel per minute
+const SVG_HOUR_WIDTH: i64 = 60;
+const SVG_MARGIN: i64 = 10;
+// colors for the categories, in order of first occurrence; slack is gray
+const SVG_COLORS: [&str; 8] = [
+    "#4e79a7", "#f28e2b", "#59a14f", "#e15759", "#76b7b2", "#edc948", "#b07aa1", "#ff9da7",
+];
+const SVG_SLACK_COLOR: &str = "#bab0ac";
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/**
+ * Horizontal SVG timeline of blocks, colored by category, with hour grid lines
+ *
+ * This is meant for the blocks of a single day. The timeline covers the full hours
+ * from the first block's start to the last block's end.
+ */
+pub fn render_svg(blocks: &[Block], config: &Config) -> String {
+    let origin = blocks
+        .first()
+        .map(|b| b.start.date().and_hms_opt(b.start.hour(), 0, 0).unwrap());
+    let first_hour = origin.map_or(0, |o| o.hour() as i64);
+    // round up to the next full hour; with a virtual midnight, the day can end after midnight
+    let hours = match (origin, blocks.last()) {
+        (Some(o), Some(b)) => ((b.stop - o).num_seconds() + 3599) / 3600,
+        _ => 0,
+    };
+    let x = |t: NaiveDateTime| {
+        SVG_MARGIN + t.signed_duration_since(origin.unwrap()).num_minutes() * SVG_HOUR_WIDTH / 60
+    };
+    let width = hours * SVG_HOUR_WIDTH + 2 * SVG_MARGIN;
+    let height = 160;
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" font-family="sans-serif" font-size="10">"#
+    )
+    .expect("failed to format SVG");
+
+    for i in 0..=hours {
+        let hx = SVG_MARGIN + i * SVG_HOUR_WIDTH;
+        let hour = (first_hour + i) % 24;
+        writeln!(
+            svg,
+            r##"<line x1="{hx}" y1="15" x2="{hx}" y2="55" stroke="#ccc"/><text x="{hx}" y="10" text-anchor="middle">{hour:02}:00</text>"##
+        )
+        .expect("failed to format SVG");
+    }
+
+    let mut categories: Vec<&str> = Vec::new();
+    for b in blocks {
+        let color = if config.is_slack(&b.task) {
+            SVG_SLACK_COLOR
+        } else {
+            let cat = category(&b.task).unwrap_or(&b.task);
+            let i = match categories.iter().position(|c| *c == cat) {
+                Some(i) => i,
+                None => {
+                    categories.push(cat);
+                    categories.len() - 1
+                }
+            };
+            SVG_COLORS[i % SVG_COLORS.len()]
+        };
+        let (bx, bw) = (x(b.start), x(b.stop) - x(b.start));
+        let task = escape_xml(&b.task);
+        let label_x = bx + bw / 2;
+        writeln!(
+            svg,
+            r#"<rect x="{bx}" y="20" width="{bw}" height="30" fill="{color}"><title>{}-{} {task}</title></rect>"#,
+            b.start.format("%H:%M"),
+            b.stop.format("%H:%M"),
+        )
+        .expect("failed to format SVG");
+        writeln!(
+            svg,
+            r#"<text x="{label_x}" y="60" transform="rotate(45 {label_x} 60)">{task}</text>"#
+        )
+        .expect("failed to format SVG");
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// number of single character insertions, deletions, or substitutions to turn a into b
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            cur.push((prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1));
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+/**
+ * Singleton: Task which occurs only once, which is often a typo
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct Singleton {
+    pub task: String,
+    pub stop: NaiveDateTime,
+    // most similar task which occurs more than once
+    pub suggestion: Option<String>,
+}
+
+impl Singleton {
+    pub fn find(entries: &[Entry]) -> Vec<Singleton> {
+        let blocks = get_blocks(entries);
+        // keep the order of first occurrence
+        let mut counts: Vec<(&str, usize)> = Vec::new();
+        for b in &blocks {
+            match counts.iter_mut().find(|(t, _)| *t == b.task) {
+                Some((_, n)) => *n += 1,
+                None => counts.push((&b.task, 1)),
+            }
+        }
+
+        blocks
+            .iter()
+            .filter(|b| counts.iter().any(|(t, n)| *t == b.task && *n == 1))
+            .map(|b| Singleton {
+                task: b.task.clone(),
+                stop: b.stop,
+                suggestion: counts
+                    .iter()
+                    .filter(|(_, n)| *n > 1)
+                    .map(|(t, _)| (edit_distance(&b.task, t), *t))
+                    // only suggest similar tasks, not just any short one
+                    .filter(|(d, t)| *d <= 2 && *d < t.chars().count())
+                    .min_by_key(|(d, _)| *d)
+                    .map(|(_, t)| t.to_string()),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Singleton {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.stop.format("%F %H:%M"), self.task)?;
+        if let Some(s) = &self.suggestion {
+            write!(f, " (did you mean '{s}'?)")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+
+    const THREE_DAYS: &str = "
+2022-06-08 07:00: arrived
+2022-06-08 12:00: code
+2022-06-08 16:00: code
+
+2022-06-09 07:30: arrived
+2022-06-09 17:00: code
+
+2022-06-10 10:30: arrived
+2022-06-10 16:30: code
+";
+
+    #[test]
+    fn test_report_period() {
+        // Thursday
+        let today = NaiveDate::from_ymd_opt(2022, 6, 9).unwrap();
+        assert_eq!(ReportPeriod::default().start(today), today);
+        assert_eq!(
+            ReportPeriod::Week.start(today),
+            NaiveDate::from_ymd_opt(2022, 6, 6).unwrap()
+        );
+        assert_eq!(
+            ReportPeriod::Month.start(today),
+            NaiveDate::from_ymd_opt(2022, 6, 1).unwrap()
+        );
+
+        // on the first day of the period
+        let monday = NaiveDate::from_ymd_opt(2022, 8, 1).unwrap();
+        assert_eq!(ReportPeriod::Week.start(monday), monday);
+        assert_eq!(ReportPeriod::Month.start(monday), monday);
+    }
+
+    #[test]
+    fn test_time_between() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 07:30: email
+2022-06-10 09:15: code
+2022-06-10 10:00: code
+2022-06-10 11:00: email
+",
+        );
+        let day = tl.get_day(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap());
+        assert_eq!(
+            time_between(day, "arrived", "code"),
+            Ok(Duration::minutes(135))
+        );
+        // first occurrence of from, next occurrence of to
+        assert_eq!(
+            time_between(day, "email", "email"),
+            Ok(Duration::minutes(210))
+        );
+        assert_eq!(
+            time_between(day, "lunch", "code"),
+            Err("No entry with task 'lunch'".to_string())
+        );
+        assert_eq!(
+            time_between(day, "code", "arrived"),
+            Err("No entry with task 'arrived' after 'code'".to_string())
+        );
+        assert_eq!(
+            time_between(&[], "arrived", "code"),
+            Err("No entry with task 'arrived'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_day_span() {
+        assert_eq!(day_span(&[]), None);
+
+        let tl = Timelog::new_from_string(THREE_DAYS);
+        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 8).unwrap(), 1);
+        let (start, end) = day_span(entries).unwrap();
+        assert_eq!(start.format("%H:%M").to_string(), "07:00");
+        assert_eq!(end.format("%H:%M").to_string(), "16:00");
+    }
+
+    #[test]
+    fn test_presence() {
+        assert_eq!(Presence::new(&[], &Activities::new_from_entries(&[])), None);
+
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 12:00: code
+2022-06-10 12:15: ** lunch
+2022-06-10 16:10: meeting
+",
+        );
+        let day = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1);
+
+        let p = Presence::new(day, &Activities::new_from_entries(day)).unwrap();
+        assert_eq!(p.present, Duration::minutes(9 * 60 + 10));
+        assert_eq!(p.tracked, p.present);
+        assert_eq!(p.untracked(), Duration::zero());
+        assert_eq!(
+            format!("{p}"),
+            "Present 9 h 10 min, tracked 9 h 10 min, untracked 0 h 0 min"
+        );
+
+        // lunch becomes a gap
+        let activities = Activities::new_from_entries_filtered(day, |t| !t.contains("lunch"));
+        let p = Presence::new(day, &activities).unwrap();
+        assert_eq!(p.untracked(), Duration::minutes(15));
+        assert_eq!(
+            format!("{p}"),
+            "Present 9 h 10 min, tracked 8 h 55 min, untracked 0 h 15 min"
+        );
+    }
+
+    #[test]
+    fn test_presence_warning() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 12:00: code
+2022-06-10 12:05: gap1
+2022-06-10 14:00: code
+2022-06-10 14:20: gap2
+2022-06-10 17:00: code
+",
+        );
+        let day = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1);
+        let tolerance = Duration::minutes(10);
+        let presence = |gap| {
+            Presence::new(
+                day,
+                &Activities::new_from_entries_filtered(day, |t| t != gap),
+            )
+            .unwrap()
+        };
+
+        // a 5 minute gap is within the tolerance
+        let p = presence("gap1");
+        assert_eq!(p.untracked(), Duration::minutes(5));
+        assert_eq!(p.warning(tolerance), None);
+
+        // a 20 minute gap is flagged
+        let p = presence("gap2");
+        assert_eq!(p.untracked(), Duration::minutes(20));
+        assert_eq!(
+            p.warning(tolerance),
+            Some("WARNING: 0 h 20 min of your time is not tracked".to_string())
+        );
+
+        // exactly at the tolerance is fine
+        let p = Presence {
+            present: Duration::hours(8),
+            tracked: Duration::hours(8) - tolerance,
+        };
+        assert_eq!(p.warning(tolerance), None);
+        assert!(p.warning(Duration::zero()).is_some());
+        assert_eq!(presence("none").warning(Duration::zero()), None);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("code", "code"), 0);
+        assert_eq!(edit_distance("codee", "code"), 1);
+        assert_eq!(edit_distance("cdoe", "code"), 2);
+        assert_eq!(edit_distance("email", ""), 5);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_singletons() {
+        assert_eq!(Singleton::find(&[]), vec![]);
+
+        let tl = Timelog::new_from_string(
+            "
+2022-06-09 07:00: arrived
+2022-06-09 08:00: code
+2022-06-09 09:00: email
+2022-06-09 10:00: codee
+2022-06-09 11:00: email
+
+2022-06-10 07:00: arrived
+2022-06-10 08:00: code
+2022-06-10 09:00: dentist
+",
+        );
+        let singletons =
+            Singleton::find(tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 2));
+        assert_eq!(
+            singletons.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+            vec![
+                "2022-06-09 10:00: codee (did you mean 'code'?)",
+                "2022-06-10 09:00: dentist",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_svg() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 07:30: arrived
+2022-06-10 09:30: p1: code
+2022-06-10 09:45: ** tea
+2022-06-10 11:00: p2: <review> & \"fix\"
+2022-06-10 12:10: p1: test
+",
+        );
+        let blocks = get_blocks(tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1));
+        let svg = render_svg(&blocks, &Config::default());
+
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>\n"));
+        // one rect per block
+        assert_eq!(svg.matches("<rect ").count(), 4);
+        // full hours from 07:00 to 13:00, 60 pixels each, plus margins
+        assert!(svg.contains(r#"width="380" height="160""#));
+        assert_eq!(svg.matches("<line ").count(), 7);
+        assert!(svg.contains(">07:00</text>"));
+        assert!(svg.contains(">13:00</text>"));
+        // 07:30 to 09:30 is 120 pixels, starting 30 pixels after the margin
+        assert!(svg.contains(r##"<rect x="40" y="20" width="120" height="30" fill="#4e79a7"><title>07:30-09:30 p1: code</title>"##));
+        assert!(svg.contains(r##"<rect x="160" y="20" width="15" height="30" fill="#bab0ac">"##));
+        assert!(svg.contains(r##"<rect x="175" y="20" width="75" height="30" fill="#f28e2b">"##));
+        // same category, same color
+        assert!(svg.contains(r##"<rect x="250" y="20" width="70" height="30" fill="#4e79a7">"##));
+        assert!(svg.contains(">p2: &lt;review&gt; &amp; &quot;fix&quot;</text>"));
+
+        let empty = render_svg(&[], &Config::default());
+        assert!(empty.contains(r#"width="20""#));
+        assert_eq!(empty.matches("<rect ").count(), 0);
+        // a day which ends after midnight, with a virtual midnight
+        clock::set_virtual_midnight(NaiveTime::from_hms_opt(3, 0, 0));
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 22:00: arrived
+2022-06-10 23:30: p1: code
+2022-06-11 01:15: p1: test
+",
+        );
+        let blocks = get_blocks(tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1));
+        let svg = render_svg(&blocks, &Config::default());
+        assert_eq!(svg.matches("<rect ").count(), 2);
+        // 22:00 to 02:00
+        assert!(svg.contains(r#"width="260" height="160""#));
+        assert!(svg.contains(">23:00</text>"));
+        assert!(svg.contains(">00:00</text>"));
+        assert!(svg.contains(">02:00</text>"));
+        assert!(svg.contains(r##"<rect x="100" y="20" width="105" height="30""##));
+        assert!(!svg.contains("width=\"-"));
+        clock::set_virtual_midnight(None);
+    }
+
+    #[test]
+    fn test_meeting_load() {
+        let config = Config::default();
+        assert_eq!(MeetingLoad::new_from_entries(&[], &config).to_string(), "");
+
+        let tl = Timelog::new_from_string(
+            "
+2022-06-09 07:00: arrived
+2022-06-09 08:00: p1: code
+2022-06-09 08:15: meeting: standup
+2022-06-09 09:00: **tea
+2022-06-09 10:00: planning -- meeting
+2022-06-09 12:00: p1: prepare meeting
+2022-06-09 12:30: meeting
+
+2022-06-10 07:00: arrived
+2022-06-10 11:00: p2: review
+",
+        );
+        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 2);
+        let load = MeetingLoad::new_from_entries(entries, &config);
+        assert_eq!(
+            load.days,
+            vec![
+                (
+                    NaiveDate::from_ymd_opt(2022, 6, 9).unwrap(),
+                    Duration::minutes(105),
+                    Duration::minutes(180)
+                ),
+                (
+                    NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(),
+                    Duration::zero(),
+                    Duration::minutes(240)
+                ),
+            ]
+        );
+        assert_eq!(
+            load.to_string(),
+            "Thu 2022-06-09: meetings 1 h 45 min, focused 3 h 0 min, meeting load 37%
+Fri 2022-06-10: meetings 0 h 0 min, focused 4 h 0 min, meeting load 0%
+-------
+Total: meetings 1 h 45 min, focused 7 h 0 min, meeting load 20%
+"
+        );
+
+        // different marker
+        let config = Config {
+            meeting_marker: "p1".to_string(),
+            ..Config::default()
+        };
+        let load = MeetingLoad::new_from_entries(entries, &config);
+        assert_eq!(
+            load.totals(),
+            (Duration::minutes(180), Duration::minutes(345))
+        );
+    }
+
+    const BILLED: &str = "
+2022-06-10 07:00: arrived
+2022-06-10 09:00: p1: code
+2022-06-10 09:20: **tea
+2022-06-10 10:40: p2: review
+2022-06-10 11:00: p1: test
+";
+
+    #[test]
+    fn test_invoice_rate() {
+        let tl = Timelog::new_from_string(BILLED);
+        let a = Activities::new_from_entries(
+            tl.get_day(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap()),
+        );
+        let invoice = Invoice::new(&a, Some(8000), &HashMap::new(), "EUR").unwrap();
+        assert_eq!(invoice.total_work(), Duration::minutes(220));
+        // the sum of the rounded categories, not 3 h 40 min * 80 = 293.33
+        assert_eq!(invoice.total_amount(), 29334);
+        assert_eq!(
+            invoice.to_string(),
+            " 2 h 20 min: p1: 80.00 EUR/h = 186.67 EUR
+ 1 h 20 min: p2: 80.00 EUR/h = 106.67 EUR
+-------
+Total: 3 h 40 min = 293.34 EUR
+"
+        );
+
+        // no rate at all
+        assert_eq!(
+            Invoice::new(&a, None, &HashMap::new(), "EUR"),
+            Err("No rate for category p1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_invoice_category_rates() {
+        let tl = Timelog::new_from_string(BILLED);
+        let a = Activities::new_from_entries(
+            tl.get_day(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap()),
+        );
+        let rates = parse_category_rates("p2=100.50").unwrap();
+        let invoice = Invoice::new(&a, Some(8000), &rates, "USD").unwrap();
+        assert_eq!(
+            invoice.rows,
+            vec![
+                ("p1".to_string(), Duration::minutes(140), 8000, 18667),
+                ("p2".to_string(), Duration::minutes(80), 10050, 13400),
+            ]
+        );
+        assert_eq!(invoice.total_amount(), 32067);
+
+        // without a default rate, every category needs one
+        let rates = parse_category_rates("p1=90, p2=100").unwrap();
+        let invoice = Invoice::new(&a, None, &rates, "USD").unwrap();
+        assert_eq!(invoice.total_amount(), 21000 + 13333);
+        assert!(parse_category_rates("p1=x").is_err());
+    }
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(parse_amount("80"), Some(8000));
+        assert_eq!(parse_amount("80.5"), Some(8050));
+        assert_eq!(parse_amount("80.05"), Some(8005));
+        assert_eq!(parse_amount("0.99"), Some(99));
+        for invalid in [
+            "",
+            "-80",
+            "80.",
+            ".5",
+            "80.505",
+            "8O",
+            "1e3",
+            "99999999999999999999",
+        ] {
+            assert_eq!(parse_amount(invalid), None, "{invalid}");
+        }
+        assert_eq!(format_amount(8005), "80.05");
+        assert_eq!(format_amount(0), "0.00");
+    }
+
+    #[test]
+    fn test_task_totals() {
+        assert_eq!(
+            TaskTotals::new_from_entries(&[], false, &Config::default()).rows,
+            vec![]
+        );
+
+        let tl = Timelog::new_from_string(
+            "
+2022-06-09 07:00: arrived
+2022-06-09 08:00: p1: code
+2022-06-09 09:00: **tea
+2022-06-09 12:00: p2: review
+2022-06-09 12:30: meeting
+
+2022-06-10 07:00: arrived
+2022-06-10 09:00: p1: code
+2022-06-10 09:30: p1: test
+",
+        );
+        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 2);
+
+        let tasks = TaskTotals::new_from_entries(entries, false, &Config::default());
+        assert_eq!(
+            tasks.to_string(),
+            " 3 h  0 min: p1: code
+ 3 h  0 min: p2: review
+ 1 h  0 min: **tea
+ 0 h 30 min: meeting
+ 0 h 30 min: p1: test
+"
+        );
+
+        let categories = TaskTotals::new_from_entries(entries, true, &Config::default());
+        assert_eq!(
+            categories.rows,
+            vec![
+                ("p1".to_string(), Duration::minutes(210)),
+                ("p2".to_string(), Duration::minutes(180)),
+                ("uncategorized".to_string(), Duration::minutes(30)),
+            ]
+        );
+
+        // wide hours
+        let long = TaskTotals {
+            rows: vec![
+                ("a".to_string(), Duration::minutes(123 * 60 + 5)),
+                ("b".to_string(), Duration::minutes(5)),
+            ],
+        };
+        assert_eq!(long.to_string(), "123 h  5 min: a\n  0 h  5 min: b\n");
+    }
+
+    #[test]
+    fn test_context_switches() {
+        assert_eq!(
+            ContextSwitches::new_from_entries(&[], &Config::default()),
+            ContextSwitches {
+                count: 0,
+                into: vec![]
+            }
+        );
+
+        let tl = Timelog::new_from_string(
+            "
+2022-06-09 07:00: arrived
+2022-06-09 08:00: p1: code
+2022-06-09 08:30: p2: review
+2022-06-09 09:00: p1: code
+2022-06-09 09:15: p1: test
+2022-06-09 10:00: **lunch
+2022-06-09 11:00: p1: code
+2022-06-09 11:30: meeting
+2022-06-09 12:00: p2: review
+2022-06-09 12:30: p1: code
+2022-06-09 13:00: home
+
+2022-06-10 07:00: arrived
+2022-06-10 08:00: p2: review
+",
+        );
+        let switches = ContextSwitches::new_from_entries(
+            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 2),
+            &Config::default(),
+        );
+        // p1 → p2 → p1 (test and lunch are no switch) → uncategorized → p2 → p1 → home
+        // (uncategorized), and p2 on the next day does not count
+        assert_eq!(switches.count, 6);
+        assert_eq!(
+            switches.into,
+            vec![
+                ("p2".to_string(), 2),
+                ("p1".to_string(), 2),
+                ("uncategorized".to_string(), 2),
+            ]
+        );
+
+        // the leaving marker block does not count
+        let switches = ContextSwitches::new_from_entries(
+            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 2),
+            &Config {
+                leaving_marker: Some("home".to_string()),
+                ..Config::default()
+            },
+        );
+        assert_eq!(switches.count, 5);
+        assert_eq!(
+            switches.to_string(),
+            "Context switches: 5
+   2: p2
+   2: p1
+   1: uncategorized
+"
+        );
+
+        // configured slack does not count either: p1 → p2 → p1 → p2 → p1 → home
+        let switches = ContextSwitches::new_from_entries(
+            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 2),
+            &Config {
+                slack_prefix: "meeting".to_string(),
+                ..Config::default()
+            },
+        );
+        assert_eq!(switches.count, 5);
+        assert_eq!(switches.into[2], ("uncategorized".to_string(), 1));
+    }
+
+    #[test]
+    fn test_arrival_header() {
+        assert_eq!(arrival_header(&[]), None);
+
+        let tl = Timelog::new_from_string(THREE_DAYS);
+        assert_eq!(
+            arrival_header(tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1)),
+            Some("Arrived 10:30 (arrived)".to_string())
+        );
+        // first day of several
+        assert_eq!(
+            arrival_header(tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 9).unwrap(), 2)),
+            Some("Arrived 07:00 (arrived)".to_string())
+        );
+
+        let tl = Timelog::new_from_string("2022-06-10 08:30: fix the build\n");
+        assert_eq!(
+            arrival_header(tl.get_day(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap())),
+            Some("Arrived 08:30 (fix the build) - the first entry of a day only records the start, its task is not counted".to_string())
+        );
+    }
+
+    #[test]
+    fn test_is_arrival() {
+        assert!(is_arrival("arrived"));
+        assert!(is_arrival("Start"));
+        assert!(is_arrival("arrived**"));
+        assert!(!is_arrival("fix the build"));
+        assert!(!is_arrival("** tea break"));
+    }
+
+    #[test]
+    fn test_week_grid() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-06 07:00: arrived
+2022-06-06 09:00: dev: code
+2022-06-06 10:30: ops: deploy
+
+2022-06-07 07:00: arrived
+2022-06-07 10:00: dev: review
+2022-06-07 10:30: ** tea
+
+2022-06-09 08:00: arrived
+2022-06-09 08:30: ops: monitoring
+2022-06-09 12:00: dev: code
+",
+        );
+        let grid = WeekGrid::new_from_entries(
+            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 12).unwrap(), 7),
+            &HashMap::new(),
+            &Config::default(),
+        );
+        assert_eq!(grid.rows.len(), 2);
+        assert_eq!(grid.rows[0].0, "dev");
+        assert_eq!(grid.rows[0].1[0], Duration::hours(2));
+        assert_eq!(grid.rows[0].1[1], Duration::hours(3));
+        assert_eq!(grid.rows[0].1[2], Duration::zero());
+        assert_eq!(
+            grid.column_totals()[3],
+            Duration::minutes(30) + Duration::minutes(210)
+        );
+
+        assert_eq!(
+            format!("{grid}"),
+            "        Mon   Tue   Wed   Thu   Fri   Sat   Sun  Total
+dev     2.0   3.0   0.0   3.5   0.0   0.0   0.0    8.5
+ops     1.5   0.0   0.0   0.5   0.0   0.0   0.0    2.0
+------------------------------------------------------
+Total   3.5   3.0   0.0   4.0   0.0   0.0   0.0   10.5
+"
+        );
+
+        assert_eq!(
+            format!(
+                "{}",
+                WeekGrid::new_from_entries(&[], &HashMap::new(), &Config::default())
+            ),
+            "        Mon   Tue   Wed   Thu   Fri   Sat   Sun  Total
+------------------------------------------------------
+Total   0.0   0.0   0.0   0.0   0.0   0.0   0.0    0.0
+"
+        );
+    }
+
+    #[test]
+    fn test_month_calendar() {
+        let first = NaiveDate::from_ymd_opt(2022, 6, 1).unwrap();
+        let tl = Timelog::new_from_string(
+            "
+2022-05-31 08:00: arrived
+2022-05-31 09:00: dev: code
+
+2022-06-01 08:00: arrived
+2022-06-01 15:30: dev: code
+2022-06-01 16:00: ** tea
+
+2022-06-03 08:00: arrived
+2022-06-03 16:00: ** vacation
+
+2022-06-13 07:00: arrived
+2022-06-13 17:15: ops: deploy
+
+2022-06-30 09:00: arrived
+2022-06-30 09:30: dev: code
+",
+        );
+        let cal =
+            MonthCalendar::new_from_entries(tl.get_period(&first, 30), first, &Config::default());
+        assert_eq!(cal.days.len(), 30);
+        assert_eq!(cal.days[0], Some(Duration::minutes(450)));
+        assert_eq!(cal.days[1], None);
+        assert_eq!(cal.days[2], None);
+        assert_eq!(cal.off, vec![NaiveDate::from_ymd_opt(2022, 6, 3).unwrap()]);
+
+        // June 2022 starts on a Wednesday
+        assert_eq!(
+            format!("{cal}"),
+            "June 2022
+    Mon     Tue     Wed     Thu     Fri     Sat     Sun
+                 1  7.5  2       3  off  4       5
+ 6       7       8       9      10      11      12
+13 10.2 14      15      16      17      18      19
+20      21      22      23      24      25      26
+27      28      29      30  0.5
+"
+        );
+
+        // entries outside of the month are ignored
+        let cal =
+            MonthCalendar::new_from_entries(tl.get_period(&first, 1), first, &Config::default());
+        assert_eq!(cal.days.iter().flatten().count(), 1);
+
+        // February 2021 starts on a Monday and has exactly four weeks
+        let first = NaiveDate::from_ymd_opt(2021, 2, 1).unwrap();
+        assert_eq!(
+            format!(
+                "{}",
+                MonthCalendar::new_from_entries(&[], first, &Config::default())
+            ),
+            "February 2021
+    Mon     Tue     Wed     Thu     Fri     Sat     Sun
+ 1       2       3       4       5       6       7
+ 8       9      10      11      12      13      14
+15      16      17      18      19      20      21
+22      23      24      25      26      27      28
+"
+        );
+    }
+
+    #[test]
+    fn test_rhythm() {
+        assert_eq!(Rhythm::new_from_entries(&[], &Config::default()), None);
+
+        let tl = Timelog::new_from_string(THREE_DAYS);
+        let r = Rhythm::new_from_entries(
+            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 3),
+            &Config::default(),
+        )
+        .unwrap();
+        assert_eq!(r.days, 3);
+        // the late start on the last day pulls the average, but not the median
+        assert_eq!(r.avg_start, NaiveTime::from_hms_opt(8, 20, 0).unwrap());
+        assert_eq!(r.median_start, NaiveTime::from_hms_opt(7, 30, 0).unwrap());
+        assert_eq!(r.avg_end, NaiveTime::from_hms_opt(16, 30, 0).unwrap());
+        assert_eq!(r.median_end, NaiveTime::from_hms_opt(16, 30, 0).unwrap());
+        assert_eq!(r.avg_span, Duration::hours(8) + Duration::minutes(10));
+
+        assert_eq!(
+            format!("{r}"),
+            "Days: 3
+Start: average 08:20, median 07:30
+End: average 16:30, median 16:30
+Workday span: average 8 h 10 min
+"
+        );
+    }
+
+    #[test]
+    fn test_rhythm_virtual_midnight() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-09 18:00: arrived
+2022-06-09 23:00: code
+
+2022-06-10 20:00: arrived
+2022-06-11 01:00: code
+2022-06-11 02:30: more code
+",
+        );
+        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 11).unwrap(), 3);
+
+        clock::set_virtual_midnight(NaiveTime::from_hms_opt(3, 0, 0));
+        let r = Rhythm::new_from_entries(entries, &Config::default()).unwrap();
+        assert_eq!(r.days, 2);
+        assert_eq!(r.avg_start, NaiveTime::from_hms_opt(19, 0, 0).unwrap());
+        // 23:00 and 02:30 on the next calendar day
+        assert_eq!(r.avg_end, NaiveTime::from_hms_opt(0, 45, 0).unwrap());
+        assert_eq!(r.median_end, NaiveTime::from_hms_opt(0, 45, 0).unwrap());
+        assert_eq!(r.avg_span, Duration::hours(5) + Duration::minutes(45));
+        clock::set_virtual_midnight(None);
+    }
+
+    #[test]
+    fn test_reports_with_config() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-01 07:00: arrived
+2022-06-01 09:00: p1: code
+2022-06-01 09:30: break: coffee
+2022-06-01 10:00: p1: code
+
+2022-06-02 07:00: arrived
+2022-06-02 08:00: break: vacation
+",
+        );
+        let first = NaiveDate::from_ymd_opt(2022, 6, 1).unwrap();
+        let entries = tl.get_period(&first, 2);
+        let config = Config {
+            slack_categories: vec!["break".to_string()],
+            ..Config::default()
+        };
+        let work = Activities::new_from_entries_with_config(entries, &config).total_work();
+        assert_eq!(work, Duration::minutes(150));
+
+        let stats = Stats::new_from_entries(entries, None, &config);
+        assert_eq!(stats.days.iter().map(|(_, d)| *d).sum::<Duration>(), work);
+        assert_eq!(stats.days[1].1, Duration::zero());
+
+        let cal = MonthCalendar::new_from_entries(entries, first, &config);
+        assert_eq!(cal.days[0], Some(work));
+        assert_eq!(cal.off, vec![first.succ_opt().unwrap()]);
+
+        let categories = TaskTotals::new_from_entries(entries, true, &config);
+        assert_eq!(categories.rows, vec![("p1".to_string(), work)]);
+
+        // the vacation day is not a work day
+        let r = Rhythm::new_from_entries_with_min(entries, Duration::minutes(1), &config).unwrap();
+        assert_eq!(r.days, 1);
+
+        // by default, "break" is a category of work
+        let stats = Stats::new_from_entries(entries, None, &Config::default());
+        assert_eq!(stats.days[1].1, Duration::hours(1));
+        let categories = TaskTotals::new_from_entries(entries, true, &Config::default());
+        assert_eq!(categories.rows.len(), 2);
+    }
+
+    #[test]
+    fn test_stats() {
+        let s = Stats::new_from_entries(&[], None, &Config::default());
+        assert_eq!(s.days, vec![]);
+        assert_eq!(s.average, None);
+
+        let tl = Timelog::new_from_string(THREE_DAYS);
+        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 3);
+
+        let s = Stats::new_from_entries(entries, None, &Config::default());
+        assert_eq!(s.days.len(), 3);
+        assert_eq!(s.days[0].1, Duration::hours(9));
+        assert_eq!(s.days[1].1, Duration::minutes(9 * 60 + 30));
+        assert_eq!(s.days[2].1, Duration::hours(6));
+        assert_eq!(s.average, Some(Duration::minutes(8 * 60 + 10)));
+
+        // still shown, but not counted
+        let s = Stats::new_from_entries(
+            entries,
+            NaiveDate::from_ymd_opt(2022, 6, 10),
+            &Config::default(),
+        );
+        assert_eq!(s.days.len(), 3);
+        assert_eq!(s.average, Some(Duration::minutes(9 * 60 + 15)));
+        assert_eq!(
+            format!("{s}"),
+            "Wed 2022-06-08: 9 h 0 min
+Thu 2022-06-09: 9 h 30 min
+Fri 2022-06-10: 6 h 0 min (not counted)
+-------
+Average work per day: 9 h 15 min
+"
+        );
+
+        // nothing left to count
+        let s = Stats::new_from_entries(
+            tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap(), 1),
+            NaiveDate::from_ymd_opt(2022, 6, 10),
+            &Config::default(),
+        );
+        assert_eq!(s.average, None);
+    }
+
+    #[test]
+    fn test_min_day_work() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-08 07:00: arrived
+2022-06-08 16:00: code
+
+2022-06-11 11:00: arrived
+2022-06-11 11:10: email
+2022-06-11 15:00: ** garden
+
+2022-06-13 08:00: arrived
+2022-06-13 15:00: code
+",
+        );
+        let entries = tl.get_n_days(&NaiveDate::from_ymd_opt(2022, 6, 13).unwrap(), 6);
+
+        // the trivial Saturday pulls down the average
+        let s = Stats::new_from_entries(entries, None, &Config::default());
+        assert_eq!(s.average, Some(Duration::minutes(9 * 60 + 10 + 7 * 60) / 3));
+
+        let s = Stats::new_from_entries_with_min(
+            entries,
+            None,
+            Duration::minutes(30),
+            &Config::default(),
+        );
+        assert_eq!(s.days.len(), 3);
+        assert_eq!(s.average, Some(Duration::hours(8)));
+        assert_eq!(
+            format!("{s}"),
+            "Wed 2022-06-08: 9 h 0 min
+Sat 2022-06-11: 0 h 10 min (not counted)
+Mon 2022-06-13: 7 h 0 min
+-------
+Average work per day: 8 h 0 min
+"
+        );
+
+        // the threshold is inclusive
+        let s = Stats::new_from_entries_with_min(
+            entries,
+            None,
+            Duration::minutes(10),
+            &Config::default(),
+        );
+        assert_eq!(
+            s.average,
+            Stats::new_from_entries(entries, None, &Config::default()).average
+        );
+
+        let r = Rhythm::new_from_entries(entries, &Config::default()).unwrap();
+        assert_eq!(r.days, 3);
+        let r =
+            Rhythm::new_from_entries_with_min(entries, Duration::minutes(30), &Config::default())
+                .unwrap();
+        assert_eq!(r.days, 2);
+        assert_eq!(r.avg_start, NaiveTime::from_hms_opt(7, 30, 0).unwrap());
+        assert_eq!(r.avg_end, NaiveTime::from_hms_opt(15, 30, 0).unwrap());
+
+        assert_eq!(
+            Rhythm::new_from_entries_with_min(entries, Duration::hours(10), &Config::default()),
+            None
+        );
+    }
+}
@@ -16,79 +16,426 @@
 extern crate chrono;
 extern crate dirs;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fmt;
 use std::fmt::Write as _; // import without risk of name clashing
 use std::fs::{self, File};
 use std::io::{self, prelude::*};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
-use chrono::{prelude::*, Duration, Local, NaiveDate, NaiveDateTime, Weekday};
+use chrono::{prelude::*, Duration, Months, NaiveDate, NaiveDateTime, Weekday};
+
+use crate::activity::{self, get_blocks};
+use crate::clock;
+use crate::config::Config;
+use crate::export;
+
+pub(crate) const TIME_FMT: &str = "%Y-%m-%d %H:%M";
+// marks an entry as continuation of an earlier block with the same task
+const CONTINUATION_MARK: &str = "(cont.)";
 
 /**
  * Single timelog entry
  */
-
-const TIME_FMT: &str = "%Y-%m-%d %H:%M";
-
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub struct Entry {
     pub stop: NaiveDateTime,
     pub task: String,
 }
 
+// the file only stores minutes, so entries in the same minute are ordered by their position
+fn minute_of(t: NaiveDateTime) -> NaiveDateTime {
+    t.with_second(0).unwrap().with_nanosecond(0).unwrap()
+}
+
+// collapse repeated spaces, which are easy to introduce when editing the file
+pub fn normalize_task(task: &str) -> String {
+    task.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+// split a trailing duration hint like "[~45m]" or "[~2h]" from the task
+fn split_duration_hint(task: &str) -> (&str, Option<Duration>) {
+    let parse = || {
+        let hint = task.strip_suffix(']')?;
+        let start = hint.rfind("[~")?;
+        let spec = &hint[start + 2..];
+        let duration = if let Some(m) = spec.strip_suffix('m') {
+            Duration::minutes(m.parse().ok()?)
+        } else {
+            Duration::hours(spec.strip_suffix('h')?.parse().ok()?)
+        };
+        Some((task[..start].trim_end(), duration))
+    };
+    match parse() {
+        Some((name, duration)) => (name, Some(duration)),
+        None => (task, None),
+    }
+}
+
+impl Entry {
+    // task without the continuation marker and duration hint
+    pub fn name(&self) -> &str {
+        let task = split_duration_hint(&self.task).0;
+        match task.strip_suffix(CONTINUATION_MARK) {
+            Some(name) => name.trim_end(),
+            None => task,
+        }
+    }
+
+    pub fn is_continuation(&self) -> bool {
+        split_duration_hint(&self.task)
+            .0
+            .ends_with(CONTINUATION_MARK)
+    }
+
+    // intended duration of the block, annotated like "code [~45m]"
+    pub fn duration_hint(&self) -> Option<Duration> {
+        split_duration_hint(&self.task).1
+    }
+
+    // name without gtimelog style tags, like "fix server" in "fix server -- sysadmin www"
+    pub fn description(&self) -> &str {
+        let name = self.name();
+        match name.split_once(" -- ") {
+            Some((description, _)) => description,
+            None => name,
+        }
+    }
+
+    // tags are part of the task, so that the file stays unchanged
+    pub fn tags(&self) -> Vec<&str> {
+        activity::tags(self.name())
+    }
+}
+
 impl fmt::Display for Entry {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}: {}", self.stop.format(TIME_FMT), self.task)
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProblemKind {
+    InvalidLine,
+    InvalidDate,
+    OutOfOrder,
+    DuplicateTime,
+}
+
+impl fmt::Display for ProblemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ProblemKind::InvalidLine => "invalid line",
+            ProblemKind::InvalidDate => "invalid date",
+            ProblemKind::OutOfOrder => "goes back in time",
+            ProblemKind::DuplicateTime => "same time as previous entry",
+        };
+        write!(f, "{s}")
+    }
+}
+
 /**
- * Collection of all entries
+ * Problem in a timelog file, as found by lint
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub struct Problem {
+    pub line_no: usize,
+    pub line: String,
+    pub kind: ProblemKind,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}: {}", self.line_no, self.kind, self.line)
+    }
+}
+
+/**
+ * Error when a timelog can't be read, as its entries would come out wrong
+ *
+ * Unlike invalid lines, which are ignored with a warning, these need to be fixed in the file.
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    // entry before the previous one
+    OutOfOrder { line_no: usize, line: String },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::OutOfOrder { line_no, line } => {
+                write!(f, "line {line_no} goes back in time: {line}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/**
+ * Error when the timelog file cannot be found or read
+ */
+#[derive(Debug, PartialEq, Eq)]
+pub enum StoreError {
+    // neither the home nor the data directory are known, e.g. $HOME is unset in a container
+    NoDataDir,
+    Parse(PathBuf, ParseError),
+    // a log which is not a file, like stdin, could not be read or parsed
+    ReadInput(String),
+    ParseInput(ParseError),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::NoDataDir => write!(
+                f,
+                "Cannot find the timelog file: unknown home and data directory; set $RTIMELOG_FILE"
+            ),
+            StoreError::Parse(path, e) => {
+                write!(
+                    f,
+                    "Cannot read {}: {e}; please fix the file",
+                    path.display()
+                )
+            }
+            StoreError::ReadInput(e) => write!(f, "Cannot read the timelog: {e}"),
+            StoreError::ParseInput(e) => write!(f, "Cannot read the timelog: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+/**
+ * Summary of the differences between two versions of a timelog, e.g. before and after editing
+ *
+ * An entry whose task changed, but not its time, counts as modified.
  */
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct EntryDiff {
+    pub added: usize,
+    pub removed: usize,
+    pub modified: usize,
+}
+
+impl EntryDiff {
+    pub fn new(before: &[Entry], after: &[Entry]) -> EntryDiff {
+        // positive: only in before, negative: only in after
+        let mut counts: HashMap<(NaiveDateTime, &str), isize> = HashMap::new();
+        for e in before {
+            *counts.entry((e.stop, &e.task)).or_default() += 1;
+        }
+        for e in after {
+            *counts.entry((e.stop, &e.task)).or_default() -= 1;
+        }
+
+        // (removed, added) per time
+        let mut by_time: HashMap<NaiveDateTime, (usize, usize)> = HashMap::new();
+        for ((stop, _), count) in counts {
+            let (removed, added) = by_time.entry(stop).or_default();
+            if count > 0 {
+                *removed += count as usize;
+            } else {
+                *added += (-count) as usize;
+            }
+        }
+
+        let mut diff = EntryDiff::default();
+        for (removed, added) in by_time.into_values() {
+            let modified = removed.min(added);
+            diff.modified += modified;
+            diff.removed += removed - modified;
+            diff.added += added - modified;
+        }
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == EntryDiff::default()
+    }
+}
+
+impl fmt::Display for EntryDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no changes");
+        }
+        let mut parts = Vec::new();
+        if self.added > 0 {
+            parts.push(format!("+{} entries", self.added));
+        }
+        if self.removed > 0 {
+            parts.push(format!("-{} entries", self.removed));
+        }
+        if self.modified > 0 {
+            parts.push(format!("{} modified", self.modified));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+// how far diff_lines() looks ahead for the next common line
+const DIFF_LOOKAHEAD: usize = 100;
+
+/**
+ * Line based diff of two texts, with a "@@ line N @@" header (of old) for each change
+ *
+ * This resyncs greedily at the nearest common line, which is good enough for the local
+ * changes of reformatting, and linear for big files. A last line without newline is
+ * marked as such.
+ */
+pub fn diff_lines(old: &str, new: &str) -> String {
+    let a: Vec<&str> = old.split_inclusive('\n').collect();
+    let b: Vec<&str> = new.split_inclusive('\n').collect();
+    let show = |prefix: char, line: &str| match line.strip_suffix('\n') {
+        Some(l) => format!("{prefix}{l}\n"),
+        None => format!("{prefix}{line} (no newline at end)\n"),
+    };
+    let mut output = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            i += 1;
+            j += 1;
+            continue;
+        }
+        // smallest number of skipped lines on both sides until they are in sync again
+        let (di, dj) = (1..=DIFF_LOOKAHEAD)
+            .flat_map(|d| (0..=d).map(move |di| (di, d - di)))
+            .find(|(di, dj)| i + di < a.len() && j + dj < b.len() && a[i + di] == b[j + dj])
+            .unwrap_or((a.len() - i, b.len() - j));
+        writeln!(output, "@@ line {} @@", i + 1).expect("failed to format diff");
+        for line in &a[i..i + di] {
+            output.push_str(&show('-', line));
+        }
+        for line in &b[j..j + dj] {
+            output.push_str(&show('+', line));
+        }
+        i += di;
+        j += dj;
+    }
+    output
+}
 
+/**
+ * Collection of all entries
+ */
 #[derive(Default, Debug)]
 pub struct Timelog {
     entries: Vec<Entry>,
     pub filename: Option<PathBuf>,
+    // modification time of the file when it was last read or written
+    mtime: Option<SystemTime>,
+    // entries from the read-only base log, which are never saved
+    base: HashSet<(NaiveDateTime, String)>,
 }
 
 impl Timelog {
-    pub fn new_from_default_file() -> Timelog {
-        Timelog::new_from_file(&Timelog::get_default_file())
+    pub fn new_from_default_file() -> Result<Timelog, StoreError> {
+        Timelog::new_from_file(&Timelog::get_default_file()?)
     }
 
-    pub fn new_from_file(path: &PathBuf) -> Timelog {
-        Timelog {
-            entries: Timelog::parse(&Timelog::read(path)),
+    pub fn new_from_file(path: &PathBuf) -> Result<Timelog, StoreError> {
+        let entries = Timelog::try_parse(&Timelog::read(path))
+            .map_err(|e| StoreError::Parse(path.clone(), e))?;
+        Ok(Timelog {
+            entries,
             filename: Some(path.clone()),
+            mtime: Timelog::get_mtime(path),
+            ..Default::default()
+        })
+    }
+
+    /**
+     * Add the entries of a read-only base log, like holidays shared in a team
+     *
+     * They are part of all queries, but save() only writes the own entries. Base entries
+     * which are already in this log are skipped.
+     */
+    pub fn with_base(mut self, base: Timelog) -> Timelog {
+        let existing: HashSet<(NaiveDateTime, String)> = self
+            .entries
+            .iter()
+            .map(|e| (e.stop, e.task.clone()))
+            .collect();
+        for entry in base.entries {
+            let key = (entry.stop, entry.task.clone());
+            if !existing.contains(&key) && self.base.insert(key) {
+                self.entries.push(entry);
+            }
         }
+        // stable, so that own entries stay before base ones with the same time
+        self.entries.sort_by_key(|e| minute_of(e.stop));
+        self
+    }
+
+    // with_base() for the base_file setting; a missing or broken base file is ignored with a warning
+    pub fn with_base_file(self, base: Option<&PathBuf>) -> Timelog {
+        match base {
+            Some(path) if path.exists() => match Timelog::new_from_file(path) {
+                Ok(base) => self.with_base(base),
+                Err(e) => {
+                    eprintln!("WARNING: ignoring base log: {e}");
+                    self
+                }
+            },
+            Some(path) => {
+                eprintln!("WARNING: base log {} does not exist", path.display());
+                self
+            }
+            None => self,
+        }
+    }
+
+    // in-memory log which does not get saved, e.g. from stdin
+    pub fn new_from_reader(mut reader: impl Read) -> Result<Timelog, StoreError> {
+        let mut contents = String::new();
+        reader
+            .read_to_string(&mut contents)
+            .map_err(|e| StoreError::ReadInput(e.to_string()))?;
+        Ok(Timelog {
+            entries: Timelog::try_parse(&contents).map_err(StoreError::ParseInput)?,
+            ..Default::default()
+        })
     }
 
     #[cfg(test)]
     pub fn new_from_string(contents: &str) -> Timelog {
         Timelog {
             entries: Timelog::parse(contents),
-            filename: None,
+            ..Default::default()
         }
     }
 
-    pub fn get_default_file() -> PathBuf {
-        let mut legacy_dir = dirs::home_dir().unwrap();
-        legacy_dir.push(".gtimelog");
-        let mut log_path = if legacy_dir.is_dir() {
-            legacy_dir
-        } else {
-            let mut data_dir = match env::var_os("XDG_DATA_HOME") {
-                Some(val) => PathBuf::from(val.into_string().unwrap()),
-                None => dirs::data_dir().unwrap(),
-            };
-            data_dir.push("gtimelog");
-            data_dir
+    pub fn get_default_file() -> Result<PathBuf, StoreError> {
+        if let Some(path) = env::var_os("RTIMELOG_FILE").filter(|p| !p.is_empty()) {
+            return Ok(PathBuf::from(path));
+        }
+
+        let data_dir = match env::var_os("XDG_DATA_HOME").filter(|d| !d.is_empty()) {
+            Some(dir) => Some(PathBuf::from(dir)),
+            None => dirs::data_dir(),
+        };
+        Timelog::find_default_file(dirs::home_dir(), data_dir)
+    }
+
+    // ~/.gtimelog/timelog.txt if that directory exists, otherwise in the data directory
+    fn find_default_file(
+        home: Option<PathBuf>,
+        data_dir: Option<PathBuf>,
+    ) -> Result<PathBuf, StoreError> {
+        let legacy_dir = home.map(|h| h.join(".gtimelog"));
+        let mut log_path = match (legacy_dir, data_dir) {
+            (Some(legacy_dir), _) if legacy_dir.is_dir() => legacy_dir,
+            (_, Some(data_dir)) => data_dir.join("gtimelog"),
+            (_, None) => return Err(StoreError::NoDataDir),
         };
         log_path.push("timelog.txt");
-        log_path
+        Ok(log_path)
     }
 
     fn read(path: &PathBuf) -> String {
@@ -111,42 +458,177 @@ impl Timelog {
         }
     }
 
+    // some Windows editors start UTF-8 files with a byte order mark
+    fn strip_bom(raw: &str) -> &str {
+        raw.strip_prefix('\u{feff}').unwrap_or(raw)
+    }
+
+    // for raw timelogs which are known to be in order, like after lint()
     fn parse(raw: &str) -> Vec<Entry> {
+        Timelog::try_parse(raw).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    pub fn try_parse(raw: &str) -> Result<Vec<Entry>, ParseError> {
         let mut entries = Vec::new();
         let mut prev: Option<NaiveDateTime> = None;
 
-        for line in raw.lines() {
+        for (i, line) in Timelog::strip_bom(raw).lines().enumerate() {
             if let Some(e) = Timelog::parse_line(line) {
                 // require a monotonously increasing file
                 if prev.is_some() && e.stop < prev.unwrap() {
-                    panic!("line {line} goes back in time");
+                    return Err(ParseError::OutOfOrder {
+                        line_no: i + 1,
+                        line: line.to_string(),
+                    });
                 }
                 prev = Some(e.stop);
                 entries.push(e);
             }
         }
-        entries
+        Ok(entries)
     }
 
     fn parse_line(line: &str) -> Option<Entry> {
+        match Timelog::parse_line_strict(line) {
+            Ok(entry) => entry,
+            Err(ProblemKind::InvalidDate) => {
+                eprintln!(
+                    "WARNING: ignoring line with invalid date in timelog: {}",
+                    line.trim()
+                );
+                None
+            }
+            Err(_) => {
+                eprintln!("WARNING: ignoring invalid line in timelog: {}", line.trim());
+                None
+            }
+        }
+    }
+
+    // Ok(None) for empty lines
+    // Times must be valid wall clock times; odd ones like 24:00, 12:60, or the leap second
+    // 23:59:60 are consistently rejected as invalid date, instead of guessing what was meant.
+    fn parse_line_strict(line: &str) -> Result<Option<Entry>, ProblemKind> {
         let line = line.trim();
         if line.is_empty() {
-            return None;
+            return Ok(None);
         }
 
-        if let Some((time, task)) = line.split_once(": ") {
-            if let Ok(dt) = NaiveDateTime::parse_from_str(time, TIME_FMT) {
-                Some(Entry {
-                    stop: dt,
-                    task: task.to_string(),
-                })
-            } else {
-                eprintln!("WARNING: ignoring line with invalid date in timelog: {line}");
-                None
+        let (time, task) = line.split_once(": ").ok_or(ProblemKind::InvalidLine)?;
+        let dt =
+            NaiveDateTime::parse_from_str(time, TIME_FMT).map_err(|_| ProblemKind::InvalidDate)?;
+        Ok(Some(Entry {
+            stop: dt,
+            task: task.to_string(),
+        }))
+    }
+
+    // check raw timelog for all problems, unlike try_parse() which ignores or stops at them
+    pub fn lint(raw: &str) -> Vec<Problem> {
+        let mut problems = Vec::new();
+        let mut prev: Option<NaiveDateTime> = None;
+
+        for (i, line) in Timelog::strip_bom(raw).lines().enumerate() {
+            let kind = match Timelog::parse_line_strict(line) {
+                Ok(None) => continue,
+                Ok(Some(e)) => {
+                    let kind = match prev {
+                        Some(p) if e.stop < p => Some(ProblemKind::OutOfOrder),
+                        Some(p) if e.stop == p => Some(ProblemKind::DuplicateTime),
+                        _ => None,
+                    };
+                    prev = Some(prev.map_or(e.stop, |p| p.max(e.stop)));
+                    match kind {
+                        Some(k) => k,
+                        None => continue,
+                    }
+                }
+                Err(k) => k,
+            };
+
+            problems.push(Problem {
+                line_no: i + 1,
+                line: line.to_string(),
+                kind,
+            });
+        }
+
+        problems
+    }
+
+    // one line summary of lint problems, for showing at startup; None if there are none
+    pub fn lint_banner(raw: &str) -> Option<String> {
+        match Timelog::lint(raw).len() {
+            0 => None,
+            1 => Some("⚠ 1 issue found; run rtimelog-cmd check".to_string()),
+            n => Some(format!("⚠ {n} issues found; run rtimelog-cmd check")),
+        }
+    }
+
+    /**
+     * Raw timelog with exactly one empty line between days, like save() writes it
+     *
+     * Returns None if raw already has that format. Lines which can't be parsed, or go
+     * back in time, would get lost or reordered, so these are returned as error instead.
+     */
+    pub fn reformat(raw: &str) -> Result<Option<String>, Vec<Problem>> {
+        let problems: Vec<Problem> = Timelog::lint(raw)
+            .into_iter()
+            .filter(|p| p.kind != ProblemKind::DuplicateTime)
+            .collect();
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+        let timelog = Timelog {
+            entries: Timelog::parse(raw),
+            ..Default::default()
+        };
+        let formatted = timelog.format_store();
+        Ok(Some(formatted).filter(|f| f != raw))
+    }
+
+    /**
+     * Raw timelog as save() would write it after reading raw
+     *
+     * Unlike reformat(), this drops lines which can't be parsed, to show what would be lost.
+     * Lines which go back in time can't be read at all, so these are returned as error.
+     */
+    pub fn roundtrip(raw: &str) -> Result<String, Vec<Problem>> {
+        let problems: Vec<Problem> = Timelog::lint(raw)
+            .into_iter()
+            .filter(|p| p.kind == ProblemKind::OutOfOrder)
+            .collect();
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+        let timelog = Timelog {
+            entries: Timelog::parse(raw),
+            ..Default::default()
+        };
+        Ok(timelog.format_store())
+    }
+
+    /**
+     * Raw timelog without a truncated last line, like "2022-06-10 16:0" after a crash
+     *
+     * Returns the repaired raw timelog and the problem of the removed line, or None if
+     * all lines can be parsed. Only the last line gets removed, and only if it is the
+     * sole line which can't be parsed; otherwise all these are returned as error.
+     */
+    pub fn repair_truncated(raw: &str) -> Result<Option<(String, Problem)>, Vec<Problem>> {
+        let mut problems: Vec<Problem> = Timelog::lint(raw)
+            .into_iter()
+            .filter(|p| matches!(p.kind, ProblemKind::InvalidLine | ProblemKind::InvalidDate))
+            .collect();
+        let content = raw.trim_end();
+        let last_line_no = Timelog::strip_bom(content).lines().count();
+        match problems.as_slice() {
+            [] => Ok(None),
+            [p] if p.line_no == last_line_no => {
+                let start = content.rfind('\n').map_or(0, |i| i + 1);
+                Ok(Some((raw[..start].to_string(), problems.remove(0))))
             }
-        } else {
-            eprintln!("WARNING: ignoring invalid line in timelog: {line}");
-            None
+            _ => Err(problems),
         }
     }
 
@@ -154,21 +636,57 @@ impl Timelog {
         let mut output = String::new();
         let mut prev: Option<NaiveDate> = None;
 
-        for entry in &self.entries {
+        let own = self
+            .entries
+            .iter()
+            .filter(|e| !self.base.contains(&(e.stop, e.task.clone())));
+        for entry in own {
             // leave an empty line between days
-            if prev.is_some() && prev.unwrap() != entry.stop.date() {
+            let date = clock::logical_date(entry.stop);
+            if prev.is_some() && prev.unwrap() != date {
                 output.push('\n');
             }
-            prev = Some(entry.stop.date());
+            prev = Some(date);
             writeln!(output, "{entry}").expect("failed to format entry");
         }
 
         output
     }
 
-    pub fn save(&self) -> Result<(), io::Error> {
+    /**
+     * Write all entries to the file
+     *
+     * This fails if the file was changed by something else since it was read, to avoid
+     * overwriting these changes. Then reload the file, and apply the change again.
+     */
+    pub fn save(&mut self) -> Result<(), io::Error> {
         assert!(self.filename.is_some());
         let filename = self.filename.as_ref().unwrap();
+        if self.changed_externally() {
+            return Err(io::Error::other(format!(
+                "{} was changed by another program, not overwriting it",
+                filename.display()
+            )));
+        }
+        self.write_file(filename)
+            .map_err(|e| Timelog::save_error(filename, e))?;
+        self.mtime = Timelog::get_mtime(filename);
+        Ok(())
+    }
+
+    fn get_mtime(path: &Path) -> Option<SystemTime> {
+        fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    // whether the file was modified (or created) since it was read or written
+    pub fn changed_externally(&self) -> bool {
+        match &self.filename {
+            Some(filename) => Timelog::get_mtime(filename) != self.mtime,
+            None => false,
+        }
+    }
+
+    fn write_file(&self, filename: &PathBuf) -> Result<(), io::Error> {
         if let Some(parent) = filename.parent() {
             fs::create_dir_all(parent)?;
         }
@@ -177,9 +695,25 @@ impl Timelog {
         Ok(())
     }
 
+    // actionable error message which names the file
+    fn save_error(filename: &Path, e: io::Error) -> io::Error {
+        let msg = match e.kind() {
+            io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem => format!(
+                "cannot write to {}: permission denied; set RTIMELOG_FILE to a writable file",
+                filename.display()
+            ),
+            _ => format!("cannot write to {}: {}", filename.display(), e),
+        };
+        io::Error::new(e.kind(), msg)
+    }
+
+    pub fn diff(&self, edited: &Timelog) -> EntryDiff {
+        EntryDiff::new(&self.entries, &edited.entries)
+    }
+
     #[cfg(test)]
     pub fn get_all(&self) -> impl Iterator<Item = &Entry> {
-        return self.entries.iter();
+        self.entries.iter()
     }
 
     pub fn get_time_range(&self, begin: NaiveDateTime, end: NaiveDateTime) -> &[Entry] {
@@ -197,45 +731,140 @@ impl Timelog {
         &self.entries[first..last]
     }
 
-    // get entries for n most recent days including given day
+    // get entries for n most recent days including given day; none for n = 0
     pub fn get_n_days(&self, day: &NaiveDate, n: u32) -> &[Entry] {
-        let eod = day.and_hms_opt(23, 59, 59).unwrap();
+        if n == 0 {
+            return &[];
+        }
+        let eod = clock::day_start(&(*day + Duration::days(1))) - Duration::seconds(1);
         self.get_time_range(eod - Duration::days(n as i64), eod)
     }
 
+    pub fn get_day(&self, day: &NaiveDate) -> &[Entry] {
+        self.get_n_days(day, 1)
+    }
+
+    // the arrival: it only provides the start time of the day, whatever its task is
+    pub fn first_entry_of_day(&self, day: &NaiveDate) -> Option<&Entry> {
+        self.get_day(day).first()
+    }
+
+    /**
+     * Entry whose block contains the given instant, i.e. what you were doing then
+     *
+     * A block goes from the previous entry's stop (exclusive) to its entry's stop
+     * (inclusive). The first entry of a day only provides the start time, so there is
+     * no block before it.
+     */
+    pub fn entry_at(&self, when: NaiveDateTime) -> Option<&Entry> {
+        let i = self.entries.iter().position(|e| e.stop >= when)?;
+        let prev = self.entries.get(i.checked_sub(1)?)?;
+        let entry = &self.entries[i];
+        if clock::logical_date(prev.stop) == clock::logical_date(entry.stop) && prev.stop < when {
+            Some(entry)
+        } else {
+            None
+        }
+    }
+
+    // get entries for n days starting with given day, like a sprint
+    pub fn get_period(&self, start: &NaiveDate, days: u32) -> &[Entry] {
+        let begin = clock::day_start(start);
+        self.get_time_range(
+            begin,
+            begin + Duration::days(days as i64) - Duration::seconds(1),
+        )
+    }
+
+    // get entries from the first to the last given day; last can be NaiveDate::MAX for an
+    // open range
+    pub fn get_date_range(&self, first: &NaiveDate, last: &NaiveDate) -> &[Entry] {
+        let end = match last.succ_opt() {
+            Some(next) => clock::day_start(&next) - Duration::seconds(1),
+            None => NaiveDateTime::MAX,
+        };
+        self.get_time_range(clock::day_start(first), end)
+    }
+
+    // export::write_csv() of the entries from begin to end (inclusive), e.g. for spreadsheets
+    pub fn to_csv(&self, begin: &NaiveDate, end: &NaiveDate) -> String {
+        export::render_csv(self.get_date_range(begin, end))
+    }
+
+    // get entries of the given ISO week from Monday to Sunday; None if the year has no such week
+    pub fn get_iso_week(&self, year: i32, week: u32) -> Option<&[Entry]> {
+        let monday = NaiveDate::from_isoywd_opt(year, week, Weekday::Mon)?;
+        Some(self.get_period(&monday, 7))
+    }
+
+    /**
+     * Get entries of the week which starts on the anchor weekday and contains day
+     *
+     * This is for contracts with weeks like Thursday to Wednesday: The week goes from
+     * the most recent anchor weekday (which can be day itself) to six days later.
+     */
+    pub fn get_custom_week(&self, day: &NaiveDate, anchor: Weekday) -> &[Entry] {
+        let days_since_anchor =
+            (day.weekday().num_days_from_monday() + 7 - anchor.num_days_from_monday()) % 7;
+        self.get_period(&(*day - Duration::days(days_since_anchor.into())), 7)
+    }
+
+    // get_days_ago(0) is today, get_days_ago(1) yesterday
+    pub fn get_days_ago(&self, n: i64) -> &[Entry] {
+        self.get_day(&(clock::today() - Duration::days(n)))
+    }
+
     pub fn get_today_as_string(&self) -> String {
-        Local::now().format("%A, %F (week %W)").to_string()
+        clock::today().format("%A, %F (week %W)").to_string()
     }
 
-    // get entries for n most recent weeks including week of given day
+    // get entries for n most recent weeks including week of given day; none for n = 0
     pub fn get_n_weeks(&self, day: &NaiveDate, n: u32) -> &[Entry] {
+        if n == 0 {
+            return &[];
+        }
         let week_of_day = day.iso_week().week();
-        let eow = NaiveDate::from_isoywd_opt(day.year(), week_of_day + 1, Weekday::Mon)
-            .unwrap()
-            .and_hms_opt(0, 0, 0)
-            .unwrap();
+        let eow = clock::day_start(
+            &NaiveDate::from_isoywd_opt(day.year(), week_of_day + 1, Weekday::Mon).unwrap(),
+        );
         self.get_time_range(eow - Duration::weeks(n as i64), eow)
     }
 
+    // get entries for n most recent calendar months including month of given day; none for
+    // n = 0
+    pub fn get_n_months(&self, day: &NaiveDate, n: u32) -> &[Entry] {
+        if n == 0 {
+            return &[];
+        }
+        let next = day.with_day(1).unwrap() + Months::new(1);
+        let begin = next - Months::new(n);
+        self.get_period(&begin, (next - begin).num_days() as u32)
+    }
+
+    pub fn get_month(&self, day: &NaiveDate) -> &[Entry] {
+        self.get_n_months(day, 1)
+    }
+
     pub fn get_this_week_as_string(&self) -> String {
-        let now_local = Local::now();
-        let week_begin = now_local
-            .checked_sub_signed(Duration::days(
-                now_local.weekday().num_days_from_monday().into(),
-            ))
-            .unwrap();
-        let week_end = week_begin.checked_add_signed(Duration::days(6)).unwrap();
-        let this_week = if week_begin.month() == now_local.month() {
+        let today = clock::today();
+        let week_begin = today - Duration::days(today.weekday().num_days_from_monday().into());
+        let week_end = week_begin + Duration::days(6);
+        let this_week = if week_begin.month() == today.month() {
             format!(
                 "{} {}-{}",
-                now_local.format("%B"),
+                today.format("%B"),
                 week_begin.day(),
                 week_end.day()
             )
         } else {
             format!("{}-{}", week_begin.format("%B %e"), week_end.day())
         };
-        format!("{} ({})", now_local.format("%Y, week %W"), this_week)
+        format!("{} ({})", today.format("%Y, week %W"), this_week)
+    }
+
+    // split entries into slices of the same day
+    pub fn split_days(entries: &[Entry]) -> impl Iterator<Item = &[Entry]> {
+        entries.chunk_by(|a, b| clock::logical_date(a.stop) == clock::logical_date(b.stop))
     }
 
     pub fn get_history(entries: &[Entry]) -> Vec<&String> {
@@ -254,51 +883,223 @@ impl Timelog {
             .collect()
     }
 
-    pub fn add(&mut self, task: String) {
-        let now = Local::now();
-        let naivenow = NaiveDate::from_ymd_opt(now.year(), now.month(), now.day())
-            .unwrap()
-            .and_hms_opt(now.hour(), now.minute(), now.second())
-            .unwrap();
-        self.entries.push(Entry {
-            task,
-            stop: naivenow,
-        });
+    // work per gtimelog style tag; an activity with several tags counts fully for each
+    pub fn get_tag_totals(entries: &[Entry], config: &Config) -> HashMap<String, Duration> {
+        let mut totals = HashMap::new();
+        for block in get_blocks(entries) {
+            if config.is_slack(&block.task) {
+                continue;
+            }
+            for tag in activity::tags(&block.task) {
+                *totals.entry(tag.to_string()).or_insert_with(Duration::zero) += block.duration();
+            }
+        }
+        totals
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use chrono::Duration;
-    use pretty_assertions::assert_eq;
+    // task of the most recent entry, or of the last entry on the given day
+    // the base log's entries are not your own tasks, and can be in the future
+    pub fn get_last_task(&self, day: Option<&NaiveDate>) -> Option<&str> {
+        let entries = match day {
+            Some(d) => self.get_n_days(d, 1),
+            None => &self.entries,
+        };
+        entries
+            .iter()
+            .rev()
+            .find(|e| !self.base.contains(&(e.stop, e.task.clone())))
+            .map(|e| e.task.as_str())
+    }
 
-    const TWO_DAYS: &'static str = "
-2022-06-09 06:02: arrived
-2022-06-09 06:27: email
-2022-06-09 06:32: **tea
-2022-06-09 12:00: work
+    // normalize spacing in all tasks; returns the number of changed entries
+    pub fn normalize_tasks(&mut self) -> usize {
+        let mut changed = 0;
+        for e in self.entries.iter_mut() {
+            let normalized = normalize_task(&e.task);
+            if normalized != e.task {
+                e.task = normalized;
+                changed += 1;
+            }
+        }
+        changed
+    }
 
-2022-06-10 07:00: arrived
-2022-06-10 12:05: rtimelog: code
-2022-06-10 12:30: **lunch
-2022-06-10 14:00: rtimelog: code
-2022-06-10 15:00: bug triage
-2022-06-10 16:00: customer joe: support
-";
+    /**
+     * Change the task of all entries which are exactly from to to, e.g. to fix typos
+     *
+     * Entries of the base log are not changed, as these are never saved. Returns the
+     * number of changed entries.
+     */
+    pub fn rename_task(&mut self, from: &str, to: &str) -> usize {
+        self.rename(from, to, false)
+    }
 
-    const TWO_WEEKS: &'static str = "
-2022-06-01 06:00: arrived
-2022-06-01 07:00: workw1
-2022-06-01 07:10: ** tea
+    // like rename_task(), but replace from wherever it occurs in the task
+    pub fn rename_task_substring(&mut self, from: &str, to: &str) -> usize {
+        self.rename(from, to, true)
+    }
 
-2022-06-03 06:00: arrived
-2022-06-03 07:00: workw1
-2022-06-03 07:10: ** tea
+    fn rename(&mut self, from: &str, to: &str, substring: bool) -> usize {
+        let mut changed = 0;
+        for e in self.entries.iter_mut() {
+            if self.base.contains(&(e.stop, e.task.clone())) {
+                continue;
+            }
+            let renamed = if substring {
+                e.task.replace(from, to)
+            } else if e.task == from {
+                to.to_string()
+            } else {
+                continue;
+            };
+            if renamed != e.task {
+                e.task = renamed;
+                changed += 1;
+            }
+        }
+        changed
+    }
 
-2022-06-08 06:00: arrived
-2022-06-08 07:00: workw2
-2022-06-08 07:10: ** tea
+    /**
+     * Add the entries of another log, e.g. from a different machine
+     *
+     * Exact duplicates are skipped. Entries with the same time but a different task are
+     * both kept, with a warning. Returns the number of added entries.
+     */
+    pub fn merge(&mut self, other: Timelog) -> usize {
+        let existing: HashSet<(NaiveDateTime, String)> = self
+            .entries
+            .iter()
+            .map(|e| (e.stop, e.task.clone()))
+            .collect();
+        let times: HashSet<NaiveDateTime> = self.entries.iter().map(|e| e.stop).collect();
+
+        let mut added = 0;
+        for entry in other.entries {
+            if existing.contains(&(entry.stop, entry.task.clone())) {
+                continue;
+            }
+            if times.contains(&entry.stop) {
+                eprintln!(
+                    "WARNING: conflicting entries at {}, keeping both",
+                    entry.stop.format(TIME_FMT)
+                );
+            }
+            self.entries.push(entry);
+            added += 1;
+        }
+
+        // stable, so that existing entries stay before new ones with the same time
+        self.entries.sort_by_key(|e| minute_of(e.stop));
+        added
+    }
+
+    pub fn add(&mut self, task: String) -> &Entry {
+        let stop = clock::now();
+        // the base log can have entries in the future, like holidays
+        let i = self
+            .entries
+            .partition_point(|e| minute_of(e.stop) <= minute_of(stop));
+        self.entries.insert(i, Entry { task, stop });
+        &self.entries[i]
+    }
+
+    /**
+     * Add an entry with an explicit time, e.g. one which was forgotten to enter in time
+     *
+     * Unlike add(), this puts it at the right position. It must not be before the last
+     * entry of that day, as that would split an existing block, and not in the future, as
+     * the next add() would then go back in time.
+     *
+     * The file only has minutes, so an entry in the same minute as an earlier one goes
+     * after it, even if that was added with a later second; that is the same order as
+     * after reloading the file.
+     */
+    pub fn add_at(&mut self, task: String, when: NaiveDateTime) -> Result<&Entry, String> {
+        if when > clock::now() {
+            return Err(format!("{} is in the future", when.format("%H:%M")));
+        }
+        let i = self
+            .entries
+            .partition_point(|e| minute_of(e.stop) <= minute_of(when));
+        if let Some(last) = self.entries[i..]
+            .iter()
+            .take_while(|e| clock::logical_date(e.stop) == clock::logical_date(when))
+            .last()
+        {
+            return Err(format!(
+                "{} is before the last entry of that day at {}",
+                when.format("%H:%M"),
+                last.stop.format("%H:%M")
+            ));
+        }
+        // keep the seconds in memory monotonous
+        let when = match i {
+            0 => when,
+            _ => when.max(self.entries[i - 1].stop),
+        };
+        self.entries.insert(i, Entry { task, stop: when });
+        Ok(&self.entries[i])
+    }
+
+    // add_at() for the given number of minutes ago, like "I finished that 15 minutes ago"
+    pub fn add_minutes_ago(&mut self, task: String, minutes: u32) -> Result<&Entry, String> {
+        self.add_at(task, clock::now() - Duration::minutes(minutes.into()))
+    }
+
+    // remove the most recent entry, e.g. a mistakenly added one; None if there are no entries
+    // entries of the base log are never saved, so these can't be removed
+    pub fn remove_last(&mut self) -> Option<Entry> {
+        let i = self
+            .entries
+            .iter()
+            .rposition(|e| !self.base.contains(&(e.stop, e.task.clone())))?;
+        Some(self.entries.remove(i))
+    }
+}
+
+// all entries in chronological order, like "for entry in &timelog"
+impl<'a> IntoIterator for &'a Timelog {
+    type Item = &'a Entry;
+    type IntoIter = std::slice::Iter<'a, Entry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use pretty_assertions::assert_eq;
+
+    const TWO_DAYS: &str = "
+2022-06-09 06:02: arrived
+2022-06-09 06:27: email
+2022-06-09 06:32: **tea
+2022-06-09 12:00: work
+
+2022-06-10 07:00: arrived
+2022-06-10 12:05: rtimelog: code
+2022-06-10 12:30: **lunch
+2022-06-10 14:00: rtimelog: code
+2022-06-10 15:00: bug triage
+2022-06-10 16:00: customer joe: support
+";
+
+    const TWO_WEEKS: &str = "
+2022-06-01 06:00: arrived
+2022-06-01 07:00: workw1
+2022-06-01 07:10: ** tea
+
+2022-06-03 06:00: arrived
+2022-06-03 07:00: workw1
+2022-06-03 07:10: ** tea
+
+2022-06-08 06:00: arrived
+2022-06-08 07:00: workw2
+2022-06-08 07:10: ** tea
 
 2022-06-09 06:00: arrived
 2022-06-09 07:00: workw2
@@ -331,6 +1132,306 @@ mod tests {
         assert_eq!(Timelog::parse_line("2022-05-31 25:61: email"), None);
         // invalid date
         assert_eq!(Timelog::parse_line("2022-13-32 13:59: email"), None);
+        assert_eq!(Timelog::parse_line("2022-02-29 13:59: email"), None);
+    }
+
+    #[test]
+    fn test_parse_line_odd_times() {
+        for line in [
+            "2022-06-10 23:59:60: leap second",
+            "2022-06-10 23:60: leap minute",
+            "2022-06-10 24:00: midnight",
+            "2022-06-10 12:60: email",
+            "2022-06-10 -1:00: email",
+            "2022-06-10 12:: email",
+            "2022-06-10 99999999999999999999:00: email",
+            "99999-06-10 12:00: email",
+            "2022-06-10 12:00:",
+        ] {
+            assert_eq!(Timelog::parse_line(line), None, "{line}");
+            assert!(Timelog::parse_line_strict(line).is_err(), "{line}");
+        }
+
+        // unpadded numbers are unambiguous
+        let e = Timelog::parse_line("2022-06-10 1:5: email").unwrap();
+        assert_eq!(e.stop.format(TIME_FMT).to_string(), "2022-06-10 01:05");
+
+        // seconds are not part of the format, so the first ": " ends the time
+        let e = Timelog::parse_line("2022-06-10 12:00: 15:30: call").unwrap();
+        assert_eq!(e.task, "15:30: call");
+    }
+
+    #[test]
+    fn test_entry_name() {
+        let e = Timelog::parse_line("2022-05-31 13:59: code").unwrap();
+        assert_eq!(e.name(), "code");
+        assert!(!e.is_continuation());
+
+        let e = Timelog::parse_line("2022-05-31 13:59: code (cont.)").unwrap();
+        assert_eq!(e.name(), "code");
+        assert!(e.is_continuation());
+        // marker is kept in the file
+        assert_eq!(&format!("{}", e), "2022-05-31 13:59: code (cont.)");
+    }
+
+    #[test]
+    fn test_entry_tags() {
+        let e = Timelog::parse_line("2022-05-31 13:59: fix server").unwrap();
+        assert_eq!(e.description(), "fix server");
+        assert!(e.tags().is_empty());
+
+        let e =
+            Timelog::parse_line("2022-05-31 13:59: fix server -- sysadmin www (cont.)").unwrap();
+        assert_eq!(e.description(), "fix server");
+        assert_eq!(e.tags(), vec!["sysadmin", "www"]);
+
+        // tags are kept in the file
+        let input = "2022-05-31 13:59: fix server -- sysadmin www [~1h]\n";
+        let tl = Timelog::new_from_string(input);
+        assert_eq!(tl.entries[0].tags(), vec!["sysadmin", "www"]);
+        assert_eq!(tl.format_store(), input);
+    }
+
+    #[test]
+    fn test_get_tag_totals() {
+        assert!(Timelog::get_tag_totals(&[], &Config::default()).is_empty());
+
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 08:00: arrived
+2022-06-10 09:00: fix server -- sysadmin www
+2022-06-10 09:30: ** tea -- www
+2022-06-10 10:00: backup -- sysadmin
+2022-06-10 11:00: code
+",
+        );
+        let totals = Timelog::get_tag_totals(&tl.entries, &Config::default());
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals["sysadmin"], Duration::minutes(90));
+        // slack does not count
+        assert_eq!(totals["www"], Duration::minutes(60));
+
+        // configured slack too
+        let config = Config {
+            slack_prefix: "backup".to_string(),
+            ..Config::default()
+        };
+        let totals = Timelog::get_tag_totals(&tl.entries, &config);
+        assert_eq!(totals["sysadmin"], Duration::minutes(60));
+    }
+
+    #[test]
+    fn test_normalize_task() {
+        assert_eq!(normalize_task("code"), "code");
+        assert_eq!(normalize_task("project1:  code"), "project1: code");
+        assert_eq!(normalize_task("fix   the  bug "), "fix the bug");
+    }
+
+    #[test]
+    fn test_into_iterator() {
+        let tl = Timelog::new_from_string(TWO_DAYS);
+        let mut tasks = Vec::new();
+        for entry in &tl {
+            tasks.push(entry.task.as_str());
+        }
+        assert_eq!(tasks.len(), 10);
+        assert_eq!(tasks[0], "arrived");
+        assert_eq!(tasks[9], "customer joe: support");
+
+        assert_eq!((&Timelog::new_from_string("")).into_iter().next(), None);
+    }
+
+    #[test]
+    fn test_add_at() {
+        let mut tl = Timelog::new_from_string(TWO_DAYS);
+        let at = |d, h, m| {
+            NaiveDate::from_ymd_opt(2022, 6, d)
+                .unwrap()
+                .and_hms_opt(h, m, 0)
+                .unwrap()
+        };
+
+        // after the last entry
+        let entry = tl.add_at("late".to_string(), at(10, 17, 0)).unwrap();
+        assert_eq!(entry.to_string(), "2022-06-10 17:00: late");
+        assert_eq!(tl.entries.last().unwrap().task, "late");
+
+        // at the end of an earlier day, before the entries of the next day
+        tl.add_at("overtime".to_string(), at(9, 20, 0)).unwrap();
+        let day = tl.get_day(&NaiveDate::from_ymd_opt(2022, 6, 9).unwrap());
+        assert_eq!(day.last().unwrap().task, "overtime");
+        assert_eq!(
+            tl.get_day(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap())[0].task,
+            "arrived"
+        );
+
+        // before the last entry of a day
+        assert_eq!(
+            tl.add_at("too early".to_string(), at(10, 16, 30)),
+            Err("16:30 is before the last entry of that day at 17:00".to_string())
+        );
+        assert_eq!(tl.entries.len(), 12);
+
+        // into an empty log
+        let mut tl = Timelog::new_from_string("");
+        assert!(tl.add_at("arrived".to_string(), at(10, 8, 0)).is_ok());
+
+        clock::set_now(Some(at(10, 12, 0)));
+        assert_eq!(
+            tl.add_at("later".to_string(), at(10, 12, 1)),
+            Err("12:01 is in the future".to_string())
+        );
+        clock::set_now(None);
+    }
+
+    #[test]
+    fn test_same_minute_order() {
+        let day = NaiveDate::from_ymd_opt(2022, 6, 10).unwrap();
+        clock::set_now(Some(day.and_hms_opt(10, 0, 40).unwrap()));
+        let mut tl = Timelog::new_from_string("2022-06-10 09:00: arrived\n");
+        tl.add("first".to_string());
+        // in the same minute, but at an earlier second than "first"
+        clock::set_now(Some(day.and_hms_opt(10, 0, 50).unwrap()));
+        let second = tl
+            .add_at("second".to_string(), day.and_hms_opt(10, 0, 0).unwrap())
+            .unwrap();
+        assert_eq!(second.stop, day.and_hms_opt(10, 0, 40).unwrap());
+        let tasks =
+            |tl: &Timelog| -> Vec<String> { tl.entries.iter().map(|e| e.task.clone()).collect() };
+        assert_eq!(tasks(&tl), vec!["arrived", "first", "second"]);
+
+        // same order after a reload
+        let reloaded = Timelog::new_from_string(&tl.format_store());
+        assert_eq!(tasks(&reloaded), tasks(&tl));
+
+        // and after merging, existing entries stay first
+        tl.merge(Timelog::new_from_string("2022-06-10 10:00: other\n"));
+        assert_eq!(tasks(&tl), vec!["arrived", "first", "second", "other"]);
+        clock::set_now(None);
+    }
+
+    #[test]
+    fn test_add_minutes_ago() {
+        let now = NaiveDate::from_ymd_opt(2022, 6, 10)
+            .unwrap()
+            .and_hms_opt(16, 30, 20)
+            .unwrap();
+        clock::set_now(Some(now));
+        let mut tl = Timelog::new_from_string(TWO_DAYS);
+
+        let entry = tl.add_minutes_ago("coffee".to_string(), 15).unwrap();
+        assert_eq!(entry.stop, now - Duration::minutes(15));
+        assert_eq!(entry.to_string(), "2022-06-10 16:15: coffee");
+        assert_eq!(tl.add_minutes_ago("now".to_string(), 0).unwrap().stop, now);
+
+        // the last entry of the day is at 16:00, and now also at 16:30
+        assert_eq!(
+            tl.add_minutes_ago("too early".to_string(), 31),
+            Err("15:59 is before the last entry of that day at 16:30".to_string())
+        );
+        assert_eq!(tl.entries.len(), 12);
+        clock::set_now(None);
+    }
+
+    #[test]
+    fn test_remove_last() {
+        let mut tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 08:00: project1: code
+2022-06-10 09:00: email
+",
+        );
+        let removed = tl.remove_last().unwrap();
+        assert_eq!(removed.task, "email");
+        assert_eq!(removed.stop.to_string(), "2022-06-10 09:00:00");
+        assert_eq!(tl.entries.len(), 2);
+
+        // several in a row
+        assert_eq!(tl.remove_last().unwrap().task, "project1: code");
+        assert_eq!(tl.remove_last().unwrap().task, "arrived");
+        assert!(tl.entries.is_empty());
+
+        // nothing left
+        assert_eq!(tl.remove_last(), None);
+        assert_eq!(Timelog::new_from_string("").remove_last(), None);
+
+        // the file only changes with save()
+        let mut path = env::temp_dir();
+        path.push(format!("rtimelog-test-remove-{}.txt", std::process::id()));
+        fs::write(&path, TWO_DAYS).unwrap();
+        let mut tl = Timelog::new_from_file(&path).unwrap();
+        assert_eq!(tl.remove_last().unwrap().task, "customer joe: support");
+        assert_eq!(fs::read_to_string(&path).unwrap(), TWO_DAYS);
+        tl.save().unwrap();
+        assert!(!fs::read_to_string(&path)
+            .unwrap()
+            .contains("customer joe: support"));
+        fs::remove_file(&path).unwrap();
+
+        // base entries are skipped, even when they are later
+        let base = Timelog::new_from_string("2022-06-10 08:00: base\n2022-06-10 10:00: base\n");
+        let mut tl = Timelog::new_from_string("2022-06-10 07:00: arrived\n2022-06-10 09:00: own\n")
+            .with_base(base);
+        assert_eq!(tl.remove_last().unwrap().task, "own");
+        assert_eq!(tl.remove_last().unwrap().task, "arrived");
+        assert_eq!(tl.remove_last(), None);
+        assert_eq!(tl.entries.len(), 2);
+    }
+
+    #[test]
+    fn test_normalize_tasks() {
+        let mut tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 08:00: project1:  code
+2022-06-10 09:00: project1: code
+",
+        );
+        // original text is preserved
+        assert_eq!(tl.entries[1].task, "project1:  code");
+
+        assert_eq!(tl.normalize_tasks(), 1);
+        assert_eq!(tl.entries[1].task, "project1: code");
+        assert_eq!(tl.normalize_tasks(), 0);
+    }
+
+    #[test]
+    fn test_rename_task() {
+        let mut tl = Timelog::new_from_string(TWO_WEEKS);
+        let before: Vec<NaiveDateTime> = tl.entries.iter().map(|e| e.stop).collect();
+
+        assert_eq!(tl.rename_task("workw2", "project-x"), 3);
+        assert_eq!(tl.rename_task("workw2", "project-x"), 0);
+        // exact match only
+        assert_eq!(tl.rename_task("workw", "project-x"), 0);
+        assert_eq!(tl.rename_task("workw1", "project-x"), 2);
+        assert_eq!(
+            tl.entries.iter().map(|e| e.stop).collect::<Vec<_>>(),
+            before
+        );
+
+        let activities = activity::Activities::new_from_entries(&tl.entries);
+        let names: Vec<&str> = activities.activities().iter().map(|a| a.name()).collect();
+        assert_eq!(names, ["project-x", "** tea"]);
+        assert_eq!(activities.activities()[0].duration(), Duration::hours(5));
+        assert!(tl
+            .format_store()
+            .contains("2022-06-10 06:00: arrived\n2022-06-10 07:00: project-x\n"));
+
+        assert_eq!(tl.rename_task_substring("-x", ": x"), 5);
+        assert_eq!(tl.rename_task_substring("tea", "coffee"), 3);
+        assert_eq!(tl.entries[2].task, "** coffee");
+        assert_eq!(tl.entries[1].task, "project: x");
+        assert_eq!(tl.rename_task_substring("nothing", "else"), 0);
+    }
+
+    #[test]
+    fn test_rename_task_base() {
+        let base = Timelog::new_from_string("2022-06-10 07:00: workw2\n");
+        let mut tl = Timelog::new_from_string("2022-06-10 08:00: workw2\n").with_base(base);
+        assert_eq!(tl.rename_task("workw2", "project-x"), 1);
+        assert_eq!(tl.format_store(), "2022-06-10 08:00: project-x\n");
     }
 
     #[test]
@@ -347,16 +1448,251 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_bom() {
+        let raw = format!("\u{feff}{}", TWO_DAYS.trim_start());
+        let entries = Timelog::parse(&raw);
+        assert_eq!(entries.len(), 10);
+        assert_eq!(&format!("{}", entries[0]), "2022-06-09 06:02: arrived");
+        assert_eq!(Timelog::lint(&raw), vec![]);
+        // reformatting drops the BOM
+        assert_eq!(
+            Timelog::reformat(&raw),
+            Ok(Some(TWO_DAYS.trim_start().to_string()))
+        );
+    }
+
+    const OUT_OF_ORDER: &str = "
+2022-06-09 06:02: arrived
+2022-06-09 06:10: ** tea
+2022-06-08 07:32: huh, previous day
+";
+
     #[test]
     #[should_panic]
     fn test_parse_out_of_order() {
-        Timelog::parse(
-            "
-2022-06-09 06:02: arrived
+        Timelog::parse(OUT_OF_ORDER);
+    }
+
+    #[test]
+    fn test_try_parse_out_of_order() {
+        let e = Timelog::try_parse(OUT_OF_ORDER).unwrap_err();
+        assert_eq!(
+            e,
+            ParseError::OutOfOrder {
+                line_no: 4,
+                line: "2022-06-08 07:32: huh, previous day".to_string()
+            }
+        );
+        assert_eq!(
+            e.to_string(),
+            "line 4 goes back in time: 2022-06-08 07:32: huh, previous day"
+        );
+        assert_eq!(Timelog::try_parse(TWO_DAYS).unwrap().len(), 10);
+
+        // surfaced when reading a file, instead of aborting
+        let mut path = env::temp_dir();
+        path.push(format!(
+            "rtimelog-test-out-of-order-{}.txt",
+            std::process::id()
+        ));
+        fs::write(&path, OUT_OF_ORDER).unwrap();
+        let e = Timelog::new_from_file(&path).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            format!(
+                "Cannot read {}: line 4 goes back in time: 2022-06-08 07:32: huh, previous day; please fix the file",
+                path.display()
+            )
+        );
+        // a broken base log gets ignored
+        let tl = Timelog::new_from_string(TWO_DAYS).with_base_file(Some(&path));
+        assert_eq!(tl.entries.len(), 10);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_lint_banner() {
+        assert_eq!(Timelog::lint_banner(""), None);
+        assert_eq!(Timelog::lint_banner(TWO_DAYS), None);
+        assert_eq!(
+            Timelog::lint_banner("2022-06-09 06:02: arrived\n2022-06-09 13:00 no colon\n"),
+            Some("⚠ 1 issue found; run rtimelog-cmd check".to_string())
+        );
+        assert_eq!(
+            Timelog::lint_banner(
+                "2022-06-09 06:02: arrived\n2022-06-08 07:00: previous day\n2022-06-09 25:00: bad\n"
+            ),
+            Some("⚠ 2 issues found; run rtimelog-cmd check".to_string())
+        );
+    }
+
+    #[test]
+    fn test_reformat() {
+        assert_eq!(Timelog::reformat(""), Ok(None));
+        assert_eq!(Timelog::reformat(TWO_DAYS.trim_start()), Ok(None));
+
+        // missing and excess separators
+        assert_eq!(
+            Timelog::reformat(
+                "2022-06-09 06:02: arrived
+2022-06-09 06:10: ** tea
+
+2022-06-09 07:00: same day
+2022-06-10 07:00: next day
+
+
+2022-06-11 07:00: day after
+"
+            ),
+            Ok(Some(
+                "2022-06-09 06:02: arrived
+2022-06-09 06:10: ** tea
+2022-06-09 07:00: same day
+
+2022-06-10 07:00: next day
+
+2022-06-11 07:00: day after
+"
+                .to_string()
+            ))
+        );
+
+        // would lose the invalid line
+        let e = Timelog::reformat("2022-06-09 06:02: arrived\nhello\n2022-06-10 07:00: next\n")
+            .unwrap_err();
+        assert_eq!(e.len(), 1);
+        assert_eq!(e[0].to_string(), "line 2: invalid line: hello");
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        // canonical
+        assert_eq!(Timelog::roundtrip(""), Ok("".to_string()));
+        let raw = TWO_DAYS.trim_start();
+        assert_eq!(Timelog::roundtrip(raw).as_deref(), Ok(raw));
+
+        // invalid line, missing separator, and missing newline
+        let raw = "2022-06-09 06:02: arrived
+hello
+2022-06-09 06:10: ** tea
+2022-06-10 07:00: next day";
+        let saved = Timelog::roundtrip(raw).unwrap();
+        assert_eq!(
+            saved,
+            "2022-06-09 06:02: arrived
+2022-06-09 06:10: ** tea
+
+2022-06-10 07:00: next day
+"
+        );
+        assert_eq!(
+            diff_lines(raw, &saved),
+            "@@ line 2 @@
+-hello
+@@ line 4 @@
+-2022-06-10 07:00: next day (no newline at end)
++
++2022-06-10 07:00: next day
+"
+        );
+
+        // can't be read
+        let e =
+            Timelog::roundtrip("2022-06-09 06:02: arrived\n2022-06-08 07:00: back\n").unwrap_err();
+        assert_eq!(e.len(), 1);
+        assert_eq!(e[0].kind, ProblemKind::OutOfOrder);
+    }
+
+    #[test]
+    fn test_repair_truncated() {
+        assert_eq!(Timelog::repair_truncated(""), Ok(None));
+        assert_eq!(Timelog::repair_truncated(TWO_DAYS), Ok(None));
+
+        let (repaired, problem) = Timelog::repair_truncated(
+            "2022-06-09 06:02: arrived
+2022-06-09 06:10: ** tea
+
+2022-06-10 07:00: next day
+2022-06-10 16:0",
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            repaired,
+            "2022-06-09 06:02: arrived
+2022-06-09 06:10: ** tea
+
+2022-06-10 07:00: next day
+"
+        );
+        assert_eq!(problem.to_string(), "line 5: invalid line: 2022-06-10 16:0");
+        assert_eq!(Timelog::lint(&repaired), vec![]);
+
+        // truncated in the time, with trailing empty lines
+        let (repaired, problem) =
+            Timelog::repair_truncated("2022-06-10 07:00: arrived\n2022-06-10 25:00: cod\n\n")
+                .unwrap()
+                .unwrap();
+        assert_eq!(repaired, "2022-06-10 07:00: arrived\n");
+        assert_eq!(problem.kind, ProblemKind::InvalidDate);
+
+        // only a single broken line
+        let (repaired, _) = Timelog::repair_truncated("2022-06-1").unwrap().unwrap();
+        assert_eq!(repaired, "");
+
+        // other broken lines are not touched
+        let e = Timelog::repair_truncated(
+            "2022-06-10 07:00: arrived\nhello\n2022-06-10 08:00: code\n2022-06-10 16:0\n",
+        )
+        .unwrap_err();
+        assert_eq!(e.len(), 2);
+        assert_eq!(e[0].to_string(), "line 2: invalid line: hello");
+        let e = Timelog::repair_truncated("hello\n2022-06-10 08:00: code\n").unwrap_err();
+        assert_eq!(e.len(), 1);
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        assert_eq!(diff_lines("", ""), "");
+        assert_eq!(diff_lines("a\nb\n", "a\nb\n"), "");
+        assert_eq!(diff_lines("a\n", ""), "@@ line 1 @@\n-a\n");
+        assert_eq!(diff_lines("a\nc\n", "a\nb\nc\n"), "@@ line 2 @@\n+b\n");
+        assert_eq!(
+            diff_lines("a\nb\nc\nd\n", "a\nx\nc\n"),
+            "@@ line 2 @@\n-b\n+x\n@@ line 4 @@\n-d\n"
+        );
+    }
+
+    #[test]
+    fn test_lint() {
+        assert_eq!(Timelog::lint(""), vec![]);
+        assert_eq!(Timelog::lint(TWO_DAYS), vec![]);
+
+        let problems = Timelog::lint(
+            "2022-06-09 06:02: arrived
 2022-06-09 06:10: ** tea
 2022-06-08 07:32: huh, previous day
+2022-06-09 06:10: tea again
+
+2022-06-09 25:00: bad time
+2022-06-09 13:00 no colon
+2022-06-09 14:00: fine
 ",
         );
+        assert_eq!(problems.len(), 4);
+        assert_eq!(
+            problems
+                .iter()
+                .map(|p| format!("{p}"))
+                .collect::<Vec<String>>(),
+            vec![
+                "line 3: goes back in time: 2022-06-08 07:32: huh, previous day",
+                "line 4: same time as previous entry: 2022-06-09 06:10: tea again",
+                "line 6: invalid date: 2022-06-09 25:00: bad time",
+                "line 7: invalid line: 2022-06-09 13:00 no colon",
+            ]
+        );
     }
 
     #[test]
@@ -364,7 +1700,7 @@ mod tests {
         let tl = Timelog::new_from_string("");
         assert_eq!(tl.get_all().next(), None);
 
-        let tl = Timelog::new_from_file(&PathBuf::from("/nonexisting"));
+        let tl = Timelog::new_from_file(&PathBuf::from("/nonexisting")).unwrap();
         assert_eq!(tl.get_all().next(), None);
 
         let tl = Timelog::new_from_string(TWO_DAYS);
@@ -417,6 +1753,18 @@ mod tests {
         assert_eq!(entries2.len(), 10);
         assert_eq!(entries2[4..], entries[..]);
         assert_eq!(&format!("{}", entries2[1]), "2022-06-09 06:27: email");
+
+        // three days, with gaps and across weeks
+        let tl = Timelog::new_from_string(TWO_WEEKS);
+        let day = |d| NaiveDate::from_ymd_opt(2022, 6, d).unwrap();
+        let entries = tl.get_n_days(&day(10), 3);
+        assert_eq!(entries.len(), 7);
+        assert_eq!(&format!("{}", entries[0]), "2022-06-08 06:00: arrived");
+        assert_eq!(&format!("{}", entries[6]), "2022-06-10 07:00: workw2");
+        assert_eq!(tl.get_n_days(&day(9), 3).len(), 5);
+        // from Friday of the first week to Sunday
+        assert_eq!(tl.get_n_days(&day(5), 3), &tl.entries[3..6]);
+        assert_eq!(tl.get_n_days(&day(7), 3), &[]);
     }
 
     #[test]
@@ -458,6 +1806,341 @@ mod tests {
         assert_eq!(tl.format_store(), TWO_DAYS.trim_start());
     }
 
+    #[test]
+    fn test_split_days() {
+        assert_eq!(Timelog::split_days(&[]).count(), 0);
+
+        let tl = Timelog::new_from_string(TWO_DAYS);
+        let days: Vec<&[Entry]> = Timelog::split_days(&tl.entries).collect();
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].len(), 4);
+        assert_eq!(days[1].len(), 6);
+        assert_eq!(&format!("{}", days[1][0]), "2022-06-10 07:00: arrived");
+    }
+
+    #[test]
+    fn test_new_from_reader() {
+        let tl = Timelog::new_from_reader(TWO_DAYS.as_bytes()).unwrap();
+        assert_eq!(tl.filename, None);
+        assert_eq!(
+            tl.get_all().collect::<Vec<_>>(),
+            Timelog::new_from_string(TWO_DAYS)
+                .get_all()
+                .collect::<Vec<_>>()
+        );
+
+        let tl = Timelog::new_from_reader(io::empty()).unwrap();
+        assert_eq!(tl.get_all().count(), 0);
+
+        assert!(matches!(
+            Timelog::new_from_reader(&[0xff, 0xfe][..]),
+            Err(StoreError::ReadInput(_))
+        ));
+
+        let e = Timelog::new_from_reader(OUT_OF_ORDER.as_bytes()).unwrap_err();
+        assert!(matches!(
+            e,
+            StoreError::ParseInput(ParseError::OutOfOrder { line_no: 4, .. })
+        ));
+        assert!(e
+            .to_string()
+            .starts_with("Cannot read the timelog: line 4 goes back in time"));
+    }
+
+    #[test]
+    fn test_entry_diff() {
+        let before = Timelog::new_from_string(TWO_DAYS);
+        let entries: Vec<Entry> = before.get_all().cloned().collect();
+
+        let diff = EntryDiff::new(&entries, &entries);
+        assert!(diff.is_empty());
+        assert_eq!(format!("{diff}"), "no changes");
+        assert_eq!(EntryDiff::new(&[], &[]), EntryDiff::default());
+
+        let mut after = entries.clone();
+        after.push(Entry {
+            stop: after[0].stop + Duration::minutes(1),
+            task: "new".to_string(),
+        });
+        after.push(Entry {
+            stop: after[0].stop + Duration::minutes(2),
+            task: "new".to_string(),
+        });
+        after[3].task = "changed".to_string();
+        after.remove(1);
+        let diff = EntryDiff::new(&entries, &after);
+        assert_eq!(
+            diff,
+            EntryDiff {
+                added: 2,
+                removed: 1,
+                modified: 1
+            }
+        );
+        assert_eq!(format!("{diff}"), "+2 entries, -1 entries, 1 modified");
+
+        // accidentally deleted everything
+        let diff = EntryDiff::new(&entries, &[]);
+        assert_eq!(diff.removed, entries.len());
+        assert_eq!(format!("{diff}"), format!("-{} entries", entries.len()));
+
+        // duplicates are counted
+        let diff = EntryDiff::new(&entries[..1], &[entries[0].clone(), entries[0].clone()]);
+        assert_eq!(format!("{diff}"), "+1 entries");
+
+        assert_eq!(
+            before.diff(&Timelog::new_from_string(TWO_WEEKS)),
+            EntryDiff::new(
+                &entries,
+                &Timelog::new_from_string(TWO_WEEKS)
+                    .get_all()
+                    .cloned()
+                    .collect::<Vec<_>>()
+            )
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut tl = Timelog::new_from_string(
+            "
+2022-06-10 07:00: arrived
+2022-06-10 08:00: code
+2022-06-10 09:00: email
+",
+        );
+        let other = Timelog::new_from_string(
+            "
+2022-06-09 07:00: arrived
+2022-06-09 15:00: laptop work
+2022-06-10 07:00: arrived
+2022-06-10 08:00: code
+2022-06-10 08:30: call
+2022-06-10 09:00: review
+",
+        );
+        assert_eq!(tl.merge(other), 4);
+        assert_eq!(
+            tl.format_store(),
+            "2022-06-09 07:00: arrived
+2022-06-09 15:00: laptop work
+
+2022-06-10 07:00: arrived
+2022-06-10 08:00: code
+2022-06-10 08:30: call
+2022-06-10 09:00: email
+2022-06-10 09:00: review
+"
+        );
+
+        // merging again is a no-op
+        let again = Timelog::new_from_string(TWO_DAYS);
+        let mut tl2 = Timelog::new_from_string(TWO_DAYS);
+        assert_eq!(tl2.merge(again), 0);
+        assert_eq!(
+            tl2.format_store(),
+            Timelog::new_from_string(TWO_DAYS).format_store()
+        );
+
+        assert_eq!(tl2.merge(Timelog::default()), 0);
+    }
+
+    #[test]
+    fn test_duration_hint() {
+        let entry = |task: &str| Entry {
+            task: task.to_string(),
+            ..Entry::default()
+        };
+
+        let e = entry("code [~45m]");
+        assert_eq!(e.name(), "code");
+        assert_eq!(e.duration_hint(), Some(Duration::minutes(45)));
+        assert!(!e.is_continuation());
+
+        let e = entry("meeting (cont.) [~2h]");
+        assert_eq!(e.name(), "meeting");
+        assert_eq!(e.duration_hint(), Some(Duration::hours(2)));
+        assert!(e.is_continuation());
+
+        for task in [
+            "code",
+            "code [45m]",
+            "code [~m]",
+            "code [~5x]",
+            "code [~45m] x",
+        ] {
+            assert_eq!(entry(task).duration_hint(), None, "{task}");
+            assert_eq!(entry(task).name(), task);
+        }
+    }
+
+    #[test]
+    fn test_save_error() {
+        let e = Timelog::save_error(
+            Path::new("/ro/timelog.txt"),
+            io::Error::from(io::ErrorKind::PermissionDenied),
+        );
+        assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+        assert_eq!(
+            e.to_string(),
+            "cannot write to /ro/timelog.txt: permission denied; set RTIMELOG_FILE to a writable file"
+        );
+
+        // parent "directory" is a file; this can't be tested with permissions, as tests may run as root
+        let mut parent = env::temp_dir();
+        parent.push(format!("rtimelog-test-save-{}", std::process::id()));
+        fs::write(&parent, "").unwrap();
+        let mut tl = Timelog::new_from_string(TWO_DAYS);
+        tl.filename = Some(parent.join("timelog.txt"));
+        let e = tl.save().unwrap_err();
+        assert!(e.to_string().starts_with(&format!(
+            "cannot write to {}/timelog.txt: ",
+            parent.display()
+        )));
+        fs::remove_file(parent).unwrap();
+    }
+
+    #[test]
+    fn test_find_default_file() {
+        let mut home = env::temp_dir();
+        home.push(format!("rtimelog-test-home-{}", std::process::id()));
+        let data_home = home.join("data");
+        fs::create_dir_all(&data_home).unwrap();
+
+        assert_eq!(
+            Timelog::find_default_file(Some(home.clone()), Some(data_home.clone())),
+            Ok(data_home.join("gtimelog/timelog.txt"))
+        );
+        // no home directory, e.g. in a container
+        assert_eq!(
+            Timelog::find_default_file(None, Some(data_home.clone())),
+            Ok(data_home.join("gtimelog/timelog.txt"))
+        );
+        assert_eq!(
+            Timelog::find_default_file(None, None),
+            Err(StoreError::NoDataDir)
+        );
+
+        // gtimelog's legacy directory takes precedence
+        fs::create_dir(home.join(".gtimelog")).unwrap();
+        assert_eq!(
+            Timelog::find_default_file(Some(home.clone()), Some(data_home)),
+            Ok(home.join(".gtimelog/timelog.txt"))
+        );
+        assert_eq!(
+            Timelog::find_default_file(Some(home.clone()), None),
+            Ok(home.join(".gtimelog/timelog.txt"))
+        );
+
+        fs::remove_dir_all(&home).unwrap();
+    }
+
+    #[test]
+    fn test_changed_externally() {
+        let mut path = env::temp_dir();
+        path.push(format!("rtimelog-test-external-{}.txt", std::process::id()));
+        fs::write(&path, TWO_DAYS).unwrap();
+
+        let mut tl = Timelog::new_from_file(&path).unwrap();
+        assert!(!tl.changed_externally());
+        tl.add("first".to_string());
+        tl.save().unwrap();
+        assert!(!tl.changed_externally());
+
+        // another program changes the file; set the mtime explicitly, as the file system
+        // time stamp granularity may be too coarse to see the change
+        let f = File::options().append(true).open(&path).unwrap();
+        writeln!(&f, "2030-01-01 08:00: external").unwrap();
+        f.set_modified(SystemTime::now() + std::time::Duration::from_secs(10))
+            .unwrap();
+        drop(f);
+        assert!(tl.changed_externally());
+
+        tl.add("second".to_string());
+        let e = tl.save().unwrap_err();
+        assert!(e
+            .to_string()
+            .ends_with("was changed by another program, not overwriting it"));
+        assert!(fs::read_to_string(&path).unwrap().contains("external"));
+
+        // reload and apply the change again
+        let mut tl = Timelog::new_from_file(&path).unwrap();
+        assert_eq!(tl.entries.last().unwrap().task, "external");
+        tl.add("second".to_string());
+        tl.save().unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("external"));
+        assert!(contents.contains("first"));
+
+        fs::remove_file(&path).unwrap();
+        assert!(tl.changed_externally());
+        assert!(!Timelog::new_from_string("").changed_externally());
+    }
+
+    #[test]
+    fn test_with_base() {
+        let mut path = env::temp_dir();
+        path.push(format!("rtimelog-test-base-{}.txt", std::process::id()));
+        fs::write(&path, TWO_DAYS).unwrap();
+        let base = Timelog::new_from_string(
+            "
+2022-06-09 00:00: holiday: team day
+2022-06-10 09:30: meeting: all hands
+2022-06-10 12:05: rtimelog: code
+",
+        );
+
+        let mut tl = Timelog::new_from_file(&path).unwrap().with_base(base);
+        // queries see both logs; the duplicate of an own entry is skipped
+        let day = tl.get_day(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap());
+        assert_eq!(day.len(), 7);
+        assert_eq!(day[1].task, "meeting: all hands");
+        assert_eq!(tl.entries[0].task, "holiday: team day");
+
+        // saving only writes the own entries
+        tl.add("new".to_string());
+        tl.save().unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains("holiday"));
+        assert!(!contents.contains("all hands"));
+        assert!(contents.contains("rtimelog: code"));
+        assert!(contents.ends_with(": new\n"));
+        assert_eq!(
+            Timelog::new_from_file(&path).unwrap().entries.len(),
+            TWO_DAYS.trim().lines().filter(|l| !l.is_empty()).count() + 1
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_with_future_base() {
+        let base = Timelog::new_from_string("2022-12-24 00:00: holiday: christmas\n");
+        let mut tl = Timelog::new_from_string(
+            "2022-06-10 07:00: arrived
+2022-06-10 09:00: code
+",
+        )
+        .with_base(base);
+        let today = NaiveDate::from_ymd_opt(2022, 6, 10).unwrap();
+        assert_eq!(tl.get_last_task(None), Some("code"));
+
+        clock::set_now(Some(today.and_hms_opt(10, 0, 0).unwrap()));
+        assert_eq!(tl.add("email".to_string()).task, "email");
+        let day = tl.get_day(&today);
+        assert_eq!(day.len(), 3);
+        assert_eq!(day[2].task, "email");
+        assert_eq!(tl.entries.last().unwrap().task, "holiday: christmas");
+        assert_eq!(tl.get_last_task(None), Some("email"));
+        assert_eq!(tl.get_last_task(Some(&today)), Some("email"));
+        assert_eq!(
+            tl.get_last_task(Some(&NaiveDate::from_ymd_opt(2022, 12, 24).unwrap())),
+            None
+        );
+        assert!(tl.format_store().ends_with("2022-06-10 10:00: email\n"));
+        clock::set_now(None);
+    }
+
     #[test]
     fn test_get_history() {
         let tl = Timelog::new_from_string("");
@@ -481,11 +2164,425 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_get_last_task() {
+        let tl = Timelog::new_from_string("");
+        assert_eq!(tl.get_last_task(None), None);
+
+        let tl = Timelog::new_from_string(TWO_DAYS);
+        assert_eq!(tl.get_last_task(None), Some("customer joe: support"));
+        assert_eq!(
+            tl.get_last_task(Some(&NaiveDate::from_ymd_opt(2022, 6, 9).unwrap())),
+            Some("work")
+        );
+        assert_eq!(
+            tl.get_last_task(Some(&NaiveDate::from_ymd_opt(2022, 6, 8).unwrap())),
+            None
+        );
+    }
+
     #[test]
     fn test_add() {
         let mut tl = Timelog::new_from_string("");
         tl.add("think hard".to_string());
         assert_eq!(tl.entries.len(), 1);
         assert_eq!(tl.entries[0].task, "think hard");
+
+        let now = NaiveDate::from_ymd_opt(2022, 6, 10)
+            .unwrap()
+            .and_hms_opt(14, 30, 12)
+            .unwrap();
+        clock::set_now(Some(now));
+        tl.add("write tests".to_string());
+        // entries stay sorted by time
+        assert_eq!(tl.entries[0].stop, now);
+        assert_eq!(tl.entries[0].task, "write tests");
+        clock::set_now(None);
+    }
+
+    #[test]
+    fn test_entry_at() {
+        let tl = Timelog::new_from_string(TWO_DAYS);
+        let at = |d, h, m| {
+            tl.entry_at(
+                NaiveDate::from_ymd_opt(2022, 6, d)
+                    .unwrap()
+                    .and_hms_opt(h, m, 0)
+                    .unwrap(),
+            )
+            .map(|e| e.task.as_str())
+        };
+
+        // mid-block
+        assert_eq!(at(9, 6, 10), Some("email"));
+        assert_eq!(at(9, 6, 30), Some("**tea"));
+        assert_eq!(at(10, 13, 0), Some("rtimelog: code"));
+        // blocks end inclusively on their entry's stop
+        assert_eq!(at(9, 6, 2), None);
+        assert_eq!(at(9, 6, 3), Some("email"));
+        assert_eq!(at(9, 6, 27), Some("email"));
+        assert_eq!(at(9, 6, 28), Some("**tea"));
+        assert_eq!(at(10, 16, 0), Some("customer joe: support"));
+        // before the first entry of a day, after the last one, and between days
+        assert_eq!(at(9, 5, 0), None);
+        assert_eq!(at(9, 23, 0), None);
+        assert_eq!(at(10, 6, 0), None);
+        assert_eq!(at(10, 16, 1), None);
+        assert_eq!(at(11, 8, 0), None);
+
+        assert_eq!(
+            Timelog::new_from_string("").entry_at(NaiveDateTime::MIN),
+            None
+        );
+    }
+
+    #[test]
+    fn test_get_month() {
+        let tl = Timelog::new_from_string(
+            "2022-05-31 23:59: end of may
+2022-06-01 08:00: arrived
+2022-06-01 09:00: first
+2022-06-15 08:00: arrived
+2022-06-15 09:00: middle
+2022-06-30 08:00: arrived
+2022-06-30 23:59: last
+2022-07-01 00:00: begin of july
+",
+        );
+        let tasks = |entries: &[Entry]| entries.iter().map(|e| e.task.clone()).collect::<Vec<_>>();
+        let day = |m, d| NaiveDate::from_ymd_opt(2022, m, d).unwrap();
+
+        // June 2022 spans ISO weeks 22 to 26
+        let june = tl.get_month(&day(6, 15));
+        assert_eq!(june.len(), 6);
+        assert_eq!(june[0].task, "arrived");
+        assert_eq!(june[5].task, "last");
+        assert_eq!(tl.get_month(&day(6, 1)), june);
+        assert_eq!(tl.get_month(&day(6, 30)), june);
+
+        assert_eq!(tasks(tl.get_month(&day(5, 2))), vec!["end of may"]);
+        assert_eq!(tasks(tl.get_month(&day(7, 31))), vec!["begin of july"]);
+        assert_eq!(tl.get_month(&day(8, 1)), &[]);
+
+        // previous months
+        assert_eq!(tl.get_n_months(&day(6, 15), 2).len(), 7);
+        assert_eq!(tl.get_n_months(&day(7, 1), 2), &tl.entries[1..]);
+        assert_eq!(tl.get_n_months(&day(7, 1), 3), &tl.entries[..]);
+    }
+
+    #[test]
+    fn test_get_n_zero() {
+        // entries right at the boundaries of the periods
+        let tl = Timelog::new_from_string(
+            "2022-06-05 23:59: sunday
+2022-06-06 00:00: monday midnight
+2022-06-30 23:59: end of june
+2022-07-01 00:00: begin of july
+",
+        );
+        let day = |m, d| NaiveDate::from_ymd_opt(2022, m, d).unwrap();
+        for d in [day(6, 5), day(6, 6), day(6, 30), day(7, 1)] {
+            assert_eq!(tl.get_n_days(&d, 0), &[], "{d}");
+            assert_eq!(tl.get_n_weeks(&d, 0), &[], "{d}");
+            assert_eq!(tl.get_n_months(&d, 0), &[], "{d}");
+            assert!(!tl.get_n_days(&d, 1).is_empty(), "{d}");
+        }
+    }
+
+    #[test]
+    fn test_get_period() {
+        let tl = Timelog::new_from_string(
+            "2022-06-19 23:00: before
+2022-06-20 08:00: arrived
+2022-06-20 09:00: sprint start
+2022-06-30 17:00: end of june
+2022-07-01 09:00: begin of july
+2022-07-03 23:59: last evening
+2022-07-04 00:00: next sprint
+",
+        );
+        let tasks = |entries: &[Entry]| entries.iter().map(|e| e.task.clone()).collect::<Vec<_>>();
+        let start = NaiveDate::from_ymd_opt(2022, 6, 20).unwrap();
+
+        // 14 days from Monday 2022-06-20 until Sunday 2022-07-03, across the month boundary
+        assert_eq!(
+            tasks(tl.get_period(&start, 14)),
+            vec![
+                "arrived",
+                "sprint start",
+                "end of june",
+                "begin of july",
+                "last evening"
+            ]
+        );
+        assert_eq!(
+            tasks(tl.get_period(&start, 1)),
+            vec!["arrived", "sprint start"]
+        );
+        assert_eq!(
+            tl.get_period(&start, 15).last().unwrap().task,
+            "next sprint"
+        );
+        assert_eq!(tl.get_period(&start, 0), &[]);
+        assert_eq!(
+            tl.get_period(&NaiveDate::from_ymd_opt(2022, 7, 4).unwrap(), 14)
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_get_date_range() {
+        let tl = Timelog::new_from_string(
+            "2022-06-19 23:00: before
+2022-06-20 08:00: arrived
+2022-06-20 09:00: code
+2022-06-21 23:59: late
+2022-06-22 00:00: next day
+",
+        );
+        let tasks = |entries: &[Entry]| entries.iter().map(|e| e.task.clone()).collect::<Vec<_>>();
+        let day = |d| NaiveDate::from_ymd_opt(2022, 6, d).unwrap();
+
+        assert_eq!(
+            tasks(tl.get_date_range(&day(20), &day(21))),
+            vec!["arrived", "code", "late"]
+        );
+        assert_eq!(tl.get_date_range(&day(20), &day(20)).len(), 2);
+        assert_eq!(tl.get_date_range(&day(21), &day(20)), &[]);
+        // open ranges
+        assert_eq!(
+            tl.get_date_range(&NaiveDate::MIN, &NaiveDate::MAX),
+            &tl.entries[..]
+        );
+        assert_eq!(tl.get_date_range(&day(21), &NaiveDate::MAX).len(), 2);
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let tl = Timelog::new_from_string(TWO_DAYS);
+        let day = |d| NaiveDate::from_ymd_opt(2022, 6, d).unwrap();
+        assert_eq!(
+            tl.to_csv(&day(10), &day(10)),
+            export::render_csv(tl.get_day(&day(10)))
+        );
+        assert!(tl
+            .to_csv(&day(9), &day(10))
+            .starts_with("date,start,stop,duration_minutes,task\n2022-06-09,,06:02,,arrived\n2022-06-09,06:02,06:27,25,email\n"));
+        assert_eq!(
+            tl.to_csv(&day(11), &day(30)),
+            "date,start,stop,duration_minutes,task\n"
+        );
+    }
+
+    #[test]
+    fn test_get_custom_week() {
+        let tl = Timelog::new_from_string(
+            "2022-06-08 17:00: wednesday before
+2022-06-09 08:00: thursday
+2022-06-11 10:00: saturday
+2022-06-15 23:59: wednesday
+2022-06-16 08:00: next thursday
+",
+        );
+        let tasks = |entries: &[Entry]| entries.iter().map(|e| e.task.clone()).collect::<Vec<_>>();
+        let thursday_week = vec!["thursday", "saturday", "wednesday"];
+
+        // Saturday 2022-06-11 is in the week from Thursday 06-09 to Wednesday 06-15
+        let saturday = NaiveDate::from_ymd_opt(2022, 6, 11).unwrap();
+        assert_eq!(
+            tasks(tl.get_custom_week(&saturday, Weekday::Thu)),
+            thursday_week
+        );
+        // first and last day of that week
+        for d in [9, 15] {
+            let day = NaiveDate::from_ymd_opt(2022, 6, d).unwrap();
+            assert_eq!(tasks(tl.get_custom_week(&day, Weekday::Thu)), thursday_week);
+        }
+        // Monday anchor is the usual week
+        assert_eq!(
+            tasks(tl.get_custom_week(&saturday, Weekday::Mon)),
+            vec!["wednesday before", "thursday", "saturday"]
+        );
+    }
+
+    #[test]
+    fn test_get_iso_week() {
+        let tl = Timelog::new_from_string(
+            "2020-12-27 10:00: sunday of week 52
+2020-12-28 10:00: monday of 2020 week 53
+2021-01-03 10:00: sunday of 2020 week 53
+2021-01-04 10:00: monday of 2021 week 1
+2021-01-10 10:00: sunday of 2021 week 1
+2022-01-02 10:00: sunday of 2021 week 52
+2022-01-03 10:00: monday of 2022 week 1
+",
+        );
+        let tasks = |entries: &[Entry]| entries.iter().map(|e| e.task.clone()).collect::<Vec<_>>();
+
+        // 2020 has 53 weeks, its last week ends in the next calendar year
+        assert_eq!(
+            tasks(tl.get_iso_week(2020, 53).unwrap()),
+            vec!["monday of 2020 week 53", "sunday of 2020 week 53"]
+        );
+        assert_eq!(
+            tasks(tl.get_iso_week(2021, 1).unwrap()),
+            vec!["monday of 2021 week 1", "sunday of 2021 week 1"]
+        );
+        // week 1 of 2022 starts on January 3rd, the 2nd still belongs to 2021
+        assert_eq!(
+            tasks(tl.get_iso_week(2021, 52).unwrap()),
+            vec!["sunday of 2021 week 52"]
+        );
+        assert_eq!(
+            tasks(tl.get_iso_week(2022, 1).unwrap()),
+            vec!["monday of 2022 week 1"]
+        );
+
+        // week 1 of 2020 starts on Monday 2019-12-30
+        let tl = Timelog::new_from_string(
+            "2019-12-29 10:00: last week of 2019
+2019-12-30 10:00: first week of 2020
+2020-01-05 10:00: still first week
+",
+        );
+        assert_eq!(
+            tasks(tl.get_iso_week(2020, 1).unwrap()),
+            vec!["first week of 2020", "still first week"]
+        );
+
+        // 2021 only has 52 weeks
+        assert_eq!(tl.get_iso_week(2021, 53), None);
+        assert_eq!(tl.get_iso_week(2021, 0), None);
+    }
+
+    #[test]
+    fn test_first_entry_of_day() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-09 07:00: arrived
+2022-06-09 08:00: code
+
+2022-06-10 08:30: fix the build
+2022-06-10 09:00: code
+",
+        );
+        let day = |d| NaiveDate::from_ymd_opt(2022, 6, d).unwrap();
+        assert_eq!(tl.first_entry_of_day(&day(9)).unwrap().task, "arrived");
+        assert_eq!(tl.first_entry_of_day(&day(8)), None);
+
+        // a real task first still only records the start
+        let first = tl.first_entry_of_day(&day(10)).unwrap();
+        assert_eq!(first.to_string(), "2022-06-10 08:30: fix the build");
+        let a = crate::activity::Activities::new_from_entries(tl.get_day(&day(10)));
+        assert_eq!(a.total_work(), Duration::minutes(30));
+        assert_eq!(a.activities()[0].name(), "code");
+    }
+
+    #[test]
+    fn test_virtual_midnight() {
+        let tl = Timelog::new_from_string(
+            "
+2022-06-10 20:00: arrived
+2022-06-10 23:00: code
+2022-06-11 01:30: more code
+
+2022-06-11 09:00: arrived
+2022-06-11 10:00: review
+",
+        );
+        let day = |d| NaiveDate::from_ymd_opt(2022, 6, d).unwrap();
+        let tasks = |entries: &[Entry]| entries.iter().map(|e| e.task.clone()).collect::<Vec<_>>();
+        assert_eq!(tasks(tl.get_day(&day(10))), vec!["arrived", "code"]);
+
+        clock::set_virtual_midnight(NaiveTime::from_hms_opt(4, 0, 0));
+        assert_eq!(
+            tasks(tl.get_day(&day(10))),
+            vec!["arrived", "code", "more code"]
+        );
+        assert_eq!(tasks(tl.get_day(&day(11))), vec!["arrived", "review"]);
+        assert_eq!(Timelog::split_days(&tl.entries).count(), 2);
+        let a = crate::activity::Activities::new_from_entries(tl.get_day(&day(10)));
+        assert_eq!(a.total_work(), Duration::minutes(330));
+        clock::set_virtual_midnight(None);
+
+        // like :at, shortly after the calendar midnight
+        let mut tl = Timelog::new_from_string("2022-06-10 20:00: arrived\n");
+        clock::set_virtual_midnight(NaiveTime::from_hms_opt(3, 0, 0));
+        clock::set_now(day(11).and_hms_opt(1, 0, 0));
+        let at = |h, m| clock::on_day(&clock::today(), NaiveTime::from_hms_opt(h, m, 0).unwrap());
+        tl.add_at("code".to_string(), at(23, 30)).unwrap();
+        tl.add_at("more code".to_string(), at(0, 45)).unwrap();
+        assert_eq!(
+            tl.format_store(),
+            "2022-06-10 20:00: arrived\n2022-06-10 23:30: code\n2022-06-11 00:45: more code\n"
+        );
+        assert_eq!(tl.get_day(&day(10)).len(), 3);
+        clock::set_now(None);
+        clock::set_virtual_midnight(None);
+    }
+
+    #[test]
+    fn test_get_days_ago() {
+        let tl = Timelog::new_from_string(TWO_DAYS);
+        let tasks = |entries: &[Entry]| entries.iter().map(|e| e.task.clone()).collect::<Vec<_>>();
+
+        clock::set_now(
+            NaiveDate::from_ymd_opt(2022, 6, 10)
+                .unwrap()
+                .and_hms_opt(18, 0, 0),
+        );
+        assert_eq!(
+            tl.get_days_ago(0),
+            tl.get_day(&NaiveDate::from_ymd_opt(2022, 6, 10).unwrap())
+        );
+        assert_eq!(tl.get_days_ago(0).len(), 6);
+        assert_eq!(
+            tasks(tl.get_days_ago(1)),
+            vec!["arrived", "email", "**tea", "work"]
+        );
+        assert_eq!(tl.get_days_ago(2), &[]);
+
+        // the next morning, today has no entries yet
+        clock::set_now(
+            NaiveDate::from_ymd_opt(2022, 6, 11)
+                .unwrap()
+                .and_hms_opt(7, 0, 0),
+        );
+        assert_eq!(tl.get_days_ago(0), &[]);
+        assert_eq!(tl.get_days_ago(1).len(), 6);
+        clock::set_now(None);
+    }
+
+    #[test]
+    fn test_as_string() {
+        let tl = Timelog::new_from_string("");
+        clock::set_now(
+            NaiveDate::from_ymd_opt(2022, 6, 10)
+                .unwrap()
+                .and_hms_opt(14, 30, 0),
+        );
+        assert_eq!(tl.get_today_as_string(), "Friday, 2022-06-10 (week 23)");
+        assert_eq!(tl.get_this_week_as_string(), "2022, week 23 (June 6-12)");
+
+        // week crossing the month boundary
+        clock::set_now(
+            NaiveDate::from_ymd_opt(2022, 6, 1)
+                .unwrap()
+                .and_hms_opt(9, 0, 0),
+        );
+        assert_eq!(tl.get_this_week_as_string(), "2022, week 22 (May 30-5)");
+
+        // before the virtual midnight, it's still the previous day and week
+        clock::set_virtual_midnight(NaiveTime::from_hms_opt(3, 0, 0));
+        clock::set_now(
+            NaiveDate::from_ymd_opt(2022, 6, 13)
+                .unwrap()
+                .and_hms_opt(1, 0, 0),
+        );
+        assert_eq!(tl.get_today_as_string(), "Sunday, 2022-06-12 (week 23)");
+        assert_eq!(tl.get_this_week_as_string(), "2022, week 23 (June 6-12)");
+        clock::set_virtual_midnight(None);
+        clock::set_now(None);
     }
 }
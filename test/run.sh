@@ -2,15 +2,16 @@
 set -eux
 
 # Run tests in debug mode
-cargo test
+cargo test --workspace
 
 # Build release mode
 cargo rustc --release --lib -- -Dwarnings
 cargo rustc --release --bin rtimelog -- -Dwarnings
+cargo rustc --release -p rtimelog-cmd -- -Dwarnings
 
 # Run tests in release mode
-cargo test --release
+cargo test --workspace --release
 
 # static checks
 cargo fmt --all --check
-cargo clippy --all-features -- -Dwarnings
+cargo clippy --workspace --all-features -- -Dwarnings